@@ -45,11 +45,122 @@ fn main() {
 }
 ```
 
-There is also a feature `process_options` to allow all the same code to work behind `Option` types. This feature is behind
-a feature gate since generating the code is a bit tricky and I suspect it may be too easy to break. Enable it by adding
-`features = ["process_options"]` in your `Cargo.toml`.
+Writing `#[xml_element("Name", from_xml)]` also uses the same annotations to implement `simple_xml_serialize::FromXml`,
+so annotated structs can be read back out of an `XMLElement`. Values are parsed with `FromStr`, child elements are read
+with `FromXml` and fields without annotations are filled in with `Default::default()`. Reading has to be asked for
+because it needs all of that from the field types, which types that are only ever written, like one that only
+implements `Display`, don't have.
 
-```rust,ignore
+```rust
+# use simple_xml_serialize::XMLElement;
+# use simple_xml_serialize_macro::xml_element;
+use simple_xml_serialize::FromXml;
+
+#[xml_element("Name", from_xml)]
+struct MyName {
+    #[sxs_type_text]
+    val: String,
+}
+
+let name = MyName::from_xml(&XMLElement::new("Name").text("p1")).unwrap();
+assert_eq!(name.val, "p1");
+```
+
+Enums can be annotated too. Unit variants are written as text, and enums of only unit variants also implement `Display`
+and `FromStr` so they can be used as attribute values and text. Other variants are written as a child element named
+after the variant, holding the fields of a struct variant or the element of a tuple variant's single field. Variants are
+renamed with `#[sxs_variant(rename="new_name")]`. With `tag="attribute_name"`, the variant is instead named in that
+attribute, and its fields are written into the enum's own element.
+
+```rust
+# use simple_xml_serialize::XMLElement;
+# use simple_xml_serialize_macro::xml_element;
+#[xml_element("Shape")]
+enum Shape {
+    #[sxs_variant(rename="empty")]
+    Empty,
+    Rect {
+        #[sxs_type_attr]
+        width: u32,
+    },
+}
+
+#[xml_element("Payment", tag="kind")]
+enum Payment {
+    Transfer {
+        #[sxs_type_attr]
+        iban: String,
+    },
+}
+
+assert_eq!(XMLElement::from(Shape::Empty).to_string(), "<Shape>empty</Shape>");
+assert_eq!(XMLElement::from(Shape::Rect{width: 2}).to_string(), r#"<Shape><Rect width="2"/></Shape>"#);
+assert_eq!(XMLElement::from(Payment::Transfer{iban: "DE02".to_string()}).to_string(), r#"<Payment kind="Transfer" iban="DE02"/>"#);
+```
+
+Generic structs and enums work too. The generated impls add the bounds their fields need on the type parameters, like
+`ToString` for attributes and `simple_xml_serialize::ToXMLElement` for elements.
+
+```rust
+# use simple_xml_serialize::XMLElement;
+# use simple_xml_serialize_macro::xml_element;
+#[xml_element("Pair")]
+struct Pair<'a, T> {
+    #[sxs_type_attr]
+    key: &'a str,
+    #[sxs_type_attr]
+    value: T,
+}
+
+assert_eq!(XMLElement::from(Pair{key: "x", value: 1.5}).to_string(), r#"<Pair key="x" value="1.5"/>"#);
+```
+
+Tuple structs are supported as well. A newtype struct without any of the field attributes is written as its text, other
+tuple fields are annotated like named ones, with attributes given a name through `rename`. Unit structs become empty
+elements.
+
+```rust
+# use simple_xml_serialize::XMLElement;
+# use simple_xml_serialize_macro::xml_element;
+#[xml_element("id")]
+struct Id(u64);
+
+#[xml_element("Point")]
+struct Point(#[sxs_type_attr(rename="x")] f32, #[sxs_type_element] Id);
+
+assert_eq!(XMLElement::from(Point(1.5, Id(3))).to_string(), r#"<Point x="1.5"><id>3</id></Point>"#);
+```
+
+Writing `#[xml_element("Name", schema)]` also generates `fn xml_schema()`, describing the element for
+`simple_xml_serialize::xsd::assemble` to write out as an XML Schema. Attributes are required unless they are an `Option`
+or may be skipped, and types of element fields must be annotated with `schema` too.
+
+```rust
+# use simple_xml_serialize::XMLElement;
+# use simple_xml_serialize_macro::xml_element;
+use simple_xml_serialize::xsd::assemble;
+
+#[xml_element("Name", schema)]
+struct MyName {
+    #[sxs_type_attr]
+    age: u8,
+    #[sxs_type_text]
+    val: String,
+}
+
+let xsd = assemble(&[MyName::xml_schema()]).to_string();
+assert!(xsd.contains(r#"<xs:attribute name="age" type="xs:unsignedByte" use="required"/>"#));
+```
+
+Fields behind an `Option` are only written when they are `Some`, and are read back as optional. `Option<T>`,
+`std::option::Option<T>` and `core::option::Option<T>` are recognised; a field whose type is an alias for an `Option`
+needs `optional`, as in `#[sxs_type_attr(optional)]`, and the alias's type argument, if it has one, is taken to be the
+type of its values. Multi elements can be an `Option<Vec<T>>`, or a `Vec<Option<T>>` whose `None` items are left out.
+A type of your own named `Option` can't be told apart from the standard one when it's written as `Option<T>` with a
+single type argument, so write it with a longer path, like `self::Option<T>`. The `process_options` feature this used
+to need no longer does anything.
+
+```rust
 use simple_xml_serialize::XMLElement;
 use simple_xml_serialize_macro::xml_element;
 
@@ -66,163 +177,1740 @@ let expected = r#"<Employee Name="Robert"/>"#;
 assert_eq!(XMLElement::from(&person1).to_string(), expected);
 
 
-let person1 = Person1{name: "Robert".to_string(), age: Some(52)};
-let expected = r#"<Employee Name="Robert" age="52"/>"#;
-assert_eq!(XMLElement::from(&person1).to_string(), expected);
-```
-*/
+let person1 = Person1{name: "Robert".to_string(), age: Some(52)};
+let expected = r#"<Employee Name="Robert" age="52"/>"#;
+assert_eq!(XMLElement::from(&person1).to_string(), expected);
+```
+*/
+
+extern crate proc_macro;
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use quote::TokenStreamExt;
+// ref
+// https://tinkering.xyz/introduction-to-proc-macros/
+// https://github.com/tylerreisinger/cache-macro/blob/master/src/lib.rs
+// https://doc.rust-lang.org/1.26.2/unstable-book/language-features/proc-macro.html
+// https://stackoverflow.com/questions/46002861/how-do-i-generate-quotetokens-from-both-a-constant-value-and-a-collection-of
+// https://docs.rs/quote/0.6.11/quote/macro.quote.html
+// https://docs.rs/quote/0.6.11/quote/trait.ToTokens.html
+// https://docs.rs/syn/0.14/syn/struct.Attribute.html
+// https://stackoverflow.com/questions/42484062/how-do-i-process-enum-struct-field-attributes-in-a-procedural-macro/42526546
+// https://stackoverflow.com/questions/49506485/how-to-provide-attributes-for-fields-for-struct-annotated-with-an-attribute-itse
+
+#[proc_macro_attribute]
+pub fn xml_element(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let item: syn::Item = match syn::parse(input) {
+        Ok(item) => item,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let args = syn::parse_macro_input!(attr as syn::AttributeArgs);
+
+    let mut errors = Vec::new();
+    let options = parse_element_args(&args, &mut errors);
+
+    // the item has to exist in the final code, minus our attributes which rust doesn't know about
+    let original_with_our_attrs_removed = remove_our_attrs_from_item_fields(item.clone());
+    let impls = gen_item_code(&item, &options, errors);
+    quote!(#original_with_our_attrs_removed #impls).into()
+}
+
+/// Derives the `From` impls turning a struct or enum into an `XMLElement`, and with `#[xml(from_xml)]`, `FromXml`
+/// reading it back. This is the same code `#[xml_element]` generates, configured through `#[xml(...)]` instead.
+///
+/// On the struct or enum, `#[xml(name = "...")]` names the element, which is the type's name otherwise.
+/// `#[xml(schema)]`, `#[xml(from_xml)]`, `#[xml(tag = "...")]` and the `rename_all_*` options work as they do for
+/// `#[xml_element]`.
+/// Fields are annotated with one of `#[xml(attr)]`, `#[xml(element)]`, `#[xml(multi_element)]` or `#[xml(text)]`,
+/// followed by any keys the `sxs_type_*` attributes take, as in `#[xml(attr, rename = "lat")]`.
+/// `#[xml(skip)]` on its own leaves a field out of the output.
+/// Variants take `#[xml(rename = "...")]`.
+///
+/// # Example
+///
+/// ```rust
+/// use simple_xml_serialize::XMLElement;
+/// use simple_xml_serialize_macro::ToXml;
+///
+/// #[derive(ToXml)]
+/// #[xml(name = "point")]
+/// struct MyPoint {
+///     #[xml(attr, rename = "lat")]
+///     latitude: f32,
+///     #[xml(text)]
+///     name: String,
+/// }
+///
+/// let point = MyPoint{latitude: 43.38, name: "p1".to_string()};
+/// assert_eq!(XMLElement::from(&point).to_string(), r#"<point lat="43.38">p1</point>"#);
+/// ```
+#[proc_macro_derive(ToXml, attributes(xml))]
+pub fn derive_to_xml(input: TokenStream) -> TokenStream {
+    let item: syn::Item = match syn::parse(input) {
+        Ok(item) => item,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let mut errors = Vec::new();
+    let options = parse_container_attrs(&item, &mut errors);
+    let item = translate_xml_attrs(item, &mut errors);
+    gen_item_code(&item, &options, errors).into()
+}
+
+// checks the item and generates the impls for it, or the errors found instead.
+// every mistake is collected so they can all be reported at once, each pointing at what caused it
+fn gen_item_code(item: &syn::Item, options: &ElementArgs, mut errors: Vec<syn::Error>) -> quote::__rt::TokenStream {
+    check_item(item, options, &mut errors);
+    if !errors.is_empty() {
+        let compile_errors = errors.iter().map(syn::Error::to_compile_error);
+        return quote!(#(#compile_errors)*);
+    }
+
+    let tag = options.tag.as_ref().map(|tag| tag.value());
+    // without a name the element is named after the type
+    let element_name = |ident: &syn::Ident| match (&options.name, options.rename_all.elements) {
+        (Some(name), _) => name.clone(),
+        (None, Some(case)) => case.apply(&ident.to_string()),
+        (None, None) => ident.to_string(),
+    };
+
+    // match item and only continue if it is a struct or enum type
+    match item {
+        syn::Item::Struct(ref struct_item) => {
+            gen_impl_code(&element_name(&struct_item.ident), options.schema.is_some(), options.from_xml.is_some(),
+                          options.rename_all, struct_item)
+        },
+        syn::Item::Enum(ref enum_item) => {
+            gen_enum_impl_code(&element_name(&enum_item.ident), tag.as_deref(), options.from_xml.is_some(), options.rename_all, enum_item)
+        },
+        _ => unreachable!("only structs and enums pass check_item"),
+    }
+}
+
+/// the arguments given to `#[xml_element(...)]`, kept as tokens so mistakes can point at them
+struct ElementArgs {
+    name: Option<String>,
+    schema: Option<syn::Ident>,
+    from_xml: Option<syn::Ident>,
+    tag: Option<syn::LitStr>,
+    rename_all: RenameAll,
+}
+
+/// the case conventions from `rename_all_attrs` and `rename_all_elements`, for the names that weren't given a `rename`.
+/// elements are only named by convention when the name comes from a rust name, IE the type's own element or a variant
+#[derive(Clone,Copy,Default)]
+struct RenameAll {
+    attrs: Option<Case>,
+    elements: Option<Case>,
+}
+
+/// the usual case conventions, named as they are written in `rename_all_*`
+#[derive(Clone,Copy,PartialEq,Debug)]
+enum Case {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+const CASES: [(&str, Case); 8] = [
+    ("lowercase", Case::Lower),
+    ("UPPERCASE", Case::Upper),
+    ("PascalCase", Case::Pascal),
+    ("camelCase", Case::Camel),
+    ("snake_case", Case::Snake),
+    ("SCREAMING_SNAKE_CASE", Case::ScreamingSnake),
+    ("kebab-case", Case::Kebab),
+    ("SCREAMING-KEBAB-CASE", Case::ScreamingKebab),
+];
+
+impl Case {
+    /// rewrites a rust name, snake_case for fields or PascalCase for types and variants, in this convention
+    fn apply(self, name: &str) -> String {
+        let words = split_words(name.trim_start_matches("r#"));
+        let capitalize = |word: &String| {
+            let mut chars = word.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+        };
+        match self {
+            Case::Lower => words.concat(),
+            Case::Upper => words.concat().to_uppercase(),
+            Case::Pascal => words.iter().map(capitalize).collect(),
+            Case::Camel => words.iter().enumerate().map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) }).collect(),
+            Case::Snake => words.join("_"),
+            Case::ScreamingSnake => words.join("_").to_uppercase(),
+            Case::Kebab => words.join("-"),
+            Case::ScreamingKebab => words.join("-").to_uppercase(),
+        }
+    }
+}
+
+// the lowercased words of a name, split at `_`, `-` and changes of case. a run of capitals is one word,
+// so `XMLElement` is `xml` and `element`
+fn split_words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !word.is_empty() {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if !prev.is_uppercase() || next_is_lower {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+const FIELD_ATTRS: [&str; 6] = ["sxs_type_attr", "sxs_type_element", "sxs_type_multi_element", "sxs_type_text", "sxs_type_map",
+                                "sxs_flatten"];
+const FIELD_KEYS: [&str; 10] = ["rename", "skip_if", "with", "from_with", "format", "bool_as", "wrapper", "entry", "key_attr", "value"];
+const FIELD_FLAGS: [&str; 6] = ["skip", "skip_if_default", "optional", "empty_wrapper", "attrs", "sorted"];
+const MAP_KEYS: [&str; 5] = ["entry", "key_attr", "value", "attrs", "sorted"];
+const VARIANT_ATTRS: [&str; 1] = ["sxs_variant"];
+const VARIANT_KEYS: [&str; 1] = ["rename"];
+
+// the element name comes first if it's given, followed by any options
+fn parse_element_args(args: &[syn::NestedMeta], errors: &mut Vec<syn::Error>) -> ElementArgs {
+    let mut element_args = ElementArgs{ name: None, schema: None, from_xml: None, tag: None, rename_all: RenameAll::default() };
+    let mut args = args.iter().peekable();
+
+    match args.peek() {
+        Some(syn::NestedMeta::Literal(syn::Lit::Str(ref name))) => {
+            element_args.name = Some(name.value());
+            args.next();
+        },
+        Some(syn::NestedMeta::Literal(ref other)) => {
+            errors.push(syn::Error::new_spanned(other, "expected the element name as a string, as in `#[xml_element(\"name\")]`"));
+            args.next();
+        },
+        _ => {},
+    }
+
+    for option in args {
+        if !parse_element_option(option, &mut element_args, errors) {
+            errors.push(syn::Error::new_spanned(option, "unknown `#[xml_element]` option, expected `schema`, `from_xml`, `tag`, \
+                                                         `rename_all_attrs` or `rename_all_elements`"));
+        }
+    }
+    element_args
+}
+
+// the options of an `#[xml_element]` other than its name, which are shared with `#[xml(...)]` on a derive.
+// returns whether the option was one of them
+fn parse_element_option(option: &syn::NestedMeta, element_args: &mut ElementArgs, errors: &mut Vec<syn::Error>) -> bool {
+    match option {
+        syn::NestedMeta::Meta(syn::Meta::Word(ref ident)) if ident == "schema" => element_args.schema = Some(ident.clone()),
+        syn::NestedMeta::Meta(syn::Meta::Word(ref ident)) if ident == "from_xml" => element_args.from_xml = Some(ident.clone()),
+        syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) if nv.ident == "tag" => match nv.lit {
+            syn::Lit::Str(ref tag) => element_args.tag = Some(tag.clone()),
+            ref other => errors.push(syn::Error::new_spanned(other, "expected the name of the tag attribute as a string")),
+        },
+        syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) if nv.ident == "rename_all_attrs" => {
+            element_args.rename_all.attrs = parse_case(&nv.lit, errors);
+        },
+        syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) if nv.ident == "rename_all_elements" => {
+            element_args.rename_all.elements = parse_case(&nv.lit, errors);
+        },
+        _ => return false,
+    }
+    true
+}
+
+// one of the names in `CASES`
+fn parse_case(lit: &syn::Lit, errors: &mut Vec<syn::Error>) -> Option<Case> {
+    let case = match lit {
+        syn::Lit::Str(ref name) => CASES.iter().find(|(case, _)| *case == name.value()).map(|(_, case)| *case),
+        _ => None,
+    };
+    if case.is_none() {
+        let expected = CASES.iter().map(|(case, _)| format!("`{}`", case)).collect::<Vec<_>>().join(", ");
+        errors.push(syn::Error::new_spanned(lit, format!("unknown case convention, expected one of {}", expected)));
+    }
+    case
+}
+
+// `#[xml(...)]` on a derived struct or enum. the element is named after the type unless it says otherwise
+fn parse_container_attrs(item: &syn::Item, errors: &mut Vec<syn::Error>) -> ElementArgs {
+    let attrs = match item {
+        syn::Item::Struct(ref struct_item) => &struct_item.attrs[..],
+        syn::Item::Enum(ref enum_item) => &enum_item.attrs[..],
+        _ => &[][..],
+    };
+    let mut element_args = ElementArgs{ name: None, schema: None, from_xml: None, tag: None, rename_all: RenameAll::default() };
+
+    for attr in attrs.iter().filter(|a| attr_name(a) == "xml") {
+        for option in xml_attr_options(attr, errors).unwrap_or_default() {
+            match option {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) if nv.ident == "name" => match nv.lit {
+                    syn::Lit::Str(ref name) => element_args.name = Some(name.value()),
+                    ref other => errors.push(syn::Error::new_spanned(other, "expected the element name as a string")),
+                },
+                other => {
+                    if !parse_element_option(&other, &mut element_args, errors) {
+                        errors.push(syn::Error::new_spanned(other, "unknown `#[xml]` option, expected `name`, `schema`, `from_xml`, `tag`, \
+                                                                   `rename_all_attrs` or `rename_all_elements`"));
+                    }
+                },
+            }
+        }
+    }
+    element_args
+}
+
+/// the list inside `#[xml(...)]`
+fn xml_attr_options(attr: &syn::Attribute, errors: &mut Vec<syn::Error>) -> Option<Vec<syn::NestedMeta>> {
+    match attr.interpret_meta() {
+        Some(syn::Meta::List(ml)) => Some(ml.nested.into_iter().collect()),
+        _ => {
+            errors.push(syn::Error::new_spanned(attr, "expected `#[xml(...)]`"));
+            None
+        },
+    }
+}
+
+// rewrites `#[xml(...)]` on the fields and variants of a derived item into the attributes `#[xml_element]` takes,
+// pointing at the original tokens so errors found later still point at what the user wrote.
+// `#[xml(attr, rename = "x")]` becomes `#[sxs_type_attr(rename = "x")]` and `#[xml(rename = "x")]` on a variant
+// becomes `#[sxs_variant(rename = "x")]`
+fn translate_xml_attrs(item: syn::Item, errors: &mut Vec<syn::Error>) -> syn::Item {
+    match item {
+        syn::Item::Struct(mut struct_item) => {
+            for field in struct_item.fields.iter_mut() {
+                translate_field_attrs(&mut field.attrs, errors);
+            }
+            struct_item.into()
+        },
+        syn::Item::Enum(mut enum_item) => {
+            for variant in enum_item.variants.iter_mut() {
+                variant.attrs = variant.attrs.drain(..).map(|attr| match attr_name(&attr) == "xml" {
+                    true => {
+                        let options = xml_attr_options(&attr, errors).unwrap_or_default();
+                        marker_attr("sxs_variant", attr.path.segments[0].ident.span(), &options)
+                    },
+                    false => attr,
+                }).collect();
+                for field in variant.fields.iter_mut() {
+                    translate_field_attrs(&mut field.attrs, errors);
+                }
+            }
+            enum_item.into()
+        },
+        _ => item,
+    }
+}
+
+// the kinds of field become an attribute each, sharing the rest of the options as their keys
+fn translate_field_attrs(attrs: &mut Vec<syn::Attribute>, errors: &mut Vec<syn::Error>) {
+    let kinds = [("attr", "sxs_type_attr"), ("element", "sxs_type_element"),
+                 ("multi_element", "sxs_type_multi_element"), ("text", "sxs_type_text"), ("map", "sxs_type_map"),
+                 ("flatten", "sxs_flatten")];
+    let mut translated = Vec::new();
+    for attr in attrs.drain(..) {
+        if attr_name(&attr) != "xml" {
+            translated.push(attr);
+            continue;
+        }
+        let options = match xml_attr_options(&attr, errors) {
+            Some(options) => options,
+            None => continue,
+        };
+        let (markers, keys): (Vec<_>, Vec<_>) = options.into_iter().partition(|option| match option {
+            syn::NestedMeta::Meta(syn::Meta::Word(ref word)) => kinds.iter().any(|(kind, _)| word == kind),
+            _ => false,
+        });
+        // `#[xml(skip)]` on its own leaves the field alone, like having no attribute
+        let only_skip = keys.iter().all(|key| match key {
+            syn::NestedMeta::Meta(syn::Meta::Word(ref word)) => word == "skip",
+            _ => false,
+        });
+        if markers.is_empty() && (keys.is_empty() || !only_skip) {
+            errors.push(syn::Error::new_spanned(&attr, "expected one of `attr`, `element`, `multi_element`, `text`, `map` or `flatten` in `#[xml(...)]`"));
+        }
+        for marker in markers {
+            if let syn::NestedMeta::Meta(syn::Meta::Word(ref word)) = marker {
+                let (_, name) = kinds.iter().find(|(kind, _)| word == kind).expect("partitioned by kind");
+                translated.push(marker_attr(name, word.span(), &keys));
+            }
+        }
+    }
+    *attrs = translated;
+}
+
+/// `#[name(keys...)]`, spanned to where it came from
+fn marker_attr(name: &str, span: quote::__rt::Span, keys: &[syn::NestedMeta]) -> syn::Attribute {
+    let tts = match keys.is_empty() {
+        true => quote!(),
+        false => quote::quote_spanned!(span=> (#(#keys),*)),
+    };
+    syn::Attribute {
+        pound_token: syn::token::Pound([span]),
+        style: syn::AttrStyle::Outer,
+        bracket_token: syn::token::Bracket(span),
+        path: syn::Ident::new(name, span).into(),
+        tts,
+    }
+}
+
+// everything the code generation relies on about the item and the attributes on its fields and variants
+fn check_item(item: &syn::Item, options: &ElementArgs, errors: &mut Vec<syn::Error>) {
+    match item {
+        syn::Item::Struct(ref struct_item) => {
+            if let Some(ref tag) = options.tag {
+                errors.push(syn::Error::new_spanned(tag, "`tag` only applies to enums"));
+            }
+            check_fields(&struct_item.fields, options.schema.is_some(), errors);
+        },
+        syn::Item::Enum(ref enum_item) => {
+            if let Some(ref schema) = options.schema {
+                errors.push(syn::Error::new_spanned(schema, "`schema` is not supported on enums"));
+            }
+            if enum_item.variants.is_empty() {
+                errors.push(syn::Error::new_spanned(&enum_item.ident, "#[xml_element] enums must have at least one variant"));
+            }
+            for variant in &enum_item.variants {
+                check_attrs(&variant.attrs, &VARIANT_ATTRS, &VARIANT_KEYS, &[], errors);
+                match variant.fields {
+                    syn::Fields::Unnamed(ref fields) => {
+                        if fields.unnamed.len() != 1 {
+                            errors.push(syn::Error::new_spanned(fields, format!(
+                                "tuple variant `{}::{}` must have exactly one field, use a struct variant", enum_item.ident, variant.ident)));
+                        }
+                        for field in &fields.unnamed {
+                            check_attrs(&field.attrs, &[], &[], &[], errors);
+                        }
+                    },
+                    _ => check_fields(&variant.fields, false, errors),
+                }
+            }
+        },
+        _ => errors.push(syn::Error::new(quote::__rt::Span::call_site(), "#[xml_element] may only be applied to structs and enums")),
+    }
+}
+
+// each field can be one kind of thing in the element, and only one of them can be its text
+fn check_fields(fields: &syn::Fields, schema: bool, errors: &mut Vec<syn::Error>) {
+    let mut text_field = false;
+    for field in fields.iter() {
+        let markers = check_attrs(&field.attrs, &FIELD_ATTRS, &FIELD_KEYS, &FIELD_FLAGS, errors);
+        for (i, (kind, attr)) in markers.iter().enumerate() {
+            if i > 0 {
+                errors.push(syn::Error::new_spanned(attr, format!(
+                    "field is already written as {}, it can't also be written as {}",
+                    describe_field_kind(&markers[0].0), describe_field_kind(kind))));
+            }
+            if kind == "sxs_type_text" {
+                if text_field {
+                    errors.push(syn::Error::new_spanned(attr, "only one field can be written as the text"));
+                }
+                text_field = true;
+            }
+            if kind == "sxs_type_attr" && field.ident.is_none() && attr_rename(attr).is_none() {
+                errors.push(syn::Error::new_spanned(attr, "attributes of tuple structs need a name, add `rename=\"name\"`"));
+            }
+            if kind == "sxs_flatten" {
+                if let Some(option) = attr_option_names(attr).first() {
+                    errors.push(syn::Error::new_spanned(option, "`sxs_flatten` takes no options"));
+                }
+                if get_field_options(field).optional || is_option_type(&field.ty) {
+                    errors.push(syn::Error::new_spanned(&field.ty, "flattened fields can't be optional"));
+                }
+            }
+            check_conversion_keys(kind, attr, errors);
+            check_wrapper_keys(kind, attr, errors);
+            check_map_keys(kind, attr, schema, errors);
+        }
+    }
+}
+
+// reports every attribute starting with `sxs_` that isn't one of `known`, or has options other than the `key="value"`s
+// in `keys` and the words in `flags`. returns the known ones found, with their names
+fn check_attrs<'a>(attrs: &'a [syn::Attribute], known: &[&str], keys: &[&str], flags: &[&str],
+                   errors: &mut Vec<syn::Error>) -> Vec<(String, &'a syn::Attribute)> {
+    let mut found = Vec::new();
+    for attr in attrs {
+        let name = attr_name(attr);
+        if !name.starts_with("sxs_") {
+            continue;
+        }
+        if !known.contains(&name.as_str()) {
+            let expected = match known.len() {
+                0 => "none are used here".to_string(),
+                1 => format!("expected `{}`", known[0]),
+                _ => format!("expected one of {}", known.iter().map(|k| format!("`{}`", k)).collect::<Vec<_>>().join(", ")),
+            };
+            errors.push(syn::Error::new_spanned(&attr.path, format!("unknown attribute `{}`, {}", name, expected)));
+            continue;
+        }
+
+        match attr.interpret_meta() {
+            Some(syn::Meta::Word(_)) => {},
+            Some(syn::Meta::List(ref ml)) => {
+                for nested in &ml.nested {
+                    match nested {
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) if keys.contains(&nv.ident.to_string().as_str()) => {
+                            check_key_value(nv, errors);
+                        },
+                        syn::NestedMeta::Meta(syn::Meta::Word(ref word)) if flags.contains(&word.to_string().as_str()) => {},
+                        syn::NestedMeta::Meta(ref meta) => {
+                            let expected = keys.iter().map(|k| format!("`{}=\"...\"`", k))
+                                .chain(flags.iter().map(|f| format!("`{}`", f)))
+                                .collect::<Vec<_>>()
+                                .join(", ");
+                            errors.push(syn::Error::new_spanned(meta.name(), format!("unknown key `{}`, expected {}", meta.name(), expected)));
+                        },
+                        syn::NestedMeta::Literal(ref lit) => {
+                            errors.push(syn::Error::new_spanned(lit, "expected `key=\"value\"`"));
+                        },
+                    }
+                }
+            },
+            _ => {
+                errors.push(syn::Error::new_spanned(&attr.tts, format!("expected `#[{}]` or `#[{}(key=\"value\")]`", name, name)));
+                continue;
+            },
+        }
+        found.push((name, attr));
+    }
+    found
+}
+
+// the value of a `key="value"` has to be a string, holding a path for the keys that name functions
+fn check_key_value(nv: &syn::MetaNameValue, errors: &mut Vec<syn::Error>) {
+    match nv.lit {
+        syn::Lit::Str(ref value) => {
+            if (nv.ident == "skip_if" || nv.ident == "with" || nv.ident == "from_with") && value.parse::<syn::ExprPath>().is_err() {
+                errors.push(syn::Error::new_spanned(value, format!("expected a path to a function for `{}`", nv.ident)));
+            }
+            if nv.ident == "bool_as" && value.value() != "numeric" {
+                errors.push(syn::Error::new_spanned(value, format!("unknown `bool_as` value `{}`, expected `numeric`", value.value())));
+            }
+            if nv.ident == "value" && value.value() != "text" && value.value() != "element" {
+                errors.push(syn::Error::new_spanned(value, format!("unknown `value` value `{}`, expected `text` or `element`", value.value())));
+            }
+        },
+        ref other => errors.push(syn::Error::new_spanned(other, format!("expected a string for `{}`", nv.ident))),
+    }
+}
+
+// `with`, `format` and `bool_as` each replace how a value is written, so only one can be given. `format` and
+// `bool_as` make strings, which only attributes and text hold, and `with` works on one value rather than a list.
+// `from_with` replaces how a value is read, as `bool_as` does too. it finds an element by the name it is given, as
+// the element is named by whatever wrote it
+fn check_conversion_keys(kind: &str, attr: &syn::Attribute, errors: &mut Vec<syn::Error>) {
+    let conversions: Vec<syn::Ident> = attr_option_names(attr).into_iter()
+        .filter(|key| key == "with" || key == "format" || key == "bool_as")
+        .collect();
+    for (i, key) in conversions.iter().enumerate() {
+        if i > 0 {
+            errors.push(syn::Error::new_spanned(key, "only one of `with`, `format` and `bool_as` can be given"));
+        } else if *key != "with" && kind != "sxs_type_attr" && kind != "sxs_type_text" {
+            errors.push(syn::Error::new_spanned(key, format!("`{}` only applies to attributes and the text", key)));
+        } else if kind == "sxs_type_multi_element" || kind == "sxs_type_map" {
+            errors.push(syn::Error::new_spanned(key, format!("`with` can't be given for {}, only for a single value", describe_field_kind(kind))));
+        }
+    }
+
+    let names = attr_option_names(attr);
+    if let Some(from_with) = names.iter().find(|key| *key == "from_with") {
+        if kind == "sxs_type_multi_element" || kind == "sxs_type_map" {
+            errors.push(syn::Error::new_spanned(from_with, format!("`from_with` can't be given for {}, only for a single value", describe_field_kind(kind))));
+        } else if names.iter().any(|key| key == "bool_as") {
+            errors.push(syn::Error::new_spanned(from_with, "only one of `from_with` and `bool_as` can be given"));
+        } else if kind == "sxs_type_element" && attr_rename(attr).is_none() {
+            errors.push(syn::Error::new_spanned(from_with, "elements read `from_with` a function need `rename=\"name\"` to be found"));
+        }
+    }
+}
+
+// `wrapper` and `empty_wrapper` only apply to multiple child elements, and `empty_wrapper` needs a wrapper
+fn check_wrapper_keys(kind: &str, attr: &syn::Attribute, errors: &mut Vec<syn::Error>) {
+    let names = attr_option_names(attr);
+    for key in names.iter().filter(|key| *key == "wrapper" || *key == "empty_wrapper") {
+        if kind != "sxs_type_multi_element" {
+            errors.push(syn::Error::new_spanned(key, format!("`{}` only applies to multiple child elements", key)));
+        } else if key == "empty_wrapper" && !names.iter().any(|name| name == "wrapper") {
+            errors.push(syn::Error::new_spanned(key, "`empty_wrapper` needs a `wrapper=\"...\"` to write"));
+        }
+    }
+}
+
+// the map options only apply to maps, which are written either as an element per entry, needing the `entry` to name
+// them, or as attributes, which a schema can't describe
+fn check_map_keys(kind: &str, attr: &syn::Attribute, schema: bool, errors: &mut Vec<syn::Error>) {
+    let names = attr_option_names(attr);
+    if kind != "sxs_type_map" {
+        for key in names.iter().filter(|key| MAP_KEYS.iter().any(|map_key| *key == map_key)) {
+            errors.push(syn::Error::new_spanned(key, format!("`{}` only applies to maps", key)));
+        }
+        return;
+    }
+    match names.iter().find(|name| *name == "attrs") {
+        Some(attrs) => {
+            for key in names.iter().filter(|key| *key == "entry" || *key == "key_attr" || *key == "value") {
+                errors.push(syn::Error::new_spanned(key, format!("`{}` doesn't apply to maps written as attributes", key)));
+            }
+            if schema {
+                errors.push(syn::Error::new_spanned(attrs, "maps written as attributes can't be described in a schema"));
+            }
+        },
+        None if !names.iter().any(|name| name == "entry") => {
+            errors.push(syn::Error::new_spanned(attr, "maps need `entry=\"...\"` naming the element written for each entry, or `attrs` to write them as attributes"));
+        },
+        None => {},
+    }
+    if let Some(rename) = names.iter().find(|name| *name == "rename") {
+        errors.push(syn::Error::new_spanned(rename, "maps aren't written with a name of their own, name their entries with `entry`"));
+    }
+}
+
+/// the names of the `key="value"`s and words given to one of our attributes
+fn attr_option_names(attr: &syn::Attribute) -> Vec<syn::Ident> {
+    match attr.interpret_meta() {
+        Some(syn::Meta::List(ml)) => ml.nested.iter().filter_map(|nested| match nested {
+            syn::NestedMeta::Meta(meta) => Some(meta.name()),
+            syn::NestedMeta::Literal(_) => None,
+        }).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// how a field with one of our attributes is written, for error messages
+fn describe_field_kind(kind: &str) -> &'static str {
+    match kind {
+        "sxs_type_attr" => "an attribute",
+        "sxs_type_element" => "a child element",
+        "sxs_type_multi_element" => "child elements",
+        "sxs_type_map" => "a map",
+        "sxs_flatten" => "part of the element",
+        _ => "the text",
+    }
+}
+
+/// the name an attribute is written with, as in `sxs_type_attr`
+fn attr_name(attr: &syn::Attribute) -> String {
+    attr.path.segments.iter().map(|s| s.ident.to_string()).collect::<Vec<_>>().join("::")
+}
+
+/// the value of `rename` in one of our attributes, if it has one
+fn attr_rename(attr: &syn::Attribute) -> Option<String> {
+    match attr.interpret_meta() {
+        Some(syn::Meta::List(ref ml)) => extract_ident_with_new_name(ml, &attr_name(attr)),
+        _ => None,
+    }
+}
+
+/// every attribute starting with `sxs_` is ours, including misspelled ones, which have been reported by then
+fn remove_our_attrs_from_item_fields(original_struct: syn::Item) -> syn::Item {
+    remove_attr_from_item(original_struct, "sxs_")
+}
+
+/// dig into the fields attributes and remove the attributes we added to avoid 
+/// compilation errors after code generation is done
+fn remove_attr_from_item(original_item: syn::Item, to_remove: &str) -> syn::Item {
+    match original_item {
+        syn::Item::Struct(mut struct_item) => {
+            remove_attr_from_fields(&mut struct_item.fields, to_remove);
+            // this has to go here since our destructuring above moves the value
+            struct_item.into()
+        },
+        syn::Item::Enum(mut enum_item) => {
+            for variant in enum_item.variants.iter_mut() {
+                remove_attr_from_attrs(&mut variant.attrs, to_remove);
+                remove_attr_from_fields(&mut variant.fields, to_remove);
+            }
+            enum_item.into()
+        },
+        _ => original_item,
+    }
+}
+
+fn remove_attr_from_fields(fields: &mut syn::Fields, to_remove: &str) {
+    for field in fields.iter_mut() {
+        remove_attr_from_attrs(&mut field.attrs, to_remove);
+    }
+}
+
+// to_remove is a prefix, matching every attribute whose name starts with it
+fn remove_attr_from_attrs(attrs: &mut Vec<syn::Attribute>, to_remove: &str) {
+    attrs.retain(|a| !attr_name(a).starts_with(to_remove));
+}
+
+// new_element_name is what our xml element will ultimately be called
+// ast is the breakdown of the struct stuff by syn that we need to examine for the code generation
+// gen_schema is whether to also generate `xml_schema()`
+// gen_from_xml is whether to also implement `FromXml`
+// rename_all holds the case conventions for the names of attributes that weren't renamed
+fn gen_impl_code(new_element_name: &str, gen_schema: bool, gen_from_xml: bool, rename_all: RenameAll,
+                 ast: &syn::ItemStruct) -> quote::__rt::TokenStream {
+    let struct_ident = &ast.ident;
+
+    // get the ident and name of the fields our attribute were applied to
+    let attr_field_idents           = get_field_idents_of_attr_type(&ast.fields, "sxs_type_attr", rename_all.attrs);
+    // element fields that weren't renamed keep their type's element name, so only attributes take the field's name
+    let element_field_idents        = get_field_idents_of_attr_type(&ast.fields, "sxs_type_element", None);
+    let multi_element_field_idents  = get_field_idents_of_attr_type(&ast.fields, "sxs_type_multi_element", None);
+    let text_field_idents           = get_field_idents_of_attr_type(&ast.fields, "sxs_type_text", None);
+    let map_field_idents            = get_field_idents_of_attr_type(&ast.fields, "sxs_type_map", None);
+    let flatten_field_idents        = get_field_idents_of_attr_type(&ast.fields, "sxs_flatten", None);
+
+    // since get_field_idents_of_attr_type returns a vec of tuple and we can't use that correctly in quote!
+    // the following is just breaking up the tuples into separate vecs
+    
+    // the impls carry the struct's generics, plus whatever bounds the fields using them need
+    let mut to_xml_generics = ast.generics.clone();
+    add_field_bounds(&mut to_xml_generics, &ast.fields, true, false);
+    let (impl_generics, ty_generics, where_clause) = to_xml_generics.split_for_impl();
+
+    // generate the code for the From trait impl
+    let from_impl = quote! {
+        impl #impl_generics From<#struct_ident #ty_generics> for simple_xml_serialize::XMLElement #where_clause {
+            fn from(si: #struct_ident #ty_generics) -> Self {
+                simple_xml_serialize::XMLElement::from(&si)
+            }
+        }
+    };
+
+    let from_xml_impl = match gen_from_xml {
+        true => gen_from_xml_code(new_element_name, ast, &attr_field_idents, &element_field_idents,
+                                  &multi_element_field_idents, &text_field_idents, &map_field_idents, &flatten_field_idents),
+        false => quote!(),
+    };
+
+    let schema_impl = match gen_schema {
+        true => gen_schema_code(new_element_name, ast, &attr_field_idents, &element_field_idents,
+                                &multi_element_field_idents, &text_field_idents, &map_field_idents, &flatten_field_idents),
+        false => quote!(),
+    };
+
+    // tuple fields are bound by destructuring, named fields are reached through `si`
+    let (bind_fields_code, bound) = match ast.fields {
+        syn::Fields::Unnamed(ref fields) => {
+            let read_idents: Vec<&syn::Ident> = attr_field_idents.iter()
+                .chain(&element_field_idents)
+                .chain(&multi_element_field_idents)
+                .chain(&text_field_idents)
+                .chain(&map_field_idents)
+                .chain(&flatten_field_idents)
+                .map(|(ident, _, _, _)| ident)
+                .collect();
+            let patterns: Vec<quote::__rt::TokenStream> = fields.unnamed.iter().enumerate().map(|(i, field)| {
+                let ident = field_local_ident(i, field);
+                match read_idents.contains(&&ident) {
+                    true => quote! { #ident },
+                    false => quote! { _ },
+                }
+            }).collect();
+            (quote! { let #struct_ident(#(#patterns),*) = si; }, true)
+        },
+        _ => (quote!(), false),
+    };
+
+    let add_attrs_code = gen_conditional_field_code(&ast.fields, attr_field_idents, bound, gen_xml_attr_code);
+    let add_elements_code = gen_conditional_field_code(&ast.fields, element_field_idents, bound, gen_xml_element_code);
+    let add_multi_elements_code = gen_conditional_field_code(&ast.fields, multi_element_field_idents, bound, gen_xml_multi_element_code);
+    let add_text_code = gen_conditional_field_code(&ast.fields, text_field_idents, bound, gen_xml_text_code);
+    let add_maps_code = gen_conditional_field_code(&ast.fields, map_field_idents, bound, gen_xml_map_code);
+    let flatten_code = gen_xml_flatten_code(&ast.fields, flatten_field_idents, bound);
+
+    // build out our From using #()* for repetition
+    let from_ref_impl = quote! {
+        impl #impl_generics From<&#struct_ident #ty_generics> for simple_xml_serialize::XMLElement #where_clause {
+            fn from(si: &#struct_ident #ty_generics) -> Self {
+                let mut new_ele = simple_xml_serialize::XMLElement::new(#new_element_name);
+                #bind_fields_code
+                
+                #add_attrs_code
+
+                #add_elements_code
+
+                #add_multi_elements_code
+
+                #add_maps_code
+
+                #flatten_code
+
+                #add_text_code
+
+                new_ele
+            }
+        }
+    };
+    // build up our final generate code and return it
+    quote! {
+        #from_ref_impl
+
+        #from_impl
+
+        #from_xml_impl
+
+        #schema_impl
+    }
+}
+
+// new_element_name is the name of the element the enum is written as
+// tag is the attribute naming the variant in the tag style, or `None` to write data variants as a child element
+// named after the variant and unit variants as text
+// gen_from_xml is whether to also implement `FromXml`
+// rename_all holds the case conventions for the names of attributes, and of variants written as child elements
+fn gen_enum_impl_code(new_element_name: &str, tag: Option<&str>, gen_from_xml: bool, rename_all: RenameAll,
+                      ast: &syn::ItemEnum) -> quote::__rt::TokenStream {
+    let enum_ident = &ast.ident;
+    let enum_name = enum_ident.to_string();
+    let unit_only = ast.variants.iter().all(|v| v.fields == syn::Fields::Unit);
+
+    let mut to_xml_arms = quote!();
+    let mut from_xml_arms = quote!();
+    let mut from_text_arms = quote!();
+    let mut variant_names = Vec::new();
+
+    for variant in &ast.variants {
+        let variant_ident = &variant.ident;
+        let name = match (tag, &variant.fields) {
+            (None, syn::Fields::Named(_)) | (None, syn::Fields::Unnamed(_)) => get_variant_name(variant, rename_all.elements),
+            _ => get_variant_name(variant, None),
+        };
+        variant_names.push(name.clone());
+
+        match variant.fields {
+            syn::Fields::Unit => {
+                match tag {
+                    Some(tag) => {
+                        to_xml_arms.append_all(quote! { #enum_ident::#variant_ident => { new_ele.add_attr(#tag, #name); }, });
+                        from_xml_arms.append_all(quote! { #name => #enum_ident::#variant_ident, });
+                    },
+                    None => {
+                        to_xml_arms.append_all(quote! { #enum_ident::#variant_ident => { new_ele.set_text(#name); }, });
+                        from_text_arms.append_all(quote! { #name => #enum_ident::#variant_ident, });
+                    },
+                }
+            },
+            syn::Fields::Unnamed(ref fields) => {
+                let ty = &fields.unnamed[0].ty;
+                match tag {
+                    Some(tag) => {
+                        // the inner element is merged into ours
+                        let merge_code = gen_merge_code(quote! { inner });
+                        to_xml_arms.append_all(quote! {
+                            #enum_ident::#variant_ident(inner) => {
+                                new_ele.add_attr(#tag, #name);
+                                #merge_code
+                            },
+                        });
+                        // so the inner value is read from everything but the tag
+                        from_xml_arms.append_all(quote! {
+                            #name => {
+                                let mut inner = element.clone();
+                                if let Some(attrs) = inner.attrs.as_mut() {
+                                    attrs.retain(|a| a.name != #tag);
+                                }
+                                return <#ty as simple_xml_serialize::FromXml>::from_xml(&inner).map(#enum_ident::#variant_ident);
+                            },
+                        });
+                    },
+                    None => {
+                        to_xml_arms.append_all(quote! {
+                            #enum_ident::#variant_ident(inner) => { new_ele.add_element(simple_xml_serialize::ToXMLElement::to_xml_element(inner).name(#name)); },
+                        });
+                        from_xml_arms.append_all(quote! {
+                            Some(#name) => #enum_ident::#variant_ident(reader.element_with(#name, <#ty as simple_xml_serialize::FromXml>::from_xml)?),
+                        });
+                    },
+                }
+            },
+            syn::Fields::Named(_) => {
+                let attr_field_idents           = get_field_idents_of_attr_type(&variant.fields, "sxs_type_attr", rename_all.attrs);
+                let element_field_idents        = get_field_idents_of_attr_type(&variant.fields, "sxs_type_element", None);
+                let multi_element_field_idents  = get_field_idents_of_attr_type(&variant.fields, "sxs_type_multi_element", None);
+                let text_field_idents           = get_field_idents_of_attr_type(&variant.fields, "sxs_type_text", None);
+                let map_field_idents            = get_field_idents_of_attr_type(&variant.fields, "sxs_type_map", None);
+                let flatten_field_idents        = get_field_idents_of_attr_type(&variant.fields, "sxs_flatten", None);
+
+                let read_fields_code = gen_read_fields_code(&variant.fields, &attr_field_idents, &element_field_idents,
+                                                            &multi_element_field_idents, &text_field_idents, &map_field_idents,
+                                                            &flatten_field_idents);
+                let field_inits = gen_field_inits(&variant.fields, &[&attr_field_idents, &element_field_idents,
+                                                                     &multi_element_field_idents, &text_field_idents,
+                                                                     &map_field_idents, &flatten_field_idents]);
+                let bound_idents: Vec<syn::Ident> = attr_field_idents.iter()
+                    .chain(&element_field_idents)
+                    .chain(&multi_element_field_idents)
+                    .chain(&text_field_idents)
+                    .chain(&map_field_idents)
+                    .chain(&flatten_field_idents)
+                    .map(|(ident, _, _, _)| ident.clone())
+                    .collect();
+
+                let add_attrs_code = gen_conditional_field_code(&variant.fields, attr_field_idents, true, gen_xml_attr_code);
+                let add_elements_code = gen_conditional_field_code(&variant.fields, element_field_idents, true, gen_xml_element_code);
+                let add_multi_elements_code = gen_conditional_field_code(&variant.fields, multi_element_field_idents, true, gen_xml_multi_element_code);
+                let add_text_code = gen_conditional_field_code(&variant.fields, text_field_idents, true, gen_xml_text_code);
+                let add_maps_code = gen_conditional_field_code(&variant.fields, map_field_idents, true, gen_xml_map_code);
+                let flatten_code = gen_xml_flatten_code(&variant.fields, flatten_field_idents, true);
+
+                match tag {
+                    Some(tag) => {
+                        to_xml_arms.append_all(quote! {
+                            #enum_ident::#variant_ident{ #(#bound_idents,)* .. } => {
+                                new_ele.add_attr(#tag, #name);
+                                #add_attrs_code
+                                #add_elements_code
+                                #add_multi_elements_code
+                                #add_maps_code
+                                #flatten_code
+                                #add_text_code
+                            },
+                        });
+                        from_xml_arms.append_all(quote! {
+                            #name => {
+                                let reader = &mut reader;
+                                #read_fields_code
+                                #enum_ident::#variant_ident{ #field_inits }
+                            },
+                        });
+                    },
+                    None => {
+                        to_xml_arms.append_all(quote! {
+                            #enum_ident::#variant_ident{ #(#bound_idents,)* .. } => {
+                                let variant_ele = {
+                                    #[allow(unused_mut)]
+                                    let mut new_ele = simple_xml_serialize::XMLElement::new(#name);
+                                    #add_attrs_code
+                                    #add_elements_code
+                                    #add_multi_elements_code
+                                    #add_maps_code
+                                    #flatten_code
+                                    #add_text_code
+                                    new_ele
+                                };
+                                new_ele.add_element(variant_ele);
+                            },
+                        });
+                        from_xml_arms.append_all(quote! {
+                            Some(#name) => reader.element_with(#name, |element| {
+                                let mut reader = simple_xml_serialize::from_xml::ElementReader::new(element);
+                                let value = {
+                                    let reader = &mut reader;
+                                    #read_fields_code
+                                    #enum_ident::#variant_ident{ #field_inits }
+                                };
+                                reader.finish()?;
+                                Ok(value)
+                            })?,
+                        });
+                    },
+                }
+            },
+        }
+    }
+
+    let read_variant_code = match tag {
+        Some(tag) => quote! {
+            let tag: String = reader.attr(#tag)?;
+            let value = match tag.as_str() {
+                #from_xml_arms
+                other => return Err(reader.error(format!("value `{}` of attribute `{}` is not a variant of `{}`", other, #tag, #enum_name))),
+            };
+        },
+        None => quote! {
+            let variant = element.contents.as_ref().and_then(|c| c.first()).map(|c| c.name.as_str());
+            let value = match variant {
+                #from_xml_arms
+                Some(other) => return Err(reader.error(format!("element `{}` is not a variant of `{}`", other, #enum_name))),
+                None => {
+                    let text: String = reader.text()?;
+                    match text.trim() {
+                        #from_text_arms
+                        other => return Err(reader.error(format!("text `{}` is not a variant of `{}`", other, #enum_name))),
+                    }
+                },
+            };
+        },
+    };
+
+    // enums of only unit variants can also be used as attribute values and text
+    let display_impl = match unit_only {
+        true => {
+            let mut display_arms = quote!();
+            let mut from_str_arms = quote!();
+            for (variant, name) in ast.variants.iter().zip(&variant_names) {
+                let variant_ident = &variant.ident;
+                display_arms.append_all(quote! { #enum_ident::#variant_ident => #name, });
+                from_str_arms.append_all(quote! { #name => Ok(#enum_ident::#variant_ident), });
+            }
+            let expected = format!("expected one of {}", variant_names.join(", "));
+            let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+            quote! {
+                impl #impl_generics std::fmt::Display for #enum_ident #ty_generics #where_clause {
+                    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str(match self {
+                            #display_arms
+                        })
+                    }
+                }
+
+                impl #impl_generics std::str::FromStr for #enum_ident #ty_generics #where_clause {
+                    type Err = String;
+
+                    fn from_str(s: &str) -> Result<Self, Self::Err> {
+                        match s {
+                            #from_str_arms
+                            _ => Err(#expected.to_string()),
+                        }
+                    }
+                }
+            }
+        },
+        false => quote!(),
+    };
+
+    // the bounds are worked out per variant, with the single field of a tuple variant treated as a child element
+    let mut to_xml_generics = ast.generics.clone();
+    let mut from_xml_generics = ast.generics.clone();
+    for variant in &ast.variants {
+        let newtype = matches!(variant.fields, syn::Fields::Unnamed(_));
+        add_field_bounds(&mut to_xml_generics, &variant.fields, true, newtype);
+        add_field_bounds(&mut from_xml_generics, &variant.fields, false, newtype);
+    }
+    let (impl_generics, ty_generics, where_clause) = to_xml_generics.split_for_impl();
+    let (from_xml_impl_generics, _, from_xml_where_clause) = from_xml_generics.split_for_impl();
+
+    let from_xml_impl = match gen_from_xml {
+        true => quote! {
+            impl #from_xml_impl_generics simple_xml_serialize::FromXml for #enum_ident #ty_generics #from_xml_where_clause {
+                fn element_name() -> &'static str {
+                    #new_element_name
+                }
+
+                #[allow(unreachable_code)]
+                fn from_xml(element: &simple_xml_serialize::XMLElement) -> Result<Self, simple_xml_serialize::FromXmlError> {
+                    let mut reader = simple_xml_serialize::from_xml::ElementReader::new(element);
+
+                    #read_variant_code
+
+                    reader.finish()?;
+                    Ok(value)
+                }
+            }
+        },
+        false => quote!(),
+    };
+
+    quote! {
+        impl #impl_generics From<&#enum_ident #ty_generics> for simple_xml_serialize::XMLElement #where_clause {
+            fn from(si: &#enum_ident #ty_generics) -> Self {
+                let mut new_ele = simple_xml_serialize::XMLElement::new(#new_element_name);
+                match si {
+                    #to_xml_arms
+                }
+                new_ele
+            }
+        }
+
+        impl #impl_generics From<#enum_ident #ty_generics> for simple_xml_serialize::XMLElement #where_clause {
+            fn from(si: #enum_ident #ty_generics) -> Self {
+                simple_xml_serialize::XMLElement::from(&si)
+            }
+        }
+
+        #from_xml_impl
+
+        #display_impl
+    }
+}
+
+/// the name a variant is written with, from `#[sxs_variant(rename="new_name")]` or its ident in the `rename_all` case
+fn get_variant_name(variant: &syn::Variant, rename_all: Option<Case>) -> String {
+    for a in &variant.attrs {
+        if let Some(syn::Meta::List(ref ml)) = a.interpret_meta() {
+            if let Some(newname) = extract_ident_with_new_name(ml, "sxs_variant") {
+                return newname;
+            }
+        }
+    }
+    match rename_all {
+        Some(case) => case.apply(&variant.ident.to_string()),
+        None => variant.ident.to_string(),
+    }
+}
+
+// generates `xml_schema()`, describing the fields in the same order they are written by the `From` impl.
+// child elements refer to the `xml_schema()` of their own type, so those have to be generated too
+#[allow(clippy::too_many_arguments)]
+fn gen_schema_code(new_element_name: &str,
+                   ast: &syn::ItemStruct,
+                   attr_field_idents: &[(syn::Ident, String, bool, bool)],
+                   element_field_idents: &[(syn::Ident, String, bool, bool)],
+                   multi_element_field_idents: &[(syn::Ident, String, bool, bool)],
+                   text_field_idents: &[(syn::Ident, String, bool, bool)],
+                   map_field_idents: &[(syn::Ident, String, bool, bool)],
+                   flatten_field_idents: &[(syn::Ident, String, bool, bool)]) -> quote::__rt::TokenStream {
+    let struct_ident = &ast.ident;
+    let type_name = struct_ident.to_string();
+    let mut describe_fields_code = quote!();
+    // fields that are only written some of the time may be missing, just like `None`s
+    let may_be_missing = |ident: &syn::Ident, is_option: bool| {
+        is_option || get_field_options(find_field(&ast.fields, ident)).is_conditional()
+    };
+
+    for (ident, name, _, is_option) in attr_field_idents {
+        let xsd_type = xsd_builtin_type(field_value_type(find_field(&ast.fields, ident)));
+        let required = !may_be_missing(ident, *is_option);
+        describe_fields_code.append_all(quote! { .attribute(#name, #xsd_type, #required) });
+    }
+
+    for (ident, name, renamed, is_option) in element_field_idents {
+        let name = match renamed {
+            false => quote! { None },
+            true => quote! { Some(#name) },
+        };
+        let item_type = strip_wrapper_types(field_value_type(find_field(&ast.fields, ident)));
+        let min_occurs: usize = if may_be_missing(ident, *is_option) { 0 } else { 1 };
+        describe_fields_code.append_all(quote! { .child(#name, <#item_type>::xml_schema, #min_occurs, Some(1)) });
+    }
+
+    for (ident, name, renamed, is_option) in multi_element_field_idents {
+        let name = match renamed {
+            false => quote! { None },
+            true => quote! { Some(#name) },
+        };
+        let item_type = strip_wrapper_types(field_value_type(find_field(&ast.fields, ident)));
+        let options = get_field_options(find_field(&ast.fields, ident));
+        let child_code = match options.wrapper {
+            None => quote! { .child(#name, <#item_type>::xml_schema, 0, None) },
+            // the wrapper gets a type of its own, named after ours
+            Some(ref wrapper) => {
+                let wrapper_type = format!("{}{}", type_name, wrapper);
+                let min_occurs: usize = if options.empty_wrapper && !may_be_missing(ident, *is_option) { 1 } else { 0 };
+                quote! {
+                    .child(Some(#wrapper), || {
+                        simple_xml_serialize::xsd::XsdFragment::new(#wrapper, #wrapper_type)
+                            .child(#name, <#item_type>::xml_schema, 0, None)
+                    }, #min_occurs, Some(1))
+                }
+            },
+        };
+        describe_fields_code.append_all(child_code);
+    }
+
+    // each entry gets a type of its own, named after ours. maps written as attributes are rejected along with `schema`
+    for (ident, _, _, _) in map_field_idents {
+        let ty = field_type(ast, ident);
+        let options = get_field_options(find_field(&ast.fields, ident));
+        if let (Some(entry), Some((key_type, value_type))) = (options.entry, map_types(option_inner_type(ty, options.optional).unwrap_or(ty))) {
+            let entry_type = format!("{}{}", type_name, entry);
+            let key_attr = options.key_attr.unwrap_or_else(|| "key".to_string());
+            let key_xsd_type = xsd_builtin_type(key_type);
+            let value_code = match options.value_element {
+                false => {
+                    let value_xsd_type = xsd_builtin_type(value_type);
+                    quote! { .text(#value_xsd_type) }
+                },
+                true => quote! { .child(None, <#value_type>::xml_schema, 1, Some(1)) },
+            };
+            describe_fields_code.append_all(quote! {
+                .child(Some(#entry), || {
+                    simple_xml_serialize::xsd::XsdFragment::new(#entry, #entry_type)
+                        .attribute(#key_attr, #key_xsd_type, true)
+                        #value_code
+                }, 0, None)
+            });
+        }
+    }
+
+    for (ident, _, _, _) in flatten_field_idents {
+        let flattened_type = field_type(ast, ident);
+        describe_fields_code.append_all(quote! { .flatten(<#flattened_type>::xml_schema()) });
+    }
 
-extern crate proc_macro;
-extern crate quote;
-extern crate syn;
+    for (ident, _, _, _) in text_field_idents {
+        let xsd_type = xsd_builtin_type(field_value_type(find_field(&ast.fields, ident)));
+        describe_fields_code.append_all(quote! { .text(#xsd_type) });
+    }
 
-use proc_macro::TokenStream;
-use quote::quote;
-use quote::TokenStreamExt;
-// ref
-// https://tinkering.xyz/introduction-to-proc-macros/
-// https://github.com/tylerreisinger/cache-macro/blob/master/src/lib.rs
-// https://doc.rust-lang.org/1.26.2/unstable-book/language-features/proc-macro.html
-// https://stackoverflow.com/questions/46002861/how-do-i-generate-quotetokens-from-both-a-constant-value-and-a-collection-of
-// https://docs.rs/quote/0.6.11/quote/macro.quote.html
-// https://docs.rs/quote/0.6.11/quote/trait.ToTokens.html
-// https://docs.rs/syn/0.14/syn/struct.Attribute.html
-// https://stackoverflow.com/questions/42484062/how-do-i-process-enum-struct-field-attributes-in-a-procedural-macro/42526546
-// https://stackoverflow.com/questions/49506485/how-to-provide-attributes-for-fields-for-struct-annotated-with-an-attribute-itse
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
 
-#[proc_macro_attribute]
-pub fn xml_element(attr: TokenStream, input: TokenStream) -> TokenStream {
-    let item: syn::Item = syn::parse(input).expect("failed to parse input");
-    
-    //clone our item so we can check and alter its attributes
-    let mut original_clone = item.clone();
+    quote! {
+        impl #impl_generics #struct_ident #ty_generics #where_clause {
+            /// Describes the element this struct is written as, for `simple_xml_serialize::xsd::assemble`
+            pub fn xml_schema() -> simple_xml_serialize::xsd::XsdFragment {
+                simple_xml_serialize::xsd::XsdFragment::new(#new_element_name, #type_name)
+                    #describe_fields_code
+            }
+        }
+    }
+}
 
-    // assert that we need to have a name argument for the new XMLElement
-    let args = attr.to_string();
-    assert!(args.starts_with("\""), "`#[xml_element]` requires an argument of the form `#[xml_element(\"xml_element_name_here\")]`");
+/// the declared type of the field generated code keeps in `ident`
+fn field_type<'a>(ast: &'a syn::ItemStruct, ident: &syn::Ident) -> &'a syn::Type {
+    &find_field(&ast.fields, ident).ty
+}
 
-    // trim down to just the value
-    let element_name = args.trim_matches(&['=', ' ', '"'][..]);
+/// the field generated code keeps in `ident`
+fn find_field<'a>(fields: &'a syn::Fields, ident: &syn::Ident) -> &'a syn::Field {
+    fields.iter().enumerate()
+        .find(|(i, f)| field_local_ident(*i, f) == *ident)
+        .map(|(_, f)| f)
+        .expect("field of the struct")
+}
 
-    // match item and only continue if it is a struct type
-    match item {
-        syn::Item::Struct(ref struct_item) => {
-            return gen_impl_code(&element_name, &mut original_clone, struct_item);
-        },
-        _ => {
-            assert!(false, "#[xml_element] may only be applied to structs");
-        },
+/// the options given alongside the kind of a field, as in `#[sxs_type_attr(skip_if="is_zero")]`
+#[derive(Default)]
+struct FieldOptions {
+    skip: bool,
+    skip_if: Option<syn::ExprPath>,
+    skip_if_default: bool,
+    with: Option<syn::ExprPath>,
+    from_with: Option<syn::ExprPath>,
+    format: Option<syn::LitStr>,
+    bool_as_numeric: bool,
+    optional: bool,
+    wrapper: Option<String>,
+    empty_wrapper: bool,
+    entry: Option<String>,
+    key_attr: Option<String>,
+    value_element: bool,
+    map_attrs: bool,
+    sorted: bool,
+}
+
+impl FieldOptions {
+    /// whether the field is only written some of the time
+    fn is_conditional(&self) -> bool {
+        self.skip_if.is_some() || self.skip_if_default
     }
+}
 
-    unreachable!();
+fn get_field_options(field: &syn::Field) -> FieldOptions {
+    let mut options = FieldOptions::default();
+    for a in &field.attrs {
+        let ml = match a.interpret_meta() {
+            Some(syn::Meta::List(ml)) => ml,
+            _ => continue,
+        };
+        if !FIELD_ATTRS.contains(&ml.ident.to_string().as_str()) {
+            continue;
+        }
+        for nested in &ml.nested {
+            match nested {
+                syn::NestedMeta::Meta(syn::Meta::Word(ref word)) if word == "skip" => options.skip = true,
+                syn::NestedMeta::Meta(syn::Meta::Word(ref word)) if word == "skip_if_default" => options.skip_if_default = true,
+                syn::NestedMeta::Meta(syn::Meta::Word(ref word)) if word == "optional" => options.optional = true,
+                syn::NestedMeta::Meta(syn::Meta::Word(ref word)) if word == "empty_wrapper" => options.empty_wrapper = true,
+                syn::NestedMeta::Meta(syn::Meta::Word(ref word)) if word == "attrs" => options.map_attrs = true,
+                syn::NestedMeta::Meta(syn::Meta::Word(ref word)) if word == "sorted" => options.sorted = true,
+                syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) => match (nv.ident.to_string().as_str(), &nv.lit) {
+                    ("skip_if", syn::Lit::Str(ref path)) => options.skip_if = path.parse().ok(),
+                    ("with", syn::Lit::Str(ref path)) => options.with = path.parse().ok(),
+                    ("from_with", syn::Lit::Str(ref path)) => options.from_with = path.parse().ok(),
+                    ("format", syn::Lit::Str(ref format)) => options.format = Some(format.clone()),
+                    ("bool_as", syn::Lit::Str(ref value)) => options.bool_as_numeric = value.value() == "numeric",
+                    ("wrapper", syn::Lit::Str(ref name)) => options.wrapper = Some(name.value()),
+                    ("entry", syn::Lit::Str(ref name)) => options.entry = Some(name.value()),
+                    ("key_attr", syn::Lit::Str(ref name)) => options.key_attr = Some(name.value()),
+                    ("value", syn::Lit::Str(ref value)) => options.value_element = value.value() == "element",
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+    }
+    options
 }
 
-/// function with hardcoded values to remove from the vec of struct field attributes
-fn remove_our_attrs_from_item_fields(original_struct: syn::Item) -> syn::Item {
-    let our_attrs = ["sxs_type_attr", "sxs_type_element", "sxs_type_text", "sxs_type_multi_element"];
+// generates the write code for a list of (local ident, name, renamed, is_option) fields
+type FieldCodeGen = fn(&syn::Fields, Vec<(syn::Ident, String, bool, bool)>, bool) -> quote::__rt::TokenStream;
 
-    let mut original_struct_clone = original_struct.clone();
+// generates the code writing each field with `gen`, leaving it out whenever its `skip_if` function says so or,
+// with `skip_if_default`, when it equals its default
+fn gen_conditional_field_code(fields: &syn::Fields,
+                              field_idents: Vec<(syn::Ident, String, bool, bool)>,
+                              bound: bool,
+                              gen: FieldCodeGen) -> quote::__rt::TokenStream {
+    let mut code = quote!();
+    for field_ident in field_idents {
+        let field = find_field(fields, &field_ident.0);
+        let options = get_field_options(field);
+        let access = field_access(&field_ident.0, bound);
+        let field_code = gen(fields, vec![field_ident], bound);
 
-    for a in our_attrs.iter() {
-        original_struct_clone = remove_attr_from_item(original_struct_clone, a);
+        let mut conditions = Vec::new();
+        if let Some(ref path) = options.skip_if {
+            conditions.push(quote! { #path(&#access) });
+        }
+        if options.skip_if_default {
+            let ty = &field.ty;
+            conditions.push(quote! { #access == <#ty as Default>::default() });
+        }
+        match conditions.is_empty() {
+            true => code.append_all(field_code),
+            false => code.append_all(quote! {
+                if !(#(#conditions)||*) {
+                    #field_code
+                }
+            }),
+        }
     }
-    original_struct_clone
+    code
 }
 
-/// dig into the fields attributes and remove the attributes we added to avoid 
-/// compilation errors after code generation is done
-fn remove_attr_from_item(original_struct: syn::Item, to_remove: &str) -> syn::Item {
-    if let syn::Item::Struct(mut struct_item) = original_struct {
-        if let syn::Fields::Named(ref mut fields) = struct_item.fields {
-            for field in fields.named.iter_mut() {
-                let index = field.attrs.iter().position(|a| {
-                    match a.interpret_meta() {
-                        Some(w) => {
-                            match w {
-                                syn::Meta::Word(i) => &i.to_string() == to_remove,
-                                syn::Meta::List(ml) => &ml.ident.to_string() == to_remove,
-                                _ => false,
-                            }
-                        },
-                        _ => false,
+/// digs through `Option<...>` and `Vec<...>` to the type of the values themselves
+fn strip_wrapper_types(ty: &syn::Type) -> &syn::Type {
+    match option_inner_type(ty, false).or_else(|| wrapped_type(ty, "Vec")) {
+        Some(inner) => strip_wrapper_types(inner),
+        None => ty,
+    }
+}
+
+/// the `T` of a `wrapper<T>`, e.g. the item type of a `Vec`
+fn wrapped_type<'a>(ty: &'a syn::Type, wrapper: &str) -> Option<&'a syn::Type> {
+    if let syn::Type::Path(t) = ty {
+        if let Some(last) = t.path.segments.iter().last() {
+            if last.ident == wrapper {
+                if let syn::PathArguments::AngleBracketed(ref args) = last.arguments {
+                    if let Some(syn::GenericArgument::Type(inner)) = args.args.iter().next() {
+                        return Some(inner);
                     }
-                });
-                if let Some(found_index) = index {
-                    field.attrs.remove(found_index);
                 }
             }
         }
-        // this has to go here since our destructuring above moves the value
-        return struct_item.into(); 
     }
-    original_struct
+    None
 }
 
-// new_element_name is what our xml element will ultimately be called
-// original_struct is the struct this macro was applied to, since that has to exist in the final code
-// ast is the breakdown of the struct stuff by syn that we need to examine for the code generation
-fn gen_impl_code(new_element_name: &str, original_struct: &mut syn::Item, ast: &syn::ItemStruct) -> TokenStream {
-    let struct_ident = &ast.ident;
+/// the `K` and `V` of a map type like `HashMap<K, V>` or `BTreeMap<K, V>`
+fn map_types(ty: &syn::Type) -> Option<(&syn::Type, &syn::Type)> {
+    let last = match ty {
+        syn::Type::Path(t) => t.path.segments.iter().last()?,
+        _ => return None,
+    };
+    let args: Vec<&syn::Type> = match last.arguments {
+        syn::PathArguments::AngleBracketed(ref args) => args.args.iter().filter_map(|arg| match arg {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }).collect(),
+        _ => return None,
+    };
+    match args.len() {
+        2 | 3 => Some((args[0], args[1])),
+        _ => None,
+    }
+}
 
-    // get the ident and name of the fields our attribute were applied to
-    let attr_field_idents           = get_field_idents_of_attr_type(&ast.fields, "sxs_type_attr");
-    let element_field_idents        = get_field_idents_of_attr_type(&ast.fields, "sxs_type_element");
-    let multi_element_field_idents  = get_field_idents_of_attr_type(&ast.fields, "sxs_type_multi_element");
-    let text_field_idents           = get_field_idents_of_attr_type(&ast.fields, "sxs_type_text");
+/// which of our attributes the field is annotated with, if any
+fn get_field_kind(field: &syn::Field) -> Option<&'static str> {
+    FIELD_ATTRS.iter().cloned().find(|kind| field.attrs.iter().any(|a| match a.interpret_meta() {
+        Some(syn::Meta::Word(ref i)) => i == kind,
+        Some(syn::Meta::List(ref ml)) => ml.ident == kind,
+        _ => false,
+    }))
+}
 
-    // since get_field_idents_of_attr_type returns a vec of tuple and we can't use that correctly in quote!
-    // the following is just breaking up the tuples into separate vecs
-    
-    // generate the code for the From trait impl
-    let from_impl = quote! {
-        impl From<#struct_ident> for XMLElement {
-            fn from(si: #struct_ident) -> Self {
-                XMLElement::from(&si)
-            }
+/// whether any of the idents in `ty` name one of `names`. lifetimes come through as a `'` followed by their ident
+fn mentions_generics(ty: &syn::Type, names: &[String]) -> bool {
+    fn walk(tokens: quote::__rt::TokenStream, names: &[String]) -> bool {
+        tokens.into_iter().any(|tt| match tt {
+            quote::__rt::TokenTree::Ident(ref i) => names.contains(&i.to_string()),
+            quote::__rt::TokenTree::Group(ref g) => walk(g.stream(), names),
+            _ => false,
+        })
+    }
+    walk(quote!(#ty), names)
+}
+
+// adds the bounds the generated code needs to the where clause of `generics`, for the fields whose types use them.
+// to_xml picks between the bounds of the `From` impls and those of `FromXml`.
+// unannotated fields are only touched when reading, where they're left to `Default`.
+// newtype is for the single field of a tuple variant, which is always a child element
+fn add_field_bounds(generics: &mut syn::Generics, fields: &syn::Fields, to_xml: bool, newtype: bool) {
+    let names: Vec<String> = generics.params.iter().map(|p| match p {
+        syn::GenericParam::Type(t) => t.ident.to_string(),
+        syn::GenericParam::Lifetime(l) => l.lifetime.ident.to_string(),
+        syn::GenericParam::Const(c) => c.ident.to_string(),
+    }).collect();
+    if names.is_empty() {
+        return;
+    }
+
+    let where_clause = generics.make_where_clause();
+    for field in fields.iter().filter(|f| mentions_generics(&f.ty, &names)) {
+        let ty = &field.ty;
+        let options = get_field_options(field);
+        let kind = match (newtype, is_bare_newtype(fields)) {
+            (true, _) => Some("sxs_type_element"),
+            (false, true) => Some("sxs_type_text"),
+            (false, false) if options.skip => None,
+            (false, false) => get_field_kind(field),
+        };
+        // the bounds are on what's inside an `Option`, and on the items of a `Vec` that aren't `None`
+        let value_ty = option_inner_type(ty, options.optional).unwrap_or(ty);
+        let item_ty = wrapped_type(value_ty, "Vec").map(|item_ty| option_inner_type(item_ty, false).unwrap_or(item_ty));
+
+        let predicates: Vec<syn::WherePredicate> = match (kind, to_xml) {
+            // `with` and `from_with` functions take the value as it is
+            (Some(_), true) if options.with.is_some() => vec![],
+            (Some(_), false) if options.from_with.is_some() => vec![],
+            (Some("sxs_type_attr"), true) | (Some("sxs_type_text"), true) if options.format.is_some() => vec![
+                syn::parse_quote!(#value_ty: std::fmt::Display),
+            ],
+            (Some("sxs_type_attr"), true) | (Some("sxs_type_text"), true) => vec![
+                syn::parse_quote!(#value_ty: ToString),
+            ],
+            (Some("sxs_type_attr"), false) | (Some("sxs_type_text"), false) => vec![
+                syn::parse_quote!(#value_ty: std::str::FromStr),
+                syn::parse_quote!(<#value_ty as std::str::FromStr>::Err: std::fmt::Display),
+            ],
+            (Some("sxs_type_map"), _) => match map_types(value_ty) {
+                Some((key_ty, item_ty)) => map_bounds(value_ty, key_ty, item_ty, &options, to_xml),
+                None => vec![],
+            },
+            (Some("sxs_type_element"), true) | (Some("sxs_flatten"), true) => vec![
+                syn::parse_quote!(#value_ty: simple_xml_serialize::ToXMLElement),
+            ],
+            (Some("sxs_type_element"), false) | (Some("sxs_flatten"), false) => vec![
+                syn::parse_quote!(#value_ty: simple_xml_serialize::FromXml),
+            ],
+            (Some(_), true) => match item_ty {
+                Some(item_ty) => vec![
+                    syn::parse_quote!(#item_ty: simple_xml_serialize::ToXMLElement),
+                ],
+                None => vec![
+                    syn::parse_quote!(for<'xml> &'xml #value_ty: IntoIterator),
+                    syn::parse_quote!(for<'xml> <&'xml #value_ty as IntoIterator>::Item: std::ops::Deref),
+                    syn::parse_quote!(for<'xml> <<&'xml #value_ty as IntoIterator>::Item as std::ops::Deref>::Target: simple_xml_serialize::ToXMLElement),
+                ],
+            },
+            (Some(_), false) => item_ty.into_iter().map(|item_ty| {
+                syn::parse_quote!(#item_ty: simple_xml_serialize::FromXml)
+            }).collect(),
+            (None, true) => vec![],
+            (None, false) => vec![
+                syn::parse_quote!(#ty: Default),
+            ],
+        };
+        where_clause.predicates.extend(predicates);
+
+        // fields that aren't always written are compared with their default, and fall back to it when read
+        if kind.is_some() && options.skip_if_default && to_xml {
+            where_clause.predicates.push(syn::parse_quote!(#ty: PartialEq + Default));
         }
-    };
+        if kind.is_some() && options.is_conditional() && !to_xml {
+            where_clause.predicates.push(syn::parse_quote!(#ty: Default));
+        }
+    }
+}
+
+// keys are written with `ToString` and sorted with `Ord`, values with `ToString` or as elements, and both are read
+// back into the map through `FromIterator`
+fn map_bounds(map_ty: &syn::Type, key_ty: &syn::Type, value_ty: &syn::Type, options: &FieldOptions, to_xml: bool) -> Vec<syn::WherePredicate> {
+    let mut predicates: Vec<syn::WherePredicate> = Vec::new();
+    match to_xml {
+        true => {
+            predicates.push(syn::parse_quote!(#key_ty: ToString));
+            if options.sorted {
+                predicates.push(syn::parse_quote!(#key_ty: Ord));
+            }
+            predicates.push(match options.value_element {
+                false => syn::parse_quote!(#value_ty: ToString),
+                true => syn::parse_quote!(#value_ty: simple_xml_serialize::ToXMLElement),
+            });
+        },
+        false => {
+            predicates.push(syn::parse_quote!(#key_ty: std::str::FromStr));
+            predicates.push(syn::parse_quote!(<#key_ty as std::str::FromStr>::Err: std::fmt::Display));
+            match options.value_element {
+                false => {
+                    predicates.push(syn::parse_quote!(#value_ty: std::str::FromStr));
+                    predicates.push(syn::parse_quote!(<#value_ty as std::str::FromStr>::Err: std::fmt::Display));
+                },
+                true => predicates.push(syn::parse_quote!(#value_ty: simple_xml_serialize::FromXml)),
+            }
+            predicates.push(syn::parse_quote!(#map_ty: std::iter::FromIterator<(#key_ty, #value_ty)>));
+        },
+    }
+    predicates
+}
 
-    let add_attrs_code = gen_xml_attr_code(attr_field_idents);
-    let add_elements_code = gen_xml_element_code(element_field_idents);
-    let add_multi_elements_code = gen_xml_multi_element_code(multi_element_field_idents);
-    let add_text_code = gen_xml_text_code(text_field_idents);
+/// the built-in schema type matching how a field's value is written. anything that isn't a primitive is a string
+fn xsd_builtin_type(ty: &syn::Type) -> &'static str {
+    let ident = match strip_wrapper_types(ty) {
+        syn::Type::Path(t) => t.path.segments.iter().last().map(|s| s.ident.to_string()),
+        _ => None,
+    };
+    match ident.as_deref() {
+        Some("bool") => "xs:boolean",
+        Some("i8") => "xs:byte",
+        Some("i16") => "xs:short",
+        Some("i32") => "xs:int",
+        Some("i64") | Some("isize") => "xs:long",
+        Some("i128") => "xs:integer",
+        Some("u8") => "xs:unsignedByte",
+        Some("u16") => "xs:unsignedShort",
+        Some("u32") => "xs:unsignedInt",
+        Some("u64") | Some("usize") => "xs:unsignedLong",
+        Some("NonZeroU64") | Some("NonZeroUsize") => "xs:positiveInteger",
+        Some("u128") => "xs:nonNegativeInteger",
+        Some("f32") => "xs:float",
+        Some("f64") => "xs:double",
+        _ => "xs:string",
+    }
+}
 
-    // build out our From using #()* for repetition
-    let from_ref_impl = quote! {
-        impl From<&#struct_ident> for XMLElement {
-            fn from(si: &#struct_ident) -> Self {
-                let mut new_ele = XMLElement::new(#new_element_name);
-                
-                #add_attrs_code
+// generates `impl FromXml`, reading fields in the same order they are written by the `From` impl.
+// fields without one of our attributes are left to `Default`
+#[allow(clippy::too_many_arguments)]
+fn gen_from_xml_code(new_element_name: &str,
+                     ast: &syn::ItemStruct,
+                     attr_field_idents: &[(syn::Ident, String, bool, bool)],
+                     element_field_idents: &[(syn::Ident, String, bool, bool)],
+                     multi_element_field_idents: &[(syn::Ident, String, bool, bool)],
+                     text_field_idents: &[(syn::Ident, String, bool, bool)],
+                     map_field_idents: &[(syn::Ident, String, bool, bool)],
+                     flatten_field_idents: &[(syn::Ident, String, bool, bool)]) -> quote::__rt::TokenStream {
+    let struct_ident = &ast.ident;
+    let read_fields_code = gen_read_fields_code(&ast.fields, attr_field_idents, element_field_idents, multi_element_field_idents,
+                                                text_field_idents, map_field_idents, flatten_field_idents);
+    let field_inits = gen_field_inits(&ast.fields, &[attr_field_idents, element_field_idents, multi_element_field_idents,
+                                                     text_field_idents, map_field_idents, flatten_field_idents]);
+    let mut from_xml_generics = ast.generics.clone();
+    add_field_bounds(&mut from_xml_generics, &ast.fields, false, false);
+    let (impl_generics, ty_generics, where_clause) = from_xml_generics.split_for_impl();
 
-                #add_elements_code
+    quote! {
+        impl #impl_generics simple_xml_serialize::FromXml for #struct_ident #ty_generics #where_clause {
+            fn element_name() -> &'static str {
+                #new_element_name
+            }
 
-                #add_multi_elements_code
+            fn from_xml(element: &simple_xml_serialize::XMLElement) -> Result<Self, simple_xml_serialize::FromXmlError> {
+                let mut reader = simple_xml_serialize::from_xml::ElementReader::new(element);
+                let value = Self::read_flattened(&mut reader)?;
+                reader.finish()?;
+                Ok(value)
+            }
 
-                #add_text_code
+            fn read_flattened(reader: &mut simple_xml_serialize::from_xml::ElementReader) -> Result<Self, simple_xml_serialize::FromXmlError> {
+                #read_fields_code
 
-                new_ele
+                Ok(#struct_ident{ #field_inits })
             }
         }
-    };
-    // remove our attrs so it doesn't screw up the generated code
-    let original_struct_with_our_attrs_removed = remove_our_attrs_from_item_fields(original_struct.clone());
+    }
+}
 
-    // build up our final generate code and return it
-    let gen = quote! {
-        #original_struct_with_our_attrs_removed
+// reads each annotated field into a local of the same name using an `ElementReader` called `reader`.
+// fields that aren't always written fall back to their default when they're missing
+fn gen_read_fields_code(fields: &syn::Fields,
+                        attr_field_idents: &[(syn::Ident, String, bool, bool)],
+                        element_field_idents: &[(syn::Ident, String, bool, bool)],
+                        multi_element_field_idents: &[(syn::Ident, String, bool, bool)],
+                        text_field_idents: &[(syn::Ident, String, bool, bool)],
+                        map_field_idents: &[(syn::Ident, String, bool, bool)],
+                        flatten_field_idents: &[(syn::Ident, String, bool, bool)]) -> quote::__rt::TokenStream {
+    let mut read_fields_code = quote!();
 
-        #from_ref_impl
+    for (ident, name, _, is_option) in attr_field_idents {
+        let options = get_field_options(find_field(fields, ident));
+        let (required, optional) = match (&options.from_with, options.bool_as_numeric) {
+            (Some(path), _) => (
+                quote! { reader.attr_with(#name, #path)? },
+                quote! { reader.optional_attr_with(#name, #path)? },
+            ),
+            (None, true) => (
+                quote! { reader.attr::<simple_xml_serialize::from_xml::NumericBool>(#name)?.0 },
+                quote! { reader.optional_attr::<simple_xml_serialize::from_xml::NumericBool>(#name)?.map(|b| b.0) },
+            ),
+            (None, false) => (
+                quote! { reader.attr(#name)? },
+                quote! { reader.optional_attr(#name)? },
+            ),
+        };
+        let read = match (is_option, options.is_conditional()) {
+            (false, false) => required,
+            (false, true) => quote! { #optional.unwrap_or_default() },
+            (true, _) => optional,
+        };
+        read_fields_code.append_all(quote! { let #ident = #read; });
+    }
 
-        #from_impl
-    };
-    gen.into()
+    // elements read `from_with` a function are found by their `rename`, which check_fields makes sure they have
+    for (ident, name, renamed, is_option) in element_field_idents {
+        let options = get_field_options(find_field(fields, ident));
+        let (required, optional) = match (&options.from_with, renamed) {
+            (Some(path), _) => (
+                quote! { reader.element_with(#name, #path)? },
+                quote! { reader.optional_element_with(#name, #path)? },
+            ),
+            (None, false) => (
+                quote! { reader.element(None)? },
+                quote! { reader.optional_element(None)? },
+            ),
+            (None, true) => (
+                quote! { reader.element(Some(#name))? },
+                quote! { reader.optional_element(Some(#name))? },
+            ),
+        };
+        let read = match (is_option, options.is_conditional()) {
+            (false, false) => required,
+            (false, true) => quote! { #optional.unwrap_or_default() },
+            (true, _) => optional,
+        };
+        read_fields_code.append_all(quote! { let #ident = #read; });
+    }
+
+    for (ident, name, renamed, is_option) in multi_element_field_idents {
+        let name = match renamed {
+            false => quote! { None },
+            true => quote! { Some(#name) },
+        };
+        let field = find_field(fields, ident);
+        let read = match (get_field_options(field).wrapper, is_option) {
+            (None, false) => quote! { reader.elements(#name)? },
+            (None, true) => quote! { reader.optional_elements(#name)? },
+            (Some(wrapper), false) => quote! { reader.wrapped_elements(#wrapper, #name)?.unwrap_or_default() },
+            (Some(wrapper), true) => quote! { reader.wrapped_elements(#wrapper, #name)? },
+        };
+        let read = match (has_optional_items(field), is_option) {
+            (false, _) => read,
+            (true, false) => quote! { #read.into_iter().map(Some).collect() },
+            (true, true) => quote! { #read.map(|items| items.into_iter().map(Some).collect()) },
+        };
+        read_fields_code.append_all(quote! { let #ident = #read; });
+    }
+
+    for (ident, _, _, is_option) in text_field_idents {
+        let options = get_field_options(find_field(fields, ident));
+        let (required, optional) = match (&options.from_with, options.bool_as_numeric) {
+            (Some(path), _) => (
+                quote! { reader.text_with(#path)? },
+                quote! { reader.optional_text_with(#path)? },
+            ),
+            (None, true) => (
+                quote! { reader.text::<simple_xml_serialize::from_xml::NumericBool>()?.0 },
+                quote! { reader.optional_text::<simple_xml_serialize::from_xml::NumericBool>()?.map(|b| b.0) },
+            ),
+            (None, false) => (
+                quote! { reader.text()? },
+                quote! { reader.optional_text()? },
+            ),
+        };
+        let read = match (is_option, options.is_conditional()) {
+            (false, false) => required,
+            (false, true) => quote! { #optional.unwrap_or_default() },
+            (true, _) => optional,
+        };
+        read_fields_code.append_all(quote! { let #ident = #read; });
+    }
+
+    // flattened fields read their own part of our element, before maps written as attributes take every attribute
+    // that's left
+    for (ident, _, _, _) in flatten_field_idents {
+        read_fields_code.append_all(quote! { let #ident = simple_xml_serialize::FromXml::read_flattened(reader)?; });
+    }
+
+    for (ident, _, _, is_option) in map_field_idents {
+        let options = get_field_options(find_field(fields, ident));
+        let read = match options.map_attrs {
+            true => quote! { reader.attr_entries()? },
+            false => {
+                let entry = options.entry.unwrap_or_default();
+                let key_attr = options.key_attr.unwrap_or_else(|| "key".to_string());
+                match options.value_element {
+                    false => quote! { reader.text_entries(#entry, #key_attr)? },
+                    true => quote! { reader.element_entries(#entry, #key_attr)? },
+                }
+            },
+        };
+        let read = match is_option {
+            false => read,
+            true => quote! {
+                {
+                    let entries: Vec<_> = #read;
+                    match entries.is_empty() {
+                        true => None,
+                        false => Some(entries.into_iter().collect()),
+                    }
+                }
+            },
+        };
+        read_fields_code.append_all(quote! { let #ident = #read; });
+    }
+
+    read_fields_code
+}
+
+// the `field: field,` or `field: Default::default(),` initializers for every field, depending on whether it was read.
+// tuple fields are initialized by index, as in `Name{ 0: field_0, }`
+fn gen_field_inits(fields: &syn::Fields, read_field_idents: &[&[(syn::Ident, String, bool, bool)]]) -> quote::__rt::TokenStream {
+    let read_idents: Vec<&syn::Ident> = read_field_idents.iter()
+        .flat_map(|idents| idents.iter())
+        .map(|(ident, _, _, _)| ident)
+        .collect();
+    let mut field_inits = quote!();
+    for (i, field) in fields.iter().enumerate() {
+        let ident = field_local_ident(i, field);
+        let member = match field.ident {
+            Some(ref name) => syn::Member::Named(name.clone()),
+            None => syn::Member::Unnamed(syn::Index::from(i)),
+        };
+        let init = match read_idents.contains(&&ident) {
+            true => quote! { #member: #ident, },
+            false => quote! { #member: Default::default(), },
+        };
+        field_inits.append_all(init);
+    }
+    field_inits
+}
+
+/// how generated code reaches a field: through `si` for structs, or through the reference
+/// a match on an enum variant bound it to
+fn field_access(ident: &syn::Ident, bound: bool) -> quote::__rt::TokenStream {
+    match bound {
+        false => quote! { si.#ident },
+        true => quote! { (*#ident) },
+    }
+}
+
+/// the `String` an attribute or the text is written as, from `value`, a reference to the field's value
+fn gen_string_value_code(options: &FieldOptions, value: quote::__rt::TokenStream) -> quote::__rt::TokenStream {
+    match (&options.with, &options.format, options.bool_as_numeric) {
+        (Some(path), _, _) => quote! { #path(#value) },
+        (None, Some(format), _) => quote! { format!(#format, #value) },
+        (None, None, true) => quote! { simple_xml_serialize::from_xml::NumericBool(*#value).to_string() },
+        (None, None, false) => quote! { #value.to_string() },
+    }
+}
+
+/// the `XMLElement` a child element is written as, from `value`, a reference to the field's value
+fn gen_element_value_code(options: &FieldOptions, value: quote::__rt::TokenStream) -> quote::__rt::TokenStream {
+    match options.with {
+        Some(ref path) => quote! { #path(#value) },
+        None => quote! { simple_xml_serialize::ToXMLElement::to_xml_element(#value) },
+    }
 }
 
-fn gen_xml_attr_code(attr_field_idents: Vec<(syn::Ident, String, bool, bool)>) -> quote::__rt::TokenStream {
+fn gen_xml_attr_code(fields: &syn::Fields, attr_field_idents: Vec<(syn::Ident, String, bool, bool)>, bound: bool) -> quote::__rt::TokenStream {
     let attr_field_names: Vec<String>     = attr_field_idents.iter().map(|(_,b,_,_)|b.clone()).collect();
     let attr_idents:      Vec<syn::Ident> = attr_field_idents.iter().map(|(a,_,_,_)|a.clone()).collect();
-    let attr_is_options:  Vec<bool>       = attr_field_idents.iter().map(|(_,_,_,d)|d.clone()).collect();
+    let attr_is_options:  Vec<bool>       = attr_field_idents.iter().map(|(_,_,_,d)|*d).collect();
     
     let mut add_attrs_code = quote!();
 
@@ -230,15 +1918,19 @@ fn gen_xml_attr_code(attr_field_idents: Vec<(syn::Ident, String, bool, bool)>) -
         let attr_is_option = attr_is_options.get(i).unwrap();
         let attr_name = attr_field_names.get(i).unwrap();
         let attr_ident = attr_idents.get(i).unwrap();
+        let attr_access = field_access(attr_ident, bound);
+        let options = get_field_options(find_field(fields, attr_ident));
 
         let attr_code = match attr_is_option {
             false => {
-                quote! { new_ele.add_attr(#attr_name, &si.#attr_ident); }
+                let value = gen_string_value_code(&options, quote! { (&#attr_access) });
+                quote! { new_ele.add_attr(#attr_name, #value); }
             },
             true => {
+                let value = gen_string_value_code(&options, quote! { a });
                 quote! {
-                    if let Some(a) = &si.#attr_ident {
-                        new_ele.add_attr(#attr_name, &a);
+                    if let Some(a) = &#attr_access {
+                        new_ele.add_attr(#attr_name, #value);
                     }
                 }
             },
@@ -248,24 +1940,28 @@ fn gen_xml_attr_code(attr_field_idents: Vec<(syn::Ident, String, bool, bool)>) -
     add_attrs_code
 }
 
-fn gen_xml_text_code(text_field_idents: Vec<(syn::Ident, String, bool, bool)>) -> quote::__rt::TokenStream {
+fn gen_xml_text_code(fields: &syn::Fields, text_field_idents: Vec<(syn::Ident, String, bool, bool)>, bound: bool) -> quote::__rt::TokenStream {
     let text_idents:        Vec<syn::Ident> = text_field_idents.iter().map(|(a,_,_,_)|a.clone()).collect();
-    let text_is_options:    Vec<bool>       = text_field_idents.iter().map(|(_,_,_,d)|d.clone()).collect();
+    let text_is_options:    Vec<bool>       = text_field_idents.iter().map(|(_,_,_,d)|*d).collect();
     
     let mut add_texts_code = quote!();
 
     for i in 0..text_is_options.len() {
         let text_is_option = text_is_options.get(i).unwrap();
         let text_ident = text_idents.get(i).unwrap();
+        let text_access = field_access(text_ident, bound);
+        let options = get_field_options(find_field(fields, text_ident));
 
         let text_code = match text_is_option {
             false => {
-                quote! { new_ele.set_text(&si.#text_ident); }
+                let value = gen_string_value_code(&options, quote! { (&#text_access) });
+                quote! { new_ele.set_text(#value); }
             },
             true => {
+                let value = gen_string_value_code(&options, quote! { a });
                 quote! {
-                    if let Some(a) = &si.#text_ident {
-                        new_ele.set_text(&a);
+                    if let Some(a) = &#text_access {
+                        new_ele.set_text(#value);
                     }
                 }
             },
@@ -275,11 +1971,11 @@ fn gen_xml_text_code(text_field_idents: Vec<(syn::Ident, String, bool, bool)>) -
     add_texts_code
 }
 
-fn gen_xml_element_code(element_field_idents: Vec<(syn::Ident, String, bool, bool)>) -> quote::__rt::TokenStream {
+fn gen_xml_element_code(fields: &syn::Fields, element_field_idents: Vec<(syn::Ident, String, bool, bool)>, bound: bool) -> quote::__rt::TokenStream {
     let element_names:          Vec<String>     = element_field_idents.iter().map(|(_,b,_,_)|b.clone()).collect();
-    let element_renamed:        Vec<bool>       = element_field_idents.iter().map(|(_,_,c,_)|c.clone()).collect();
+    let element_renamed:        Vec<bool>       = element_field_idents.iter().map(|(_,_,c,_)|*c).collect();
     let element_idents:         Vec<syn::Ident> = element_field_idents.iter().map(|(a,_,_,_)|a.clone()).collect();
-    let element_is_options:     Vec<bool>       = element_field_idents.iter().map(|(_,_,_,d)|d.clone()).collect();
+    let element_is_options:     Vec<bool>       = element_field_idents.iter().map(|(_,_,_,d)|*d).collect();
     
     let mut add_elements_code = quote!();
 
@@ -288,35 +1984,121 @@ fn gen_xml_element_code(element_field_idents: Vec<(syn::Ident, String, bool, boo
         let element_name = element_names.get(i).unwrap();
         let element_was_renamed = element_renamed.get(i).unwrap();
         let element_ident = element_idents.get(i).unwrap();
+        let element_access = field_access(element_ident, bound);
+        let options = get_field_options(find_field(fields, element_ident));
 
         let element_code = match element_is_option {
-            false => match element_was_renamed {
-                false => quote! { new_ele.add_element(&si.#element_ident); },
-                true => quote! { new_ele.add_element(XMLElement::from(&si.#element_ident).name(#element_name)); },
+            false => {
+                let value = gen_element_value_code(&options, quote! { &#element_access });
+                match element_was_renamed {
+                    false => quote! { new_ele.add_element(#value); },
+                    true => quote! { new_ele.add_element(#value.name(#element_name)); },
+                }
+            },
+            true => {
+                let value = gen_element_value_code(&options, quote! { a });
+                match element_was_renamed {
+                    false => quote! {
+                        if let Some(a) = &#element_access {
+                            new_ele.add_element(#value);
+                        }
+                    },
+                    true => quote! {
+                        if let Some(a) = &#element_access {
+                            new_ele.add_element(#value.name(#element_name));
+                        }
+                    },
+                }
             },
-            true => match element_was_renamed {
-                false => quote! {
-                    if let Some(a) = &si.#element_ident {
-                        new_ele.add_element(a);
-                    }
-                },
-                true => quote! { 
-                    if let Some(a) = &si.#element_ident {
-                        new_ele.add_element(XMLElement::from(a).name(#element_name));
-                    }
-                },
-            }, 
         };
         add_elements_code.append_all(element_code);
     }
     add_elements_code
 }
 
-fn gen_xml_multi_element_code(multi_element_field_idents: Vec<(syn::Ident, String, bool, bool)>) -> quote::__rt::TokenStream {
+// maps are written as an `entry` element per entry, holding the key in `key_attr` and the value as its text or
+// child element, or as one of our attributes per entry. `sorted` writes them in the order of their keys
+fn gen_xml_map_code(fields: &syn::Fields, map_field_idents: Vec<(syn::Ident, String, bool, bool)>, bound: bool) -> quote::__rt::TokenStream {
+    let mut add_maps_code = quote!();
+    for (ident, _, _, is_option) in map_field_idents {
+        let access = field_access(&ident, bound);
+        let options = get_field_options(find_field(fields, &ident));
+        let collection = match is_option {
+            false => quote! { (&#access) },
+            true => quote! { a },
+        };
+        let entries = match options.sorted {
+            false => quote! { #collection.into_iter() },
+            true => quote! {
+                {
+                    let mut entries: Vec<_> = #collection.into_iter().collect();
+                    entries.sort_by(|a, b| a.0.cmp(b.0));
+                    entries
+                }
+            },
+        };
+        let add_entry_code = match (options.map_attrs, options.value_element) {
+            (true, _) => quote! { new_ele.add_attr(&key.to_string(), value); },
+            (false, value_element) => {
+                let entry = options.entry.unwrap_or_default();
+                let key_attr = options.key_attr.unwrap_or_else(|| "key".to_string());
+                let value_code = match value_element {
+                    false => quote! { .text(value) },
+                    true => quote! { .element(simple_xml_serialize::ToXMLElement::to_xml_element(value)) },
+                };
+                quote! { new_ele.add_element(simple_xml_serialize::XMLElement::new(#entry).attr(#key_attr, key)#value_code); }
+            },
+        };
+        let map_code = quote! {
+            for (key, value) in #entries {
+                #add_entry_code
+            }
+        };
+        add_maps_code.append_all(match is_option {
+            false => map_code,
+            true => quote! {
+                if let Some(a) = &#access {
+                    #map_code
+                }
+            },
+        });
+    }
+    add_maps_code
+}
+
+// flattened fields are merged into our element. this comes before our own text, which replaces theirs
+fn gen_xml_flatten_code(_fields: &syn::Fields, flatten_field_idents: Vec<(syn::Ident, String, bool, bool)>, bound: bool) -> quote::__rt::TokenStream {
+    let mut flatten_code = quote!();
+    for (ident, _, _, _) in flatten_field_idents {
+        let access = field_access(&ident, bound);
+        flatten_code.append_all(gen_merge_code(quote! { &#access }));
+    }
+    flatten_code
+}
+
+/// merges the attributes, child elements and text `inner` is written with into our element
+fn gen_merge_code(inner: quote::__rt::TokenStream) -> quote::__rt::TokenStream {
+    quote! {
+        {
+            let inner = simple_xml_serialize::ToXMLElement::to_xml_element(#inner);
+            for a in inner.attrs.into_iter().flatten() {
+                new_ele.add_attr(&a.name, a.value);
+            }
+            if let Some(contents) = inner.contents {
+                new_ele.add_elements(contents);
+            }
+            if let Some(text) = inner.text {
+                new_ele.set_text(text);
+            }
+        }
+    }
+}
+
+fn gen_xml_multi_element_code(fields: &syn::Fields, multi_element_field_idents: Vec<(syn::Ident, String, bool, bool)>, bound: bool) -> quote::__rt::TokenStream {
     let multi_element_names:        Vec<String>     = multi_element_field_idents.iter().map(|(_,b,_,_)|b.clone()).collect();
-    let multi_element_renamed:      Vec<bool>       = multi_element_field_idents.iter().map(|(_,_,c,_)|c.clone()).collect();
+    let multi_element_renamed:      Vec<bool>       = multi_element_field_idents.iter().map(|(_,_,c,_)|*c).collect();
     let multi_element_idents:       Vec<syn::Ident> = multi_element_field_idents.iter().map(|(a,_,_,_)|a.clone()).collect();
-    let multi_element_is_options:   Vec<bool>       = multi_element_field_idents.iter().map(|(_,_,_,d)|d.clone()).collect();
+    let multi_element_is_options:   Vec<bool>       = multi_element_field_idents.iter().map(|(_,_,_,d)|*d).collect();
     
     let mut add_multi_elements_code = quote!();
 
@@ -325,134 +2107,149 @@ fn gen_xml_multi_element_code(multi_element_field_idents: Vec<(syn::Ident, Strin
         let multi_element_name = multi_element_names.get(i).unwrap();
         let multi_element_was_renamed = multi_element_renamed.get(i).unwrap();
         let multi_element_ident = multi_element_idents.get(i).unwrap();
+        let multi_element_access = field_access(multi_element_ident, bound);
+        let field = find_field(fields, multi_element_ident);
+        let options = get_field_options(field);
+        // items that are `None` are left out
+        let flatten = match has_optional_items(field) {
+            false => quote!(),
+            true => quote! { .flatten() },
+        };
+        let collection = match multi_element_is_option {
+            false => quote! { (&#multi_element_access) },
+            true => quote! { a },
+        };
+        // the items go straight into our element, or into a wrapper element around them
+        let target = match options.wrapper {
+            None => quote! { new_ele },
+            Some(_) => quote! { wrapper },
+        };
 
-        let multi_element_code = match multi_element_is_option {
-            false => match multi_element_was_renamed {
-                false => quote! { 
-                    new_ele.add_elements(&si.#multi_element_ident);
-                },
-                true => quote! { 
-                    new_ele.add_elements_with_name(#multi_element_name, &si.#multi_element_ident); 
-                },
+        let mut add_code = match multi_element_was_renamed {
+            false => quote! { 
+                #target.add_elements(#collection.into_iter()#flatten.map(|item| simple_xml_serialize::ToXMLElement::to_xml_element(&*item)));
+            },
+            true => quote! { 
+                #target.add_elements_with_name(#multi_element_name, #collection.into_iter()#flatten.map(|item| simple_xml_serialize::ToXMLElement::to_xml_element(&*item))); 
             },
-            true => match multi_element_was_renamed {
+        };
+        if let Some(ref wrapper) = options.wrapper {
+            // an empty wrapper is only written when asked for
+            let add_wrapper = match options.empty_wrapper {
                 false => quote! {
-                    if let Some(a) = &si.#multi_element_ident {
-                        new_ele.add_elements(a);
-                    }
-                },
-                true => quote! { 
-                    if let Some(a) = &si.#multi_element_ident {
-                        new_ele.add_elements_with_name(#multi_element_name, a); 
+                    if wrapper.contents.is_some() {
+                        new_ele.add_element(wrapper);
                     }
                 },
-            }, 
+                true => quote! { new_ele.add_element(wrapper); },
+            };
+            add_code = quote! {
+                let mut wrapper = simple_xml_serialize::XMLElement::new(#wrapper);
+                #add_code
+                #add_wrapper
+            };
+        }
+
+        let multi_element_code = match multi_element_is_option {
+            false => quote! {
+                {
+                    #add_code
+                }
+            },
+            true => quote! {
+                if let Some(a) = &#multi_element_access {
+                    #add_code
+                }
+            },
         };
         add_multi_elements_code.append_all(multi_element_code);
     }
     add_multi_elements_code
 }
 
-// dig down into the attributes of the named fields of our struct.
+/// whether a multi element field is a `Vec<Option<T>>`, or an `Option` of one
+fn has_optional_items(field: &syn::Field) -> bool {
+    wrapped_type(field_value_type(field), "Vec").is_some_and(is_option_type)
+}
+
+// dig down into the attributes of the fields of our struct.
 // return the field idents that match the provided attr_type paired with the name they will 
-// ultimately be serialized with and a bool specifying if we renamed the field or not
-#[cfg(feature = "process_options")]
-fn get_field_idents_of_attr_type(fields: &syn::Fields, attr_type: &str) -> Vec<(syn::Ident, String, bool, bool)> {
-    match fields {
-        syn::Fields::Named(ref fields) => {
-            let mut field_vec = Vec::new();
-            for field in &fields.named {
-                for a in field.attrs.clone().iter() {
-                    if let Some(w) = a.interpret_meta() {
-                        match w {
-                            // this is if our attribute is of the form #[sxs_type_element]
-                            syn::Meta::Word(i) => {
-                                if &i.to_string() == attr_type {
-                                    if field.ident.is_some() {
-                                        let is_option = is_option_type(&field.ty);
-                                        // field.ident.to_string() gives us the name of the field
-                                        let val = (field.clone().ident.unwrap(), field.clone().ident.unwrap().to_string(), false, is_option);
-                                        field_vec.push(val);
-                                    }
-                                }
-                            },
-                            // this is if our attribute is of the form #[sxs_type_element(rename="new_name"))]
-                            syn::Meta::List(ref ml) => {
-                                let newname = extract_ident_with_new_name(ml, attr_type);
-                                if newname.is_some() &&  field.ident.is_some(){
-                                    let is_option = is_option_type(&field.ty);
-                                    let fc = field.clone();
-                                    field_vec.push((fc.ident.unwrap(), newname.unwrap(), true, is_option));
-                                }
-                            },
-                            _ => {},
+// ultimately be serialized with and a bool specifying if we renamed the field or not.
+// fields of tuple structs are identified by `field_0`, `field_1`, ... and the only field of a newtype struct is
+// its text unless it says otherwise
+fn get_field_idents_of_attr_type(fields: &syn::Fields, attr_type: &str, rename_all: Option<Case>) -> Vec<(syn::Ident, String, bool, bool)> {
+    let mut field_vec = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let ident = field_local_ident(i, field);
+        let options = get_field_options(field);
+        let is_option = options.optional || is_option_type(&field.ty);
+        // skipped fields are left alone like fields without our attributes
+        if options.skip {
+            continue;
+        }
+        if attr_type == "sxs_type_text" && is_bare_newtype(fields) {
+            field_vec.push((ident.clone(), ident.to_string(), false, is_option));
+        }
+        for a in field.attrs.clone().iter() {
+            if let Some(w) = a.interpret_meta() {
+                match w {
+                    // this is if our attribute is of the form #[sxs_type_element]
+                    syn::Meta::Word(ref i) if i == attr_type => {
+                        field_vec.push(field_ident_with_name(field, &ident, rename_all, is_option));
+                    },
+                    // this is if our attribute is of the form #[sxs_type_element(rename="new_name"))], or has other keys
+                    syn::Meta::List(ref ml) if ml.ident == attr_type => {
+                        match extract_ident_with_new_name(ml, attr_type) {
+                            Some(newname) => field_vec.push((ident.clone(), newname, true, is_option)),
+                            None => field_vec.push(field_ident_with_name(field, &ident, rename_all, is_option)),
                         }
-                    }
+                    },
+                    _ => {},
                 }
             }
-            field_vec
         }
-        // Ignore unit structs or anonymous fields.
-        _ => {
-            Vec::new()
-        },
+    }
+    field_vec
+}
+
+// a field that wasn't renamed is written with its own name, in the `rename_all` case if there is one.
+// element fields pass no case, so those that weren't renamed keep their type's element name
+// fields of tuple structs have no name of their own to use
+fn field_ident_with_name(field: &syn::Field, ident: &syn::Ident, rename_all: Option<Case>, is_option: bool) -> (syn::Ident, String, bool, bool) {
+    match (&field.ident, rename_all) {
+        (Some(name), Some(case)) => (ident.clone(), case.apply(&name.to_string()), true, is_option),
+        // ident.to_string() gives us the name of the field
+        _ => (ident.clone(), ident.to_string(), false, is_option),
+    }
+}
+
+/// the ident generated code keeps a field's value in, which is its own name unless it's a tuple field
+fn field_local_ident(index: usize, field: &syn::Field) -> syn::Ident {
+    match field.ident {
+        Some(ref ident) => ident.clone(),
+        None => syn::Ident::new(&format!("field_{}", index), quote::__rt::Span::call_site()),
     }
 }
 
-#[cfg(not(feature = "process_options"))]
-fn get_field_idents_of_attr_type(fields: &syn::Fields, attr_type: &str) -> Vec<(syn::Ident, String, bool, bool)> {
+/// a tuple struct with a single field that has none of our attributes, which is written as text
+fn is_bare_newtype(fields: &syn::Fields) -> bool {
     match fields {
-        syn::Fields::Named(ref fields) => {
-            let mut field_vec = Vec::new();
-            for field in &fields.named {
-                for a in field.attrs.clone().iter() {
-                    if let Some(w) = a.interpret_meta() {
-                        match w {
-                            // this is if our attribute is of the form #[sxs_type_element]
-                            syn::Meta::Word(i) => {
-                                if &i.to_string() == attr_type {
-                                    if field.ident.is_some() {
-                                        // field.ident.to_string() gives us the name of the field
-                                        let val = (field.clone().ident.unwrap(), field.clone().ident.unwrap().to_string(), false, false);
-                                        field_vec.push(val);
-                                    }
-                                }
-                            },
-                            // this is if our attribute is of the form #[sxs_type_element(rename="new_name"))]
-                            syn::Meta::List(ref ml) => {
-                                let newname = extract_ident_with_new_name(ml, attr_type);
-                                if newname.is_some() &&  field.ident.is_some(){
-                                    let fc = field.clone();
-                                    field_vec.push((fc.ident.unwrap(), newname.unwrap(), true, false));
-                                }
-                            },
-                            _ => {},
-                        }
-                    }
-                }
-            }
-            field_vec
-        }
-        // Ignore unit structs or anonymous fields.
-        _ => {
-            Vec::new()
-        },
+        syn::Fields::Unnamed(ref fields) => fields.unnamed.len() == 1 && get_field_kind(&fields.unnamed[0]).is_none(),
+        _ => false,
     }
 }
 
 /// digs down into `#[sxs_type_element(rename="new_name"))]` to grab "new_name"
 fn extract_ident_with_new_name(ml: &syn::MetaList, attr_type: &str) -> Option<String> {
-    if ml.ident.to_string() != attr_type {
+    if ml.ident != attr_type {
         return None;
     }
     for nested in &ml.nested {
-        if let syn::NestedMeta::Meta(nv) = nested {
-            if let syn::Meta::NameValue(mnv) = nv {
-                // the only type of attribute param we currently allow is "rename"
-                if &mnv.ident.to_string() == "rename" {
-                    if let syn::Lit::Str(ref ls) = mnv.lit {
-                        return Some(ls.value());
-                    }
+        if let syn::NestedMeta::Meta(syn::Meta::NameValue(mnv)) = nested {
+            // the only type of attribute param we currently allow is "rename"
+            if mnv.ident == "rename" {
+                if let syn::Lit::Str(ref ls) = mnv.lit {
+                    return Some(ls.value());
                 }
             }
         }
@@ -460,18 +2257,37 @@ fn extract_ident_with_new_name(ml: &syn::MetaList, attr_type: &str) -> Option<St
     None
 }
 
-#[cfg(feature = "process_options")]
+/// whether the field's type is an `Option`, so it's only written when it's `Some`
 fn is_option_type(ty: &syn::Type) -> bool {
+    option_inner_type(ty, false).is_some()
+}
 
-    // syn::Type::Path(TypePath)
-    if let syn::Type::Path(t) = ty {
-        if t.path.segments.len() > 0 {
-            let path_seg = &t.path.segments[0];
-            if path_seg.ident.to_string() == "Option" {
-                return true;
-            }
-        }
-    }
+/// the type of the values a field holds when it has one, IE the `T` of an `Option<T>` or of an alias marked `optional`
+fn field_value_type(field: &syn::Field) -> &syn::Type {
+    option_inner_type(&field.ty, get_field_options(field).optional).unwrap_or(&field.ty)
+}
 
-    false
+/// the `T` of an `Option<T>`, also written as `std::option::Option<T>` or `core::option::Option<T>`.
+/// any other path ending in `Option` is some other type. aliases can't be seen through, so with `optional` the only
+/// type argument of any path is taken instead, IE the `T` of `Maybe<T>`, and an alias without one gives `None`.
+/// a type of our own named `Option` with one type argument can't be told apart from the standard one when it's
+/// written as `Option<T>`, it has to be written with a longer path like `self::Option<T>`
+fn option_inner_type(ty: &syn::Type, optional: bool) -> Option<&syn::Type> {
+    let path = match ty {
+        syn::Type::Path(t) if t.qself.is_none() => &t.path,
+        _ => return None,
+    };
+    let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+    let is_option_path = match segments.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["Option"] => path.leading_colon.is_none(),
+        ["std", "option", "Option"] | ["core", "option", "Option"] => true,
+        _ => optional,
+    };
+    match path.segments.iter().last().map(|s| &s.arguments) {
+        Some(syn::PathArguments::AngleBracketed(ref args)) if is_option_path && args.args.len() == 1 => match args.args[0] {
+            syn::GenericArgument::Type(ref inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
 }
\ No newline at end of file
@@ -0,0 +1,30 @@
+// The code `simple_xml_serialize::codegen` generates in its `codegen_sequences_and_choices` test, checked here to
+// still compile and round trip with the macro.
+
+include!("fixtures/codegen_sequences_and_choices.rs");
+
+#[test]
+fn codegen_output_round_trip() {
+    use simple_xml_serialize::FromXml;
+    use std::num::NonZeroU64;
+
+    let order = Order{
+        order_id: 1,
+        status: Some(Status::OnHold),
+        buyer: Party{type_: "ABC".to_string(), value: "Bob".to_string()},
+        note: None,
+        choice: OrderChoice::Cash(Cash{value: "5".to_string()}),
+        line: vec![Line{qty: NonZeroU64::new(2).unwrap()}, Line{qty: NonZeroU64::new(3).unwrap()}],
+    };
+    let xml = XMLElement::from(&order);
+    assert_eq!(xml.to_string(), r#"<order orderId="1" status="on-hold"><buyer type="ABC">Bob</buyer><cash>5</cash><line qty="2"/><line qty="3"/></order>"#);
+    assert_eq!(Order::from_xml(&xml), Ok(order));
+
+    let xml: XMLElement = r#"<order orderId="1" status="closed"><buyer type="ABC"/><card>1234</card></order>"#.parse().unwrap();
+    assert_eq!(Order::from_xml(&xml).unwrap_err().to_string(),
+               "/order: value `closed` of attribute `status` is not valid: expected one of open, on-hold");
+
+    let xml: XMLElement = r#"<order orderId="1"><buyer type="ABC"/><card>1234</card><line qty="0"/></order>"#.parse().unwrap();
+    assert_eq!(Order::from_xml(&xml).unwrap_err().to_string(),
+               "/order/line: value `0` of attribute `qty` is not valid: number would be zero for non-zero type");
+}
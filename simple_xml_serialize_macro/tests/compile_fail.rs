@@ -0,0 +1,7 @@
+// each file in tests/ui is expected to fail to compile with the errors in the matching .stderr file.
+// run with `TRYBUILD=overwrite` to regenerate them after changing a message
+#[test]
+fn compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,120 @@
+// Generated by simple_xml_serialize::codegen, do not edit.
+
+use simple_xml_serialize::XMLElement;
+use simple_xml_serialize_macro::xml_element;
+
+#[xml_element("cash", from_xml)]
+#[derive(Clone,PartialEq,Debug)]
+pub struct Cash {
+    #[sxs_type_text]
+    pub value: String,
+}
+
+#[xml_element("order", from_xml)]
+#[derive(Clone,PartialEq,Debug)]
+pub struct Order {
+    #[sxs_type_attr(rename="orderId")]
+    pub order_id: i32,
+    #[sxs_type_attr]
+    pub status: Option<Status>,
+    #[sxs_type_element(rename="buyer")]
+    pub buyer: Party,
+    #[sxs_type_element(rename="note")]
+    pub note: Option<Note>,
+    #[sxs_type_element]
+    pub choice: OrderChoice,
+    #[sxs_type_multi_element(rename="line")]
+    pub line: Vec<Line>,
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub enum Status {
+    Open,
+    OnHold,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Status::Open => "open",
+            Status::OnHold => "on-hold",
+        })
+    }
+}
+
+impl std::str::FromStr for Status {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(Status::Open),
+            "on-hold" => Ok(Status::OnHold),
+            _ => Err("expected one of open, on-hold".to_string()),
+        }
+    }
+}
+
+#[xml_element("buyer", from_xml)]
+#[derive(Clone,PartialEq,Debug)]
+pub struct Party {
+    #[sxs_type_attr(rename="type")]
+    pub type_: Code,
+    #[sxs_type_text]
+    pub value: String,
+}
+
+/// Restricted to pattern `[A-Z]{3}`
+pub type Code = String;
+
+#[xml_element("note", from_xml)]
+#[derive(Clone,PartialEq,Debug)]
+pub struct Note {
+    #[sxs_type_text]
+    pub value: String,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub enum OrderChoice {
+    Card(Card),
+    Cash(Cash),
+}
+
+impl From<&OrderChoice> for XMLElement {
+    fn from(choice: &OrderChoice) -> Self {
+        match choice {
+            OrderChoice::Card(v) => XMLElement::from(v).name("card"),
+            OrderChoice::Cash(v) => XMLElement::from(v).name("cash"),
+        }
+    }
+}
+
+impl simple_xml_serialize::FromXml for OrderChoice {
+    fn element_name() -> &'static str {
+        "card"
+    }
+
+    fn matches_name(name: &str) -> bool {
+        name == "card" || name == "cash"
+    }
+
+    fn from_xml(element: &XMLElement) -> Result<Self, simple_xml_serialize::FromXmlError> {
+        match element.name.as_str() {
+            "card" => <Card as simple_xml_serialize::FromXml>::from_xml(element).map(OrderChoice::Card),
+            _ => <Cash as simple_xml_serialize::FromXml>::from_xml(element).map(OrderChoice::Cash),
+        }
+    }
+}
+
+#[xml_element("card", from_xml)]
+#[derive(Clone,PartialEq,Debug)]
+pub struct Card {
+    #[sxs_type_text]
+    pub value: String,
+}
+
+#[xml_element("line", from_xml)]
+#[derive(Clone,PartialEq,Debug)]
+pub struct Line {
+    #[sxs_type_attr]
+    pub qty: std::num::NonZeroU64,
+}
@@ -1,5 +1,5 @@
 use simple_xml_serialize::XMLElement;
-use simple_xml_serialize_macro::xml_element;
+use simple_xml_serialize_macro::{xml_element, ToXml};
 
 #[test]
 fn code_gen_test_eq() {
@@ -267,7 +267,6 @@ fn code_gen_test_basic_4() {
     assert_eq!(XMLElement::from(&person1).to_string(), XMLElement::from(&person2).to_string());
 }
 
-#[cfg(feature = "process_options")]
 #[test]
 fn code_gen_test_optional_attr_1() {
     
@@ -289,7 +288,6 @@ fn code_gen_test_optional_attr_1() {
     assert_eq!(XMLElement::from(&person1).to_string(), expected);
 }
 
-#[cfg(feature = "process_options")]
 #[test]
 fn code_gen_test_optional_attr_2() {
     
@@ -311,7 +309,6 @@ fn code_gen_test_optional_attr_2() {
     assert_eq!(XMLElement::from(&person1).to_string(), expected);
 }
 
-#[cfg(feature = "process_options")]
 #[test]
 fn code_gen_test_optional_text() {
     
@@ -333,7 +330,6 @@ fn code_gen_test_optional_text() {
     assert_eq!(XMLElement::from(&person1).to_string(), expected);
 }
 
-#[cfg(feature = "process_options")]
 #[test]
 fn code_gen_test_optional_element() {
     
@@ -361,7 +357,6 @@ fn code_gen_test_optional_element() {
     assert_eq!(XMLElement::from(&person1).to_string(), expected);
 }
 
-#[cfg(feature = "process_options")]
 #[test]
 fn code_gen_test_optional_multi_element() {
     
@@ -387,4 +382,1106 @@ fn code_gen_test_optional_multi_element() {
     let person1 = Person1{names: Some(p1_names), age: 52};
     let expected = r#"<Employee age="52"><Name>Robert</Name><Name>Frost</Name></Employee>"#;
     assert_eq!(XMLElement::from(&person1).to_string(), expected);
-}
\ No newline at end of file
+}
+#[test]
+fn from_xml_round_trip() {
+    use simple_xml_serialize::FromXml;
+
+    #[xml_element("Employees", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Team {
+        #[sxs_type_attr(rename="Dept")]
+        dept: String,
+        #[sxs_type_element(rename="Lead")]
+        lead: Name,
+        #[sxs_type_multi_element]
+        names: Vec<Name>,
+        #[sxs_type_text]
+        motto: String,
+        cached_len: usize,
+    }
+
+    #[xml_element("Name", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Name {
+        #[sxs_type_attr]
+        age: u8,
+        #[sxs_type_text]
+        val: String,
+    }
+
+    let team = Team{
+        dept: "Research".to_string(),
+        lead: Name{age: 40, val: "Ada".to_string()},
+        names: vec![Name{age: 30, val: "Alice".to_string()}, Name{age: 31, val: "Bob".to_string()}],
+        motto: "ship it".to_string(),
+        cached_len: 0,
+    };
+    let xml = XMLElement::from(&team);
+    assert_eq!(Team::from_xml(&xml), Ok(team));
+
+    let parsed: XMLElement = xml.to_string().parse().unwrap();
+    assert_eq!(Team::from_xml(&parsed).unwrap().names[1].val, "Bob");
+}
+
+#[test]
+fn from_xml_errors() {
+    use simple_xml_serialize::FromXml;
+
+    #[xml_element("Employees", from_xml)]
+    #[derive(Debug)]
+    struct Team {
+        #[sxs_type_element(rename="Lead")]
+        lead: Name,
+        #[sxs_type_multi_element(rename="Member")]
+        members: Vec<Name>,
+    }
+
+    #[xml_element("Name", from_xml)]
+    #[derive(Debug)]
+    struct Name {
+        #[sxs_type_attr]
+        age: u8,
+    }
+
+    let error = |xml: &str| Team::from_xml(&xml.parse().unwrap()).unwrap_err().to_string();
+    assert_eq!(error("<Employees/>"), "/Employees: missing element `Lead`");
+    assert_eq!(error(r#"<Employees><Lead/></Employees>"#), "/Employees/Lead: missing attribute `age`");
+    assert_eq!(error(r#"<Employees><Lead age="1" id="2"/></Employees>"#), "/Employees/Lead: unexpected attribute `id`");
+    assert_eq!(error(r#"<Employees><Lead age="1"/><Member age="2"/><Member age="old"/></Employees>"#),
+               "/Employees/Member[2]: value `old` of attribute `age` is not valid: invalid digit found in string");
+    assert_eq!(error(r#"<Employees><Lead age="1"/><Name age="2"/></Employees>"#), "/Employees: unexpected element `Name`");
+}
+
+#[test]
+fn from_xml_optional_fields() {
+    use simple_xml_serialize::FromXml;
+
+    #[xml_element("Employee", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Person {
+        #[sxs_type_attr]
+        age: Option<u8>,
+        #[sxs_type_element]
+        name: Option<Name>,
+        #[sxs_type_multi_element(rename="Alias")]
+        aliases: Option<Vec<Name>>,
+        #[sxs_type_text]
+        note: Option<String>,
+    }
+
+    #[xml_element("Name", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Name {
+        #[sxs_type_text]
+        val: String,
+    }
+
+    let empty = Person{age: None, name: None, aliases: None, note: None};
+    assert_eq!(Person::from_xml(&XMLElement::from(&empty)), Ok(empty));
+
+    let full = Person{
+        age: Some(52),
+        name: Some(Name{val: "Robert".to_string()}),
+        aliases: Some(vec![Name{val: "Bob".to_string()}]),
+        note: Some("hi".to_string()),
+    };
+    assert_eq!(Person::from_xml(&XMLElement::from(&full)), Ok(full));
+}
+
+#[test]
+fn from_xml_is_opt_in() {
+    // `Celsius` can't be parsed and `Sensor` has no `Default`, so neither struct could be read back
+    struct Celsius(f32);
+
+    impl std::fmt::Display for Celsius {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{}C", self.0)
+        }
+    }
+
+    struct Sensor {
+        id: u32,
+    }
+
+    #[xml_element("Reading")]
+    struct Reading {
+        #[sxs_type_attr]
+        temperature: Celsius,
+        sensor: Sensor,
+    }
+
+    #[derive(ToXml)]
+    struct Probe {
+        #[xml(attr)]
+        temperature: Celsius,
+        sensor: Sensor,
+    }
+
+    let reading = Reading{temperature: Celsius(21.5), sensor: Sensor{id: 1}};
+    assert_eq!(XMLElement::from(&reading).to_string(), r#"<Reading temperature="21.5C"/>"#);
+    let probe = Probe{temperature: Celsius(-3.0), sensor: Sensor{id: reading.sensor.id}};
+    assert_eq!(XMLElement::from(&probe).to_string(), r#"<Probe temperature="-3C"/>"#);
+    assert_eq!(probe.sensor.id, 1);
+}
+
+#[test]
+fn xml_schema_validates_output() {
+    use simple_xml_serialize::xsd::{assemble, Schema};
+
+    #[xml_element("Order", schema)]
+    struct Order {
+        #[sxs_type_attr]
+        id: u32,
+        #[sxs_type_attr(rename="Paid")]
+        paid: bool,
+        #[sxs_type_element(rename="Buyer")]
+        buyer: Party,
+        #[sxs_type_multi_element]
+        items: Vec<Item>,
+    }
+
+    #[xml_element("Party", schema)]
+    struct Party {
+        #[sxs_type_text]
+        name: String,
+    }
+
+    #[xml_element("Item", schema)]
+    struct Item {
+        #[sxs_type_attr]
+        price: f64,
+        #[sxs_type_text]
+        sku: String,
+    }
+
+    let xsd = assemble(&[Order::xml_schema()]).to_string_pretty_prolog("\n", "  ");
+    assert_eq!(xsd, r#"<?xml version="1.0" encoding="UTF-8"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema" elementFormDefault="qualified">
+  <xs:element name="Order" type="Order"/>
+  <xs:complexType name="Order">
+    <xs:sequence>
+      <xs:element name="Buyer" type="Party"/>
+      <xs:element name="Item" type="Item" minOccurs="0" maxOccurs="unbounded"/>
+    </xs:sequence>
+    <xs:attribute name="id" type="xs:unsignedInt" use="required"/>
+    <xs:attribute name="Paid" type="xs:boolean" use="required"/>
+  </xs:complexType>
+  <xs:complexType name="Party">
+    <xs:simpleContent>
+      <xs:extension base="xs:string"/>
+    </xs:simpleContent>
+  </xs:complexType>
+  <xs:complexType name="Item">
+    <xs:simpleContent>
+      <xs:extension base="xs:string">
+        <xs:attribute name="price" type="xs:double" use="required"/>
+      </xs:extension>
+    </xs:simpleContent>
+  </xs:complexType>
+</xs:schema>"#);
+
+    let order = Order{
+        id: 7,
+        paid: true,
+        buyer: Party{name: "Robert".to_string()},
+        items: vec![Item{price: 1.5, sku: "a-1".to_string()}, Item{price: 2.0, sku: "b-2".to_string()}],
+    };
+    let schema = Schema::parse(&xsd).unwrap();
+    assert_eq!(schema.validate(&XMLElement::from(&order)), Ok(()));
+
+    #[xml_element("Line", schema)]
+    struct Line {
+        #[sxs_type_attr]
+        qty: std::num::NonZeroU64,
+    }
+
+    let xsd = assemble(&[Line::xml_schema()]).to_string();
+    assert!(xsd.contains(r#"<xs:attribute name="qty" type="xs:positiveInteger" use="required"/>"#));
+}
+
+#[test]
+fn xml_schema_optional_fields() {
+    use simple_xml_serialize::xsd::XsdAttribute;
+
+    #[xml_element("Employee", schema)]
+    struct Person {
+        #[sxs_type_attr]
+        age: Option<u8>,
+        #[sxs_type_element]
+        name: Option<Name>,
+        #[sxs_type_multi_element(rename="Alias")]
+        aliases: Option<Vec<Name>>,
+    }
+
+    #[xml_element("Name", schema)]
+    struct Name {
+        #[sxs_type_text]
+        val: String,
+    }
+
+    let fragment = Person::xml_schema();
+    assert_eq!(fragment.attributes, vec![XsdAttribute{name: "age".to_string(), type_name: "xs:unsignedByte".to_string(), required: false}]);
+    let occurs: Vec<_> = fragment.children.iter().map(|c| (c.name.clone(), c.min_occurs, c.max_occurs)).collect();
+    assert_eq!(occurs, vec![(None, 0, Some(1)), (Some("Alias".to_string()), 0, None)]);
+    assert_eq!((fragment.children[1].schema)().name, "Name");
+}
+
+#[test]
+fn enum_unit_variants() {
+    use simple_xml_serialize::FromXml;
+
+    #[xml_element("Status", from_xml)]
+    #[derive(Clone,Copy,PartialEq,Debug)]
+    enum Status {
+        Open,
+        #[sxs_variant(rename="on-hold")]
+        OnHold,
+    }
+
+    #[xml_element("Ticket", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Ticket {
+        #[sxs_type_attr]
+        status: Status,
+        #[sxs_type_element(rename="Previous")]
+        previous: Status,
+        #[sxs_type_text]
+        next: Status,
+    }
+
+    assert_eq!(Status::OnHold.to_string(), "on-hold");
+    assert_eq!("Open".parse::<Status>(), Ok(Status::Open));
+    assert_eq!("Closed".parse::<Status>(), Err("expected one of Open, on-hold".to_string()));
+
+    let ticket = Ticket{status: Status::OnHold, previous: Status::Open, next: Status::Open};
+    let xml = XMLElement::from(&ticket);
+    assert_eq!(xml.to_string(), r#"<Ticket status="on-hold"><Previous>Open</Previous>Open</Ticket>"#);
+    assert_eq!(Ticket::from_xml(&xml), Ok(ticket));
+
+    let error = Ticket::from_xml(&r#"<Ticket status="Closed"><Previous>Open</Previous>Open</Ticket>"#.parse().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "/Ticket: value `Closed` of attribute `status` is not valid: expected one of Open, on-hold");
+}
+
+#[test]
+fn enum_data_variants() {
+    use simple_xml_serialize::FromXml;
+
+    #[xml_element("Shape", from_xml)]
+    #[derive(PartialEq,Debug)]
+    enum Shape {
+        Empty,
+        Circle(Circle),
+        #[sxs_variant(rename="rect")]
+        Rectangle {
+            #[sxs_type_attr]
+            width: u32,
+            #[sxs_type_attr(rename="h")]
+            height: u32,
+            #[sxs_type_multi_element(rename="Label")]
+            labels: Vec<Circle>,
+        },
+    }
+
+    #[xml_element("Circle", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Circle {
+        #[sxs_type_attr]
+        r: f32,
+    }
+
+    #[xml_element("Drawing", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Drawing {
+        #[sxs_type_multi_element]
+        shapes: Vec<Shape>,
+    }
+
+    let drawing = Drawing{shapes: vec![
+        Shape::Empty,
+        Shape::Circle(Circle{r: 1.5}),
+        Shape::Rectangle{width: 2, height: 3, labels: vec![Circle{r: 0.5}]},
+    ]};
+    let xml = XMLElement::from(&drawing);
+    assert_eq!(xml.to_string(), concat!(
+        r#"<Drawing><Shape>Empty</Shape><Shape><Circle r="1.5"/></Shape>"#,
+        r#"<Shape><rect width="2" h="3"><Label r="0.5"/></rect></Shape></Drawing>"#));
+    assert_eq!(Drawing::from_xml(&xml), Ok(drawing));
+
+    let error = |xml: &str| Drawing::from_xml(&xml.parse().unwrap()).unwrap_err().to_string();
+    assert_eq!(error("<Drawing><Shape><Square/></Shape></Drawing>"), "/Drawing/Shape: element `Square` is not a variant of `Shape`");
+    assert_eq!(error("<Drawing><Shape>Full</Shape></Drawing>"), "/Drawing/Shape: text `Full` is not a variant of `Shape`");
+    assert_eq!(error(r#"<Drawing><Shape><rect width="2"/></Shape></Drawing>"#), "/Drawing/Shape/rect: missing attribute `h`");
+}
+
+#[test]
+fn enum_tag_attribute() {
+    use simple_xml_serialize::FromXml;
+
+    #[xml_element("Payment", tag="kind", from_xml)]
+    #[derive(PartialEq,Debug)]
+    enum Payment {
+        Cash,
+        Card(Card),
+        Transfer {
+            #[sxs_type_attr]
+            iban: String,
+            #[sxs_type_text]
+            reference: String,
+        },
+    }
+
+    #[xml_element("Card", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Card {
+        #[sxs_type_attr]
+        last4: String,
+        #[sxs_type_element(rename="Holder")]
+        holder: Holder,
+    }
+
+    #[xml_element("Holder", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Holder {
+        #[sxs_type_text]
+        name: String,
+    }
+
+    let payments = vec![
+        (Payment::Cash, r#"<Payment kind="Cash"/>"#),
+        (Payment::Card(Card{last4: "4242".to_string(), holder: Holder{name: "Robert".to_string()}}),
+         r#"<Payment kind="Card" last4="4242"><Holder>Robert</Holder></Payment>"#),
+        (Payment::Transfer{iban: "DE02".to_string(), reference: "invoice 7".to_string()},
+         r#"<Payment kind="Transfer" iban="DE02">invoice 7</Payment>"#),
+    ];
+    for (payment, expected) in payments {
+        let xml = XMLElement::from(&payment);
+        assert_eq!(xml.to_string(), expected);
+        assert_eq!(Payment::from_xml(&xml), Ok(payment));
+    }
+
+    let error = Payment::from_xml(&r#"<Payment kind="Cheque"/>"#.parse().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "/Payment: value `Cheque` of attribute `kind` is not a variant of `Payment`");
+    let error = Payment::from_xml(&r#"<Payment kind="Cash" iban="DE02"/>"#.parse().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "/Payment: unexpected attribute `iban`");
+}
+
+#[test]
+fn generic_structs() {
+    use simple_xml_serialize::FromXml;
+
+    #[xml_element("Page", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Page<T, N> where N: Copy {
+        #[sxs_type_attr]
+        number: N,
+        #[sxs_type_element(rename="Featured")]
+        featured: T,
+        #[sxs_type_multi_element]
+        items: Vec<T>,
+    }
+
+    #[xml_element("Item", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Item {
+        #[sxs_type_text]
+        name: String,
+    }
+
+    let page = Page {
+        number: 2u32,
+        featured: Item{name: "c".to_string()},
+        items: vec![Item{name: "a".to_string()}, Item{name: "b".to_string()}],
+    };
+    let xml = XMLElement::from(&page);
+    assert_eq!(xml.to_string(), r#"<Page number="2"><Featured>c</Featured><Item>a</Item><Item>b</Item></Page>"#);
+    assert_eq!(Page::from_xml(&xml), Ok(page));
+
+    #[xml_element("Either", from_xml)]
+    #[derive(PartialEq,Debug)]
+    enum Either<L, R> {
+        Left(L),
+        Right {
+            #[sxs_type_attr]
+            value: R,
+        },
+    }
+
+    let either: Either<Item, i64> = Either::Right{value: -1};
+    let xml = XMLElement::from(&either);
+    assert_eq!(xml.to_string(), r#"<Either><Right value="-1"/></Either>"#);
+    assert_eq!(Either::from_xml(&xml), Ok(either));
+    let either: Either<Item, i64> = Either::Left(Item{name: "d".to_string()});
+    assert_eq!(XMLElement::from(either).to_string(), r#"<Either><Left>d</Left></Either>"#);
+}
+
+#[test]
+fn borrowed_structs() {
+    #[xml_element("Link")]
+    struct Link<'a> {
+        #[sxs_type_attr]
+        href: &'a str,
+        #[sxs_type_text]
+        label: &'a str,
+    }
+
+    let href = String::from("https://example.com");
+    let link = Link{href: &href, label: "example"};
+    assert_eq!(XMLElement::from(&link).to_string(), r#"<Link href="https://example.com">example</Link>"#);
+}
+
+#[test]
+fn tuple_structs() {
+    use simple_xml_serialize::FromXml;
+
+    #[xml_element("id", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Id(u64);
+
+    #[xml_element("Point", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Point(
+        #[sxs_type_attr(rename="x")] f32,
+        #[sxs_type_attr(rename="y")] f32,
+        #[sxs_type_element] Id,
+        String,
+    );
+
+    #[xml_element("Marker", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Marker;
+
+    let id = Id(7);
+    assert_eq!(XMLElement::from(&id).to_string(), "<id>7</id>");
+    assert_eq!(Id::from_xml(&"<id> 7 </id>".parse().unwrap()), Ok(id));
+
+    let point = Point(1.5, -2.0, Id(3), String::new());
+    let xml = XMLElement::from(&point);
+    assert_eq!(xml.to_string(), r#"<Point x="1.5" y="-2"><id>3</id></Point>"#);
+    assert_eq!(Point::from_xml(&xml), Ok(point));
+    let xsd = simple_xml_serialize::xsd::assemble(&[Point::xml_schema()]).to_string();
+    assert!(xsd.contains(r#"<xs:attribute name="x" type="xs:float" use="required"/>"#));
+    assert!(xsd.contains(r#"<xs:extension base="xs:unsignedLong"/>"#));
+
+    assert_eq!(XMLElement::from(Marker).to_string(), "<Marker/>");
+    assert_eq!(Marker::from_xml(&"<Marker/>".parse().unwrap()), Ok(Marker));
+    let error = Marker::from_xml(&"<Marker>text</Marker>".parse().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "/Marker: unexpected text `text`");
+}
+
+#[test]
+fn derive_to_xml() {
+    use simple_xml_serialize::FromXml;
+
+    #[derive(ToXml,PartialEq,Debug)]
+    #[xml(name = "custom_name_here", from_xml)]
+    struct MyPoint {
+        #[xml(attr, rename = "lat")]
+        latitude: f32,
+        #[xml(attr)]
+        lon: f32,
+        #[xml(element, rename = "Identifier")]
+        name: MyName,
+        #[xml(multi_element)]
+        tags: Vec<Tag>,
+        note: String,
+    }
+
+    #[derive(ToXml,PartialEq,Debug)]
+    #[xml(name = "Name", from_xml)]
+    struct MyName {
+        #[xml(text)]
+        val: String,
+    }
+
+    #[derive(ToXml,PartialEq,Debug)]
+    #[xml(tag = "kind", from_xml)]
+    enum Tag {
+        #[xml(rename = "plain")]
+        Plain,
+        Colored {
+            #[xml(attr)]
+            color: String,
+        },
+    }
+
+    let my_point = MyPoint {
+        latitude: 43.38,
+        lon: 60.11,
+        name: MyName{val: "p1".to_string()},
+        tags: vec![Tag::Plain, Tag::Colored{color: "red".to_string()}],
+        note: String::new(),
+    };
+    let xml = XMLElement::from(&my_point);
+    let expected = r#"<custom_name_here lat="43.38" lon="60.11"><Identifier>p1</Identifier><Tag kind="plain"/><Tag kind="Colored" color="red"/></custom_name_here>"#;
+    assert_eq!(xml.to_string(), expected);
+    assert_eq!(MyPoint::from_xml(&xml), Ok(my_point));
+}
+
+#[test]
+fn rename_all() {
+    use simple_xml_serialize::FromXml;
+
+    #[xml_element(rename_all_attrs = "camelCase", rename_all_elements = "PascalCase", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct PurchaseOrder {
+        #[sxs_type_attr]
+        order_id: u32,
+        #[sxs_type_attr(rename="SKU")]
+        sku_code: String,
+        #[sxs_type_element]
+        shipping_address: Address,
+        #[sxs_type_multi_element]
+        line_items: Vec<Address>,
+    }
+
+    #[xml_element("address", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Address {
+        #[sxs_type_text]
+        street: String,
+    }
+
+    let order = PurchaseOrder {
+        order_id: 7,
+        sku_code: "A-1".to_string(),
+        shipping_address: Address{street: "Main".to_string()},
+        line_items: vec![Address{street: "Side".to_string()}],
+    };
+    let xml = XMLElement::from(&order);
+    // element fields that weren't renamed keep their type's element name
+    let expected = r#"<PurchaseOrder orderId="7" SKU="A-1"><address>Main</address><address>Side</address></PurchaseOrder>"#;
+    assert_eq!(xml.to_string(), expected);
+    assert_eq!(PurchaseOrder::from_xml(&xml), Ok(order));
+
+    #[derive(ToXml)]
+    #[xml(rename_all_attrs = "SCREAMING_SNAKE_CASE", rename_all_elements = "kebab-case", from_xml)]
+    enum HTTPRequest {
+        GetPage {
+            #[xml(attr)]
+            max_age: u32,
+        },
+        #[xml(rename = "POST")]
+        PostForm {
+            #[xml(attr)]
+            content_type: String,
+        },
+    }
+
+    let request = HTTPRequest::GetPage{max_age: 60};
+    assert_eq!(XMLElement::from(&request).to_string(), r#"<http-request><get-page MAX_AGE="60"/></http-request>"#);
+    let request = HTTPRequest::PostForm{content_type: "text/xml".to_string()};
+    assert_eq!(XMLElement::from(&request).to_string(), r#"<http-request><POST CONTENT_TYPE="text/xml"/></http-request>"#);
+
+    #[xml_element(rename_all_attrs = "kebab-case", rename_all_elements = "SCREAMING-KEBAB-CASE")]
+    struct XMLDoc {
+        #[sxs_type_attr]
+        xml_version2: String,
+    }
+    #[xml_element(rename_all_attrs = "UPPERCASE", rename_all_elements = "lowercase")]
+    struct SnakeRoot {
+        #[sxs_type_attr]
+        is_open: bool,
+    }
+    #[xml_element(rename_all_elements = "snake_case")]
+    struct IOError;
+    assert_eq!(XMLElement::from(XMLDoc{xml_version2: "1".to_string()}).to_string(), r#"<XML-DOC xml-version2="1"/>"#);
+    assert_eq!(XMLElement::from(SnakeRoot{is_open: true}).to_string(), r#"<snakeroot ISOPEN="true"/>"#);
+    assert_eq!(XMLElement::from(IOError).to_string(), "<io_error/>");
+}
+
+#[test]
+fn conditional_fields() {
+    use simple_xml_serialize::FromXml;
+    use simple_xml_serialize::xsd::{assemble, Schema};
+
+    fn is_zero(n: &u32) -> bool {
+        *n == 0
+    }
+
+    #[xml_element("Item", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Item {
+        #[sxs_type_attr(skip_if="is_zero")]
+        count: u32,
+        #[sxs_type_attr(skip_if_default)]
+        kind: String,
+        #[sxs_type_attr(skip)]
+        cache: String,
+        #[sxs_type_element(rename="Note", skip_if_default)]
+        note: Note,
+        // an empty Vec writes nothing without needing a condition
+        #[sxs_type_multi_element(rename="Tag")]
+        tags: Vec<Note>,
+        #[sxs_type_text(skip_if="String::is_empty")]
+        label: String,
+    }
+
+    #[xml_element("Note", schema, from_xml)]
+    #[derive(PartialEq,Debug,Default)]
+    struct Note {
+        #[sxs_type_text]
+        text: String,
+    }
+
+    // what may be left out is optional in the schema, so the output validates either way
+    let xsd = assemble(&[Item::xml_schema()]).to_string();
+    assert!(xsd.contains(r#"<xs:attribute name="count" type="xs:unsignedInt" use="optional"/>"#));
+    assert!(xsd.contains(r#"<xs:element name="Note" type="Note" minOccurs="0"/>"#));
+    let schema = Schema::parse(&xsd).unwrap();
+
+    let item = Item{count: 0, kind: String::new(), cache: "ignored".to_string(), note: Note::default(), tags: vec![], label: String::new()};
+    let xml = XMLElement::from(&item);
+    assert_eq!(xml.to_string(), "<Item/>");
+    assert_eq!(schema.validate(&xml), Ok(()));
+    assert_eq!(Item::from_xml(&xml), Ok(Item{cache: String::new(), ..item}));
+
+    let item = Item{
+        count: 2,
+        kind: "gift".to_string(),
+        cache: String::new(),
+        note: Note{text: "n".to_string()},
+        tags: vec![Note{text: "t".to_string()}],
+        label: "l".to_string(),
+    };
+    let xml = XMLElement::from(&item);
+    assert_eq!(xml.to_string(), r#"<Item count="2" kind="gift"><Note>n</Note><Tag>t</Tag>l</Item>"#);
+    assert_eq!(schema.validate(&xml), Ok(()));
+    assert_eq!(Item::from_xml(&xml), Ok(item));
+
+    #[derive(ToXml)]
+    #[xml(from_xml)]
+    struct Point<T> {
+        #[xml(attr, skip_if_default)]
+        x: T,
+        #[xml(skip)]
+        y: T,
+    }
+    assert_eq!(XMLElement::from(Point{x: 0.0, y: 1.0}).to_string(), "<Point/>");
+    assert_eq!(XMLElement::from(Point{x: 1.5, y: 1.0}).to_string(), r#"<Point x="1.5"/>"#);
+    let point = Point::<f64>::from_xml(&"<Point/>".parse().unwrap()).unwrap();
+    assert_eq!((point.x, point.y), (0.0, 0.0));
+}
+
+#[test]
+fn conversion_functions() {
+    use simple_xml_serialize::FromXml;
+
+    mod fmt {
+        use simple_xml_serialize::{XMLElement, FromXmlError};
+        use simple_xml_serialize::from_xml::ElementReader;
+        use simple_xml_serialize_macro::xml_element;
+
+        #[xml_element("Stamp", from_xml)]
+        pub struct Stamp {
+            #[sxs_type_text]
+            pub seconds: u32,
+        }
+
+        pub fn lat(lat: &f32) -> String {
+            format!("{:.3}N", lat)
+        }
+
+        pub fn stamp(stamp: &Stamp) -> XMLElement {
+            XMLElement::new("Stamp").text(format!("{:04}", stamp.seconds))
+        }
+
+        // `Date` has neither `ToString` nor `FromStr`, it is only written `with` and read `from_with` these
+        #[derive(PartialEq,Debug)]
+        pub struct Date {
+            pub year: u16,
+            pub month: u8,
+            pub day: u8,
+        }
+
+        pub fn iso_date(date: &Date) -> String {
+            format!("{:04}-{:02}-{:02}", date.year, date.month, date.day)
+        }
+
+        pub fn parse_iso_date(value: &str) -> Result<Date, String> {
+            let parts: Vec<u16> = value.split('-').filter_map(|part| part.parse().ok()).collect();
+            match parts[..] {
+                [year, month, day] => Ok(Date{year, month: month as u8, day: day as u8}),
+                _ => Err("expected a date like `2024-01-31`".to_string()),
+            }
+        }
+
+        pub fn date_element(date: &Date) -> XMLElement {
+            XMLElement::new("Date").attr("y", date.year).attr("m", date.month).attr("d", date.day)
+        }
+
+        pub fn read_date_element(element: &XMLElement) -> Result<Date, FromXmlError> {
+            let mut reader = ElementReader::new(element);
+            let date = Date{year: reader.attr("y")?, month: reader.attr("m")?, day: reader.attr("d")?};
+            reader.finish()?;
+            Ok(date)
+        }
+    }
+    use fmt::{Stamp, Date};
+
+    #[xml_element("Reading")]
+    struct Reading {
+        #[sxs_type_attr(with="fmt::lat")]
+        lat: f32,
+        #[sxs_type_attr(format="{:.2}")]
+        level: f64,
+        #[sxs_type_attr(bool_as="numeric")]
+        valid: bool,
+        #[sxs_type_element(rename="taken", with="fmt::stamp")]
+        taken: Stamp,
+        #[sxs_type_text(format="{:>4}")]
+        label: u32,
+    }
+
+    let reading = Reading{lat: 43.38, level: 1.0 / 3.0, valid: true, taken: Stamp{seconds: 7}, label: 12};
+    let expected = r#"<Reading lat="43.380N" level="0.33" valid="1"><taken>0007</taken>  12</Reading>"#;
+    assert_eq!(XMLElement::from(&reading).to_string(), expected);
+
+    #[xml_element("Visit", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Visit {
+        #[sxs_type_attr(with="fmt::iso_date", from_with="fmt::parse_iso_date")]
+        on: Date,
+        #[sxs_type_attr(with="fmt::iso_date", from_with="fmt::parse_iso_date")]
+        until: Option<Date>,
+        #[sxs_type_element(rename="booked", with="fmt::date_element", from_with="fmt::read_date_element")]
+        booked: Date,
+        #[sxs_type_text(from_with="fmt::parse_iso_date", with="fmt::iso_date")]
+        note: Date,
+    }
+
+    let visit = Visit{
+        on: Date{year: 2024, month: 1, day: 31},
+        until: None,
+        booked: Date{year: 2023, month: 12, day: 1},
+        note: Date{year: 2024, month: 2, day: 2},
+    };
+    let xml = XMLElement::from(&visit);
+    assert_eq!(xml.to_string(), r#"<Visit on="2024-01-31"><booked y="2023" m="12" d="1"/>2024-02-02</Visit>"#);
+    assert_eq!(Visit::from_xml(&xml), Ok(visit));
+
+    let xml = r#"<Visit on="2024-01-31" until="2024-02"><booked y="1" m="1" d="1"/>2024-02-02</Visit>"#.parse().unwrap();
+    assert_eq!(Visit::from_xml(&xml).unwrap_err().to_string(),
+               "/Visit: value `2024-02` of attribute `until` is not valid: expected a date like `2024-01-31`");
+
+    #[derive(ToXml,PartialEq,Debug)]
+    #[xml(from_xml)]
+    struct Flags {
+        #[xml(attr, bool_as = "numeric")]
+        on: bool,
+        #[xml(attr, bool_as = "numeric", skip_if_default)]
+        dirty: bool,
+        #[xml(text, format = "{:.1}")]
+        ratio: f32,
+    }
+
+    let flags = Flags{on: false, dirty: false, ratio: 0.25};
+    let xml = XMLElement::from(&flags);
+    assert_eq!(xml.to_string(), r#"<Flags on="0">0.2</Flags>"#);
+    assert_eq!(Flags::from_xml(&xml), Ok(Flags{ratio: 0.2, ..flags}));
+    assert_eq!(Flags::from_xml(&r#"<Flags on="true" dirty="1">1</Flags>"#.parse().unwrap()),
+               Ok(Flags{on: true, dirty: true, ratio: 1.0}));
+    assert_eq!(Flags::from_xml(&r#"<Flags on="yes">1</Flags>"#.parse().unwrap()).unwrap_err().to_string(),
+               "/Flags: value `yes` of attribute `on` is not valid: expected `1` or `0`");
+}
+
+#[test]
+fn option_fields() {
+    use simple_xml_serialize::FromXml;
+
+    type MaybeCount = Option<u32>;
+
+    #[xml_element("Tag", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Tag {
+        #[sxs_type_text]
+        text: String,
+    }
+
+    #[xml_element("Listing", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Listing {
+        #[sxs_type_attr]
+        id: std::option::Option<u32>,
+        #[sxs_type_attr(optional)]
+        count: MaybeCount,
+        #[sxs_type_multi_element(rename="Tag")]
+        tags: Option<Vec<Tag>>,
+        #[sxs_type_multi_element(rename="Slot")]
+        slots: Vec<Option<Tag>>,
+    }
+
+    let tag = |text: &str| Tag{text: text.to_string()};
+    let listing = Listing{id: None, count: Some(3), tags: Some(vec![tag("a")]), slots: vec![Some(tag("b")), None, Some(tag("c"))]};
+    let xml = XMLElement::from(&listing);
+    assert_eq!(xml.to_string(), r#"<Listing count="3"><Tag>a</Tag><Slot>b</Slot><Slot>c</Slot></Listing>"#);
+    assert_eq!(Listing::from_xml(&xml), Ok(Listing{slots: vec![Some(tag("b")), Some(tag("c"))], ..listing}));
+
+    let listing = Listing{id: Some(1), count: None, tags: None, slots: vec![None]};
+    let xml = XMLElement::from(&listing);
+    assert_eq!(xml.to_string(), r#"<Listing id="1"/>"#);
+    assert_eq!(Listing::from_xml(&xml), Ok(Listing{slots: vec![], ..listing}));
+
+    // a type of our own that happens to be called `Option` is written like any other
+    mod own {
+        use simple_xml_serialize_macro::xml_element;
+
+        pub struct Option(pub &'static str);
+
+        impl std::fmt::Display for Option {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl std::str::FromStr for Option {
+            type Err = String;
+
+            fn from_str(_: &str) -> Result<Self, Self::Err> {
+                Ok(Option("read"))
+            }
+        }
+
+        #[xml_element("Choice", from_xml)]
+        pub struct Choice {
+            #[sxs_type_attr]
+            pub picked: Option,
+        }
+    }
+    let choice = own::Choice{picked: own::Option("yes")};
+    assert_eq!(XMLElement::from(&choice).to_string(), r#"<Choice picked="yes"/>"#);
+
+    // with `optional`, the values of an alias are its type argument, for the bounds and the schema alike
+    type Maybe<T> = Option<T>;
+
+    #[xml_element("Reading", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Reading<T> {
+        #[sxs_type_attr(optional)]
+        value: Maybe<T>,
+        #[sxs_type_attr(optional)]
+        level: Maybe<u8>,
+        #[sxs_type_element(optional)]
+        tag: Maybe<Tag>,
+        #[sxs_type_multi_element(optional, rename="Tag")]
+        history: Maybe<Vec<Option<Tag>>>,
+    }
+
+    let reading = Reading{value: Some(1.5), level: None, tag: None, history: Some(vec![None, Some(tag("h"))])};
+    let xml = XMLElement::from(&reading);
+    assert_eq!(xml.to_string(), r#"<Reading value="1.5"><Tag>h</Tag></Reading>"#);
+    assert_eq!(Reading::from_xml(&xml), Ok(Reading{tag: Some(tag("h")), history: None, ..reading}));
+
+    let xsd = simple_xml_serialize::xsd::assemble(&[Reading::<f64>::xml_schema()]).to_string();
+    assert!(xsd.contains(r#"<xs:attribute name="level" type="xs:unsignedByte" use="optional"/>"#), "{}", xsd);
+}
+
+#[test]
+fn wrapped_multi_elements() {
+    use simple_xml_serialize::FromXml;
+    use simple_xml_serialize::xsd::{assemble, Schema};
+
+    #[xml_element("Name", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Name {
+        #[sxs_type_text]
+        text: String,
+    }
+
+    #[xml_element("Person", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Person {
+        #[sxs_type_multi_element(wrapper="Names")]
+        names: Vec<Name>,
+        #[sxs_type_multi_element(wrapper="Aliases", rename="Alias", empty_wrapper)]
+        aliases: Vec<Name>,
+        #[sxs_type_multi_element(wrapper="Titles", rename="Title")]
+        titles: Option<Vec<Name>>,
+    }
+
+    let name = |text: &str| Name{text: text.to_string()};
+    let person = Person{names: vec![name("Ann"), name("Bo")], aliases: vec![], titles: None};
+    let xml = XMLElement::from(&person);
+    assert_eq!(xml.to_string(), "<Person><Names><Name>Ann</Name><Name>Bo</Name></Names><Aliases/></Person>");
+    assert_eq!(Person::from_xml(&xml), Ok(person));
+
+    let person = Person{names: vec![], aliases: vec![name("Al")], titles: Some(vec![name("Dr")])};
+    let xml = XMLElement::from(&person);
+    assert_eq!(xml.to_string(), "<Person><Aliases><Alias>Al</Alias></Aliases><Titles><Title>Dr</Title></Titles></Person>");
+    assert_eq!(Person::from_xml(&xml), Ok(person));
+
+    let xsd = assemble(&[Person::xml_schema()]).to_string();
+    assert!(xsd.contains(r#"<xs:element name="Names" type="PersonNames" minOccurs="0"/>"#));
+    assert!(xsd.contains(r#"<xs:element name="Aliases" type="PersonAliases"/>"#));
+    assert!(xsd.contains(r#"<xs:complexType name="PersonAliases"><xs:sequence><xs:element name="Alias" type="Name" minOccurs="0" maxOccurs="unbounded"/>"#));
+    let schema = Schema::parse(&xsd).unwrap();
+    assert_eq!(schema.validate(&xml), Ok(()));
+    let missing = "<Person><Names><Name>Ann</Name></Names></Person>".parse().unwrap();
+    assert!(schema.validate(&missing).is_err());
+}
+
+#[test]
+fn flattened_fields() {
+    use simple_xml_serialize::FromXml;
+    use simple_xml_serialize::xsd::{assemble, Schema};
+
+    #[xml_element("Ids", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Ids {
+        #[sxs_type_attr]
+        id: u32,
+    }
+
+    #[xml_element("Audit", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Audit {
+        #[sxs_flatten]
+        ids: Ids,
+        #[sxs_type_attr(rename="by")]
+        created_by: String,
+        #[sxs_type_element]
+        note: Note,
+    }
+
+    #[xml_element("Note", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Note {
+        #[sxs_type_text]
+        text: String,
+    }
+
+    #[xml_element("Invoice", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Invoice {
+        #[sxs_type_attr]
+        total: u32,
+        #[sxs_flatten]
+        audit: Audit,
+        #[sxs_type_text]
+        memo: String,
+    }
+
+    let invoice = Invoice{
+        total: 12,
+        audit: Audit{ids: Ids{id: 3}, created_by: "ann".to_string(), note: Note{text: "checked".to_string()}},
+        memo: "paid".to_string(),
+    };
+    let xml = XMLElement::from(&invoice);
+    assert_eq!(xml.to_string(), r#"<Invoice total="12" by="ann" id="3"><Note>checked</Note>paid</Invoice>"#);
+    assert_eq!(Invoice::from_xml(&xml), Ok(invoice));
+    assert_eq!(Invoice::from_xml(&r#"<Invoice total="1" by="bo"><Note>n</Note>m</Invoice>"#.parse().unwrap()).unwrap_err().to_string(),
+               "/Invoice: missing attribute `id`");
+
+    let xsd = assemble(&[Invoice::xml_schema()]).to_string();
+    assert!(xsd.contains(r#"<xs:attribute name="id" type="xs:unsignedInt" use="required"/>"#));
+    assert_eq!(Schema::parse(&xsd).unwrap().validate(&xml), Ok(()));
+
+    #[derive(ToXml,PartialEq,Debug)]
+    #[xml(from_xml)]
+    struct Tagged(#[xml(flatten)] Ids, #[xml(attr, rename = "tag")] String);
+
+    let tagged = Tagged(Ids{id: 9}, "x".to_string());
+    let xml = XMLElement::from(&tagged);
+    assert_eq!(xml.to_string(), r#"<Tagged tag="x" id="9"/>"#);
+    assert_eq!(Tagged::from_xml(&xml), Ok(tagged));
+}
+
+#[test]
+fn map_fields() {
+    use std::collections::{BTreeMap, HashMap};
+    use simple_xml_serialize::FromXml;
+    use simple_xml_serialize::xsd::{assemble, Schema};
+
+    #[xml_element("Note", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Note {
+        #[sxs_type_text]
+        text: String,
+    }
+
+    #[xml_element("Config", schema, from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Config {
+        #[sxs_type_map(entry="Property", key_attr="name", value="text", sorted)]
+        properties: HashMap<String, u32>,
+        #[sxs_type_map(entry="Note", value="element")]
+        notes: BTreeMap<u8, Note>,
+        #[sxs_type_map(entry="Tag")]
+        tags: Option<HashMap<String, String>>,
+    }
+
+    let mut properties = HashMap::new();
+    properties.insert("port".to_string(), 80);
+    properties.insert("limit".to_string(), 5);
+    let mut notes = BTreeMap::new();
+    notes.insert(2, Note{text: "b".to_string()});
+    notes.insert(1, Note{text: "a".to_string()});
+    let config = Config{properties, notes, tags: None};
+    let xml = XMLElement::from(&config);
+    assert_eq!(xml.to_string(), concat!(
+        r#"<Config><Property name="limit">5</Property><Property name="port">80</Property>"#,
+        r#"<Note key="1"><Note>a</Note></Note><Note key="2"><Note>b</Note></Note></Config>"#));
+    assert_eq!(Config::from_xml(&xml), Ok(config));
+
+    let xsd = assemble(&[Config::xml_schema()]).to_string();
+    assert!(xsd.contains(r#"<xs:attribute name="name" type="xs:string" use="required"/>"#));
+    assert_eq!(Schema::parse(&xsd).unwrap().validate(&xml), Ok(()));
+
+    let xml: XMLElement = r#"<Config><Tag key="a">x</Tag><Property name="p">nope</Property></Config>"#.parse().unwrap();
+    assert_eq!(Config::from_xml(&xml).unwrap_err().to_string(),
+               "/Config/Property: text `nope` is not valid: invalid digit found in string");
+
+    #[xml_element("Labels", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Labels {
+        #[sxs_type_attr]
+        id: u32,
+        #[sxs_type_map(attrs, sorted)]
+        labels: HashMap<String, String>,
+    }
+
+    let mut labels = HashMap::new();
+    labels.insert("team".to_string(), "core".to_string());
+    labels.insert("env".to_string(), "prod".to_string());
+    let labelled = Labels{id: 4, labels};
+    let xml = XMLElement::from(&labelled);
+    assert_eq!(xml.to_string(), r#"<Labels id="4" env="prod" team="core"/>"#);
+    assert_eq!(Labels::from_xml(&xml), Ok(labelled));
+
+    // a flattened group reads its attributes before the map takes the rest
+    #[xml_element("Audit", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Audit {
+        #[sxs_type_attr]
+        created: u32,
+        #[sxs_type_attr(rename="by")]
+        created_by: String,
+    }
+
+    #[xml_element("Document", from_xml)]
+    #[derive(PartialEq,Debug)]
+    struct Document {
+        #[sxs_type_map(attrs)]
+        labels: BTreeMap<String, String>,
+        #[sxs_flatten]
+        audit: Audit,
+    }
+
+    let mut labels = BTreeMap::new();
+    labels.insert("team".to_string(), "core".to_string());
+    let document = Document{labels, audit: Audit{created: 20240131, created_by: "ann".to_string()}};
+    let xml = XMLElement::from(&document);
+    assert_eq!(xml.to_string(), r#"<Document team="core" created="20240131" by="ann"/>"#);
+    assert_eq!(Document::from_xml(&xml), Ok(document));
+
+    #[derive(ToXml,PartialEq,Debug)]
+    #[xml(from_xml)]
+    struct Counts(#[xml(map, entry = "Count")] BTreeMap<String, u8>);
+
+    let mut counts = BTreeMap::new();
+    counts.insert("x".to_string(), 1);
+    let counts = Counts(counts);
+    let xml = XMLElement::from(&counts);
+    assert_eq!(xml.to_string(), r#"<Counts><Count key="x">1</Count></Counts>"#);
+    assert_eq!(Counts::from_xml(&xml), Ok(counts));
+}
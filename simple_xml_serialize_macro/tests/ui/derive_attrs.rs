@@ -0,0 +1,22 @@
+use simple_xml_serialize_macro::ToXml;
+
+#[derive(ToXml)]
+#[xml(nam = "Point", schem)]
+struct Point {
+    #[xml(atr)]
+    lat: f32,
+    #[xml(attr, renam = "longitude")]
+    lon: f32,
+    #[xml(attr, text)]
+    alt: f32,
+    #[xml]
+    name: String,
+}
+
+#[derive(ToXml)]
+enum Status {
+    #[xml(name = "open")]
+    Open,
+}
+
+fn main() {}
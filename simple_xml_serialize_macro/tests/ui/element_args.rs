@@ -0,0 +1,35 @@
+use simple_xml_serialize_macro::xml_element;
+
+#[xml_element(1)]
+struct NameNotString {
+    #[sxs_type_attr]
+    id: u32,
+}
+
+#[xml_element(rename_all_attrs = "Camel", rename_all_elements = 1)]
+struct UnknownCase {
+    #[sxs_type_attr]
+    id: u32,
+}
+
+#[xml_element("Unknown", schem)]
+struct UnknownOption {
+    #[sxs_type_attr]
+    id: u32,
+}
+
+#[xml_element("Tagged", tag="kind")]
+struct TagOnStruct {
+    #[sxs_type_attr]
+    id: u32,
+}
+
+#[xml_element("Schema", schema)]
+enum SchemaOnEnum {
+    A,
+}
+
+#[xml_element("Function")]
+fn function() {}
+
+fn main() {}
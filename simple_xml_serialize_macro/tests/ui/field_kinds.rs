@@ -0,0 +1,70 @@
+use simple_xml_serialize_macro::xml_element;
+
+#[xml_element("Point")]
+struct Point {
+    #[sxs_type_attr]
+    #[sxs_type_element]
+    lat: f32,
+    #[sxs_type_text]
+    lon: f32,
+    #[sxs_type_text]
+    name: String,
+}
+
+#[xml_element("Pair")]
+struct Pair(#[sxs_type_attr] f32, #[sxs_type_attr(rename="y")] f32);
+
+#[xml_element("Shape")]
+enum Shape {
+    Line(f32, f32),
+}
+
+#[xml_element("Reading")]
+struct Reading {
+    #[sxs_type_attr(with="fmt_lat", format="{:.2}")]
+    lat: f32,
+    #[sxs_type_attr(bool_as="yes_no")]
+    valid: bool,
+    #[sxs_type_element(format="{:.2}")]
+    level: f64,
+    #[sxs_type_multi_element(with="fmt_lat")]
+    history: Vec<f32>,
+}
+
+#[xml_element("Log", from_xml)]
+struct Log {
+    #[sxs_type_element(from_with="read_stamp")]
+    taken: Reading,
+    #[sxs_type_attr(from_with="parse_flag", bool_as="numeric")]
+    flag: bool,
+    #[sxs_type_multi_element(from_with="read_stamp")]
+    history: Vec<Reading>,
+}
+
+#[xml_element("Person")]
+struct Person {
+    #[sxs_type_attr(wrapper="Names")]
+    name: String,
+    #[sxs_type_multi_element(empty_wrapper)]
+    aliases: Vec<String>,
+}
+
+#[xml_element("Invoice")]
+struct Invoice {
+    #[sxs_flatten(rename="audit")]
+    audit: Person,
+    #[sxs_flatten]
+    extra: Option<Person>,
+}
+
+#[xml_element("Config", schema)]
+struct Config {
+    #[sxs_type_map(key_attr="name")]
+    properties: std::collections::HashMap<String, String>,
+    #[sxs_type_attr(entry="Item")]
+    item: String,
+    #[sxs_type_map(attrs, entry="Label", value="json")]
+    labels: std::collections::HashMap<String, String>,
+}
+
+fn main() {}
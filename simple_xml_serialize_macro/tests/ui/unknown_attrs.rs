@@ -0,0 +1,31 @@
+use simple_xml_serialize_macro::xml_element;
+
+#[xml_element("Point")]
+struct Point {
+    #[sxs_type_atr]
+    lat: f32,
+    #[sxs_type_attr(renam="lon")]
+    longitude: f32,
+    #[sxs_type_attr(rename=1)]
+    alt: f32,
+    #[sxs_type_element("Name")]
+    name: String,
+}
+
+#[xml_element("Status")]
+enum Status {
+    #[sxs_variant(name="open")]
+    Open,
+    #[sxs_type_attr]
+    Closed,
+}
+
+#[xml_element("Skipped")]
+struct Skipped {
+    #[sxs_type_attr(skip_if="not a path")]
+    a: u32,
+    #[sxs_type_attr(skip_if_defualt)]
+    b: u32,
+}
+
+fn main() {}
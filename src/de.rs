@@ -0,0 +1,644 @@
+/*!
+A serde `Deserializer` reading any `T: Deserialize` from an `XMLElement`, enabled with the `serde` feature.
+
+It follows the same conventions as the `ser` module, so values written with `ser::to_element` read back unchanged:
+
+* Struct fields are read from child elements of the same name, or from attributes when the field name starts with `@`.
+* A field named `$value` reads the text of the element, or the children no other field asked for.
+* Sequences are read from every child element with the field's name. Inside attributes and text they are read
+  from a space separated list.
+* An enum is read from a child element named after the variant, or from text holding the name of a unit variant.
+  At the root, the root element's name picks the variant.
+
+As no elements are written for an empty sequence, sequence fields need `#[serde(default)]` to read them back.
+Unknown attributes and elements are passed on to serde, which ignores them unless the type is marked with
+`#[serde(deny_unknown_fields)]`. Errors give the path to the element with the problem.
+
+```rust
+# #[cfg(feature = "serde")] {
+use serde::Deserialize;
+use simple_xml_serialize::XMLElement;
+use simple_xml_serialize::de::from_element;
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Order {
+    #[serde(rename = "@id")]
+    id: u32,
+    #[serde(default)]
+    item: Vec<Item>,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Item {
+    #[serde(rename = "@sku")]
+    sku: String,
+    #[serde(rename = "$value")]
+    quantity: u32,
+}
+
+let xml: XMLElement = r#"<Order id="7"><item sku="a1">2</item><item sku="b2">5</item></Order>"#.parse().unwrap();
+let order: Order = from_element(&xml).unwrap();
+assert_eq!(order.item[1], Item{sku: "b2".to_string(), quantity: 5});
+
+let xml: XMLElement = r#"<Order id="7"><item sku="a1">2</item><item sku="b2">lots</item></Order>"#.parse().unwrap();
+let error = from_element::<Order>(&xml).unwrap_err();
+assert_eq!(error.to_string(), "/Order/item[2]: value `lots` of text is not a valid u32");
+# }
+```
+*/
+
+use std::error;
+use std::fmt;
+
+use serde::de::{self, Deserialize, DeserializeSeed, IntoDeserializer, Visitor};
+
+use crate::ser::{ATTR_PREFIX, TEXT_KEY};
+use crate::XMLElement;
+
+/// Why an element could not be read into a value
+#[derive(Clone,PartialEq,Debug)]
+pub struct Error {
+    /// The path to the element with the problem, IE `/order/item[2]`. Empty when it is not known.
+    pub path: String,
+    pub message: String,
+}
+
+impl Error {
+    fn new(path: &str, message: impl ToString) -> Self {
+        Error{path: path.to_string(), message: message.to_string()}
+    }
+
+    /// Fills in the path of an error raised by a `Visitor`, which does not know where it is
+    fn at(mut self, path: &str) -> Self {
+        if self.path.is_empty() {
+            self.path = path.to_string();
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::new("", msg)
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        match field.strip_prefix(ATTR_PREFIX) {
+            Some(attr) => Error::new("", format!("missing attribute `{}`", attr)),
+            None if field == TEXT_KEY => Error::new("", "missing text"),
+            None => Error::new("", format!("missing element `{}`", field)),
+        }
+    }
+}
+
+/// Reads a value out of an element
+/// # Arguments
+///
+/// * `element` - The element to read. Its name is only used to choose the variant when reading an enum.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "serde")] {
+/// # use serde::Deserialize;
+/// use simple_xml_serialize::XMLElement;
+/// use simple_xml_serialize::de::from_element;
+///
+/// #[derive(Deserialize)]
+/// struct Point {
+///     #[serde(rename = "@lat")]
+///     lat: f32,
+/// }
+/// let point: Point = from_element(&XMLElement::new("Point").attr("lat", 43.38)).unwrap();
+/// assert_eq!(point.lat, 43.38);
+/// # }
+/// ```
+pub fn from_element<'de, T: Deserialize<'de>>(element: &'de XMLElement) -> Result<T, Error> {
+    T::deserialize(NodeDe::root(element))
+}
+
+/// What part of the tree a value is read from
+#[derive(Clone,Debug)]
+enum Kind<'de> {
+    /// A whole element. With `named_variant` the element's own name picks the variant of an enum.
+    Element(&'de XMLElement, bool),
+    /// An attribute value or the text of an element, with a description for errors
+    Text(&'de str, String),
+    /// Every child element with the same name, with their paths
+    Children(&'de str, Vec<(&'de XMLElement, String)>),
+    /// The text of an element along with the children no other field read, for `$value`
+    Content(&'de XMLElement, Vec<usize>),
+    Unit,
+}
+
+#[derive(Clone,Debug)]
+struct NodeDe<'de> {
+    /// The path of the element this value is in
+    path: String,
+    kind: Kind<'de>,
+}
+
+fn blank(text: &str) -> bool {
+    text.trim().is_empty()
+}
+
+/// The path of the child at `index` in `parent`, with an index when it shares its name with a sibling
+fn child_path(parent_path: &str, parent: &XMLElement, index: usize) -> String {
+    let contents = parent.contents.as_deref().unwrap_or(&[]);
+    let name = &contents[index].name;
+    let mut path = format!("{}/{}", parent_path, name);
+    if contents.iter().filter(|c| &c.name == name).count() > 1 {
+        let nth = contents[..index].iter().filter(|c| &c.name == name).count() + 1;
+        path.push_str(&format!("[{}]", nth));
+    }
+    path
+}
+
+impl<'de> NodeDe<'de> {
+    fn root(element: &'de XMLElement) -> Self {
+        NodeDe{path: format!("/{}", element.name), kind: Kind::Element(element, true)}
+    }
+
+    fn element(element: &'de XMLElement, path: String, named_variant: bool) -> Self {
+        NodeDe{path, kind: Kind::Element(element, named_variant)}
+    }
+
+    fn error(&self, message: impl ToString) -> Error {
+        Error::new(&self.path, message)
+    }
+
+    /// Turns a group of children holding a single element into that element, as anything but a sequence needs one
+    fn single(self) -> Result<Self, Error> {
+        match self.kind {
+            Kind::Children(name, mut items) => {
+                if items.len() > 1 {
+                    return Err(Error::new(&self.path, format!("element `{}` appears {} times, but only one was expected", name, items.len())));
+                }
+                match items.pop() {
+                    Some((element, path)) => Ok(NodeDe::element(element, path, false)),
+                    None => Ok(NodeDe{path: self.path, kind: Kind::Unit}),
+                }
+            },
+            _ => Ok(self),
+        }
+    }
+
+    /// The text a simple value is read from, and what it is for errors
+    fn text(&self) -> (&'de str, String) {
+        match &self.kind {
+            Kind::Element(element, _) | Kind::Content(element, _) => (element.text.as_deref().unwrap_or(""), "text".to_string()),
+            Kind::Text(text, what) => (text, what.clone()),
+            Kind::Children(..) | Kind::Unit => ("", "text".to_string()),
+        }
+    }
+
+    fn parse<T: std::str::FromStr>(self, type_name: &str) -> Result<T, Error> {
+        let node = self.single()?;
+        let (text, what) = node.text();
+        text.trim().parse().map_err(|_| node.error(format!("value `{}` of {} is not a valid {}", text, what, type_name)))
+    }
+
+    /// The items of a sequence read from space separated text
+    fn words(&self, text: &'de str) -> Vec<NodeDe<'de>> {
+        text.split_whitespace().map(|w| NodeDe{path: self.path.clone(), kind: Kind::Text(w, "list item".to_string())}).collect()
+    }
+
+    fn items(self) -> Vec<NodeDe<'de>> {
+        match &self.kind {
+            Kind::Children(_, items) => items.iter().map(|(e, p)| NodeDe::element(e, p.clone(), false)).collect(),
+            Kind::Text(text, _) => self.words(text),
+            Kind::Element(element, _) if element.contents.as_ref().is_some_and(|c| !c.is_empty()) => {
+                let contents = element.contents.as_deref().unwrap_or(&[]);
+                (0..contents.len()).map(|i| NodeDe::element(&contents[i], child_path(&self.path, element, i), true)).collect()
+            },
+            Kind::Element(element, _) => self.words(element.text.as_deref().unwrap_or("")),
+            Kind::Content(element, unused) => {
+                let contents = element.contents.as_deref().unwrap_or(&[]);
+                let mut items: Vec<NodeDe<'de>> = unused.iter().map(|i| NodeDe::element(&contents[*i], child_path(&self.path, element, *i), true)).collect();
+                items.extend(self.words(element.text.as_deref().unwrap_or("")));
+                items
+            },
+            Kind::Unit => Vec::new(),
+        }
+    }
+
+    /// The fields of an element as map entries. `fields` are the names a struct asked for, used to tell
+    /// which children belong to `$value`.
+    fn entries(&self, element: &'de XMLElement, fields: Option<&[&str]>) -> Vec<(String, NodeDe<'de>)> {
+        let mut entries = Vec::new();
+        for a in element.attrs.iter().flatten() {
+            let what = format!("attribute `{}`", a.name);
+            entries.push((format!("{}{}", ATTR_PREFIX, a.name), NodeDe{path: self.path.clone(), kind: Kind::Text(&a.value, what)}));
+        }
+
+        let contents = element.contents.as_deref().unwrap_or(&[]);
+        let wants_content = fields.is_some_and(|f| f.contains(&TEXT_KEY));
+        let mut content = Vec::new();
+        let mut groups: Vec<(&'de str, Vec<(&'de XMLElement, String)>)> = Vec::new();
+        for (i, child) in contents.iter().enumerate() {
+            if wants_content && !fields.is_some_and(|f| f.contains(&child.name.as_str())) {
+                content.push(i);
+                continue;
+            }
+            let path = child_path(&self.path, element, i);
+            match groups.iter_mut().find(|(name, _)| *name == child.name) {
+                Some((_, items)) => items.push((child, path)),
+                None => groups.push((&child.name, vec![(child, path)])),
+            }
+        }
+        for (name, items) in groups {
+            entries.push((name.to_string(), NodeDe{path: self.path.clone(), kind: Kind::Children(name, items)}));
+        }
+
+        if wants_content {
+            entries.push((TEXT_KEY.to_string(), NodeDe{path: self.path.clone(), kind: Kind::Content(element, content)}));
+        } else if let Some(text) = element.text.as_deref().filter(|t| !blank(t)) {
+            entries.push((TEXT_KEY.to_string(), NodeDe{path: self.path.clone(), kind: Kind::Text(text, "text".to_string())}));
+        }
+        entries
+    }
+
+    fn visit_entries<V: Visitor<'de>>(self, fields: Option<&[&str]>, visitor: V) -> Result<V::Value, Error> {
+        let node = self.single()?;
+        let entries = match &node.kind {
+            Kind::Element(element, _) | Kind::Content(element, _) => node.entries(element, fields),
+            Kind::Unit => Vec::new(),
+            Kind::Text(_, what) => return Err(node.error(format!("{} cannot hold a struct or map", what))),
+            Kind::Children(..) => unreachable!(),
+        };
+        visitor.visit_map(MapDe{entries: entries.into_iter(), value: None}).map_err(|e: Error| e.at(&node.path))
+    }
+
+    /// The variant of an enum and the node holding its data
+    fn variant(self) -> Result<(&'de str, NodeDe<'de>), Error> {
+        let node = self.single()?;
+        let unit = |path: &str| NodeDe{path: path.to_string(), kind: Kind::Unit};
+        match &node.kind {
+            Kind::Element(element, true) => Ok((&element.name, NodeDe::element(element, node.path.clone(), false))),
+            Kind::Element(element, false) => {
+                let contents = element.contents.as_deref().unwrap_or(&[]);
+                match contents.len() {
+                    0 => Ok((element.text.as_deref().unwrap_or("").trim(), unit(&node.path))),
+                    1 => Ok((&contents[0].name, NodeDe::element(&contents[0], child_path(&node.path, element, 0), false))),
+                    n => Err(node.error(format!("an enum is held by a single element, found {}", n))),
+                }
+            },
+            Kind::Content(element, unused) => match unused.first() {
+                Some(i) => {
+                    let child = &element.contents.as_deref().unwrap_or(&[])[*i];
+                    Ok((&child.name, NodeDe::element(child, child_path(&node.path, element, *i), false)))
+                },
+                None => Ok((element.text.as_deref().unwrap_or("").trim(), unit(&node.path))),
+            },
+            Kind::Text(text, _) => Ok((text.trim(), unit(&node.path))),
+            Kind::Unit => Err(node.error("missing enum variant")),
+            Kind::Children(..) => unreachable!(),
+        }
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident $type:ty,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let path = self.path.clone();
+                let value: $type = self.parse(stringify!($type))?;
+                visitor.$visit(value).map_err(|e: Error| e.at(&path))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for NodeDe<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let node = match self.kind {
+            Kind::Children(_, ref items) if items.len() > 1 => return self.deserialize_seq(visitor),
+            _ => self.single()?,
+        };
+        let path = node.path.clone();
+        match &node.kind {
+            Kind::Element(element, _) if element.attrs.is_none() && element.contents.is_none() => match element.text.as_deref() {
+                Some(text) => visitor.visit_borrowed_str(text),
+                None => visitor.visit_unit(),
+            },
+            Kind::Element(..) => node.deserialize_map(visitor),
+            Kind::Content(_, unused) if !unused.is_empty() => node.deserialize_seq(visitor),
+            Kind::Content(element, _) => visitor.visit_borrowed_str(element.text.as_deref().unwrap_or("")),
+            Kind::Text(text, _) => visitor.visit_borrowed_str(text),
+            Kind::Unit => visitor.visit_unit(),
+            Kind::Children(..) => unreachable!(),
+        }.map_err(|e: Error| e.at(&path))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let node = self.single()?;
+        let (text, what) = node.text();
+        let value = match text.trim() {
+            "true" | "1" => true,
+            "false" | "0" => false,
+            _ => return Err(node.error(format!("value `{}` of {} is not a valid bool", text, what))),
+        };
+        visitor.visit_bool(value).map_err(|e: Error| e.at(&node.path))
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8 i8,
+        deserialize_i16 => visit_i16 i16,
+        deserialize_i32 => visit_i32 i32,
+        deserialize_i64 => visit_i64 i64,
+        deserialize_i128 => visit_i128 i128,
+        deserialize_u8 => visit_u8 u8,
+        deserialize_u16 => visit_u16 u16,
+        deserialize_u32 => visit_u32 u32,
+        deserialize_u64 => visit_u64 u64,
+        deserialize_u128 => visit_u128 u128,
+        deserialize_f32 => visit_f32 f32,
+        deserialize_f64 => visit_f64 f64,
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let node = self.single()?;
+        let (text, what) = node.text();
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c).map_err(|e: Error| e.at(&node.path)),
+            _ => Err(node.error(format!("value `{}` of {} is not a single character", text, what))),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let node = self.single()?;
+        visitor.visit_borrowed_str(node.text().0).map_err(|e: Error| e.at(&node.path))
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let node = self.single()?;
+        visitor.visit_borrowed_bytes(node.text().0.as_bytes()).map_err(|e: Error| e.at(&node.path))
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let present = match &self.kind {
+            Kind::Content(element, unused) => !unused.is_empty() || element.text.as_deref().is_some_and(|t| !blank(t)),
+            Kind::Children(_, items) => !items.is_empty(),
+            Kind::Unit => false,
+            Kind::Element(..) | Kind::Text(..) => true,
+        };
+        let path = self.path.clone();
+        match present {
+            true => visitor.visit_some(self),
+            false => visitor.visit_none(),
+        }.map_err(|e: Error| e.at(&path))
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit().map_err(|e: Error| e.at(&self.path))
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        let path = self.path.clone();
+        visitor.visit_newtype_struct(self).map_err(|e: Error| e.at(&path))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let path = self.path.clone();
+        visitor.visit_seq(SeqDe{items: self.items().into_iter()}).map_err(|e: Error| e.at(&path))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.visit_entries(None, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        self.visit_entries(Some(fields), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        let path = self.path.clone();
+        let (variant, content) = self.variant()?;
+        visitor.visit_enum(EnumDe{variant, content}).map_err(|e: Error| e.at(&path))
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+struct SeqDe<'de> {
+    items: std::vec::IntoIter<NodeDe<'de>>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDe<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        match self.items.next() {
+            Some(item) => seed.deserialize(item).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct MapDe<'de> {
+    entries: std::vec::IntoIter<(String, NodeDe<'de>)>,
+    value: Option<NodeDe<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for MapDe<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value requested before its key")),
+        }
+    }
+}
+
+struct EnumDe<'de> {
+    variant: &'de str,
+    content: NodeDe<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDe<'de> {
+    type Error = Error;
+    type Variant = NodeDe<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, NodeDe<'de>), Error> {
+        let variant: de::value::BorrowedStrDeserializer<'de, Error> = de::value::BorrowedStrDeserializer::new(self.variant);
+        let path = self.content.path.clone();
+        Ok((seed.deserialize(variant).map_err(|e: Error| e.at(&path))?, self.content))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for NodeDe<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        self.visit_entries(Some(fields), visitor)
+    }
+}
+
+macro_rules! forward_to_root {
+    ($($method:ident($($arg:ident: $type:ty),*),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $type,)* visitor: V) -> Result<V::Value, Error> {
+                NodeDe::root(self).$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+/// Reads from the element as the root of a tree, like `from_element`
+impl<'de> de::Deserializer<'de> for &'de XMLElement {
+    type Error = Error;
+
+    forward_to_root! {
+        deserialize_any(), deserialize_bool(), deserialize_i8(), deserialize_i16(), deserialize_i32(), deserialize_i64(),
+        deserialize_i128(), deserialize_u8(), deserialize_u16(), deserialize_u32(), deserialize_u64(), deserialize_u128(),
+        deserialize_f32(), deserialize_f64(), deserialize_char(), deserialize_str(), deserialize_string(),
+        deserialize_bytes(), deserialize_byte_buf(), deserialize_option(), deserialize_unit(),
+        deserialize_unit_struct(name: &'static str), deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(), deserialize_tuple(len: usize), deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(), deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]), deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser::to_element;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Shape {
+        Square,
+        Circle(f32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Drawing {
+        #[serde(rename = "@id")]
+        id: u32,
+        #[serde(rename = "@tags")]
+        tags: Vec<String>,
+        title: Option<String>,
+        subtitle: Option<String>,
+        #[serde(default)]
+        shape: Vec<Shape>,
+        meta: BTreeMap<String, i32>,
+        flag: bool,
+        #[serde(rename = "$value")]
+        content: Vec<Shape>,
+    }
+
+    #[test]
+    fn de_round_trip() {
+        let drawing = Drawing{
+            id: 1,
+            tags: vec!["a".to_string(), "b".to_string()],
+            title: Some("t".to_string()),
+            subtitle: None,
+            shape: vec![Shape::Square, Shape::Circle(1.5)],
+            meta: vec![("k".to_string(), 3)].into_iter().collect(),
+            flag: true,
+            content: vec![Shape::Rect{w: 2, h: 3}, Shape::Square],
+        };
+        let xml = to_element(&drawing).unwrap();
+        assert_eq!(from_element::<Drawing>(&xml).unwrap(), drawing);
+
+        let parsed: XMLElement = xml.to_string().parse().unwrap();
+        assert_eq!(Drawing::deserialize(&parsed).unwrap(), drawing);
+
+        let root = to_element(&Shape::Rect{w: 1, h: 2}).unwrap();
+        assert_eq!(from_element::<Shape>(&root).unwrap(), Shape::Rect{w: 1, h: 2});
+    }
+
+    #[test]
+    fn de_errors() {
+        #[derive(Deserialize, Debug)]
+        #[serde(deny_unknown_fields)]
+        struct Point {
+            #[serde(rename = "@x")]
+            _x: i32,
+            _shape: Option<Shape>,
+        }
+        let error = |xml: &str| from_element::<Point>(&xml.parse().unwrap()).unwrap_err().to_string();
+        assert_eq!(error("<Point/>"), "/Point: missing attribute `x`");
+        assert_eq!(error("<Point x='left'/>"), "/Point: value `left` of attribute `x` is not a valid i32");
+        assert_eq!(error("<Point x='1' y='2'/>"), "/Point: unknown field `@y`, expected `@x` or `_shape`");
+        assert_eq!(error("<Point x='1'><_shape><Circle>big</Circle></_shape></Point>"),
+                   "/Point/_shape/Circle: value `big` of text is not a valid f32");
+        assert_eq!(error("<Point x='1'><_shape>Hexagon</_shape></Point>"),
+                   "/Point/_shape: unknown variant `Hexagon`, expected one of `Square`, `Circle`, `Rect`");
+        assert_eq!(error("<Point x='1'><_shape>Square</_shape><_shape>Square</_shape></Point>"),
+                   "/Point: element `_shape` appears 2 times, but only one was expected");
+    }
+}
@@ -0,0 +1,705 @@
+/*!
+Lossless, format-preserving editing of existing XML documents.
+
+`XMLElement` only keeps what is needed to write a document, so reading a hand-maintained file into
+one and writing it back out loses comments, attribute quoting and whitespace. A `Document` instead
+keeps the original text of everything it read. Only the parts that are edited get regenerated, so a
+document that was not changed serializes back to exactly the input.
+
+```rust
+use simple_xml_serialize::XMLElement;
+use simple_xml_serialize::document::Document;
+
+let input = r#"<?xml version="1.0"?>
+<!-- hand maintained -->
+<project version='1'>
+    <dependency name="a" />
+</project>
+"#;
+let mut doc = Document::parse(input).unwrap();
+assert_eq!(doc.to_string(), input);
+
+doc.root_mut().set_attr("version", 2);
+doc.root_mut().add_element(XMLElement::new("dependency").attr("name", "b"));
+
+let expected = r#"<?xml version="1.0"?>
+<!-- hand maintained -->
+<project version='2'>
+    <dependency name="a" />
+    <dependency name="b"/>
+</project>
+"#;
+assert_eq!(doc.to_string(), expected);
+```
+*/
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::dtd;
+use crate::parser::{self, ParseError, Token, Tokenizer};
+use crate::XMLElement;
+
+/// A parsed XML document that remembers the exact text it was read from
+#[derive(Clone,PartialEq,Debug)]
+pub struct Document {
+    /// Everything before the root element, IE the XML declaration, comments and the doctype
+    pub prolog: Vec<Node>,
+    root: Element,
+    /// Everything after the root element
+    pub epilog: Vec<Node>,
+}
+
+/// One piece of a document. Apart from `Element`, every variant holds its original text unchanged,
+/// including delimiters such as `<!--` and `-->`.
+#[derive(Clone,PartialEq,Debug)]
+pub enum Node {
+    Element(Element),
+    /// Character data with entity references left as they were written
+    Text(String),
+    CData(String),
+    Comment(String),
+    ProcessingInstruction(String),
+    Doctype(String),
+}
+
+/// An attribute along with the text it was read from
+#[derive(Clone,PartialEq,Debug)]
+pub struct Attr {
+    name: String,
+    value: String,
+    /// The whitespace before the attribute name followed by `name="value"` exactly as written
+    raw: String,
+}
+
+impl Attr {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The attribute value with entity references decoded
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+/// An element of a `Document`. Editing functions mirror the ones on `XMLElement`.
+#[derive(Clone,PartialEq,Debug)]
+pub struct Element {
+    name: String,
+    attrs: Vec<Attr>,
+    /// Whitespace between the last attribute and the closing `>` or `/>`
+    tag_tail: String,
+    /// `None` for self-closing elements like `<myelement/>`
+    end_tag: Option<String>,
+    /// The nodes between the start and end tag
+    pub children: Vec<Node>,
+    /// General entities declared by the document's DTD, for decoding `text`
+    entities: Arc<HashMap<String, String>>,
+}
+
+impl Document {
+    /// Parses a complete XML document, keeping all of its original formatting
+    /// # Arguments
+    ///
+    /// * `input` - A string slice holding the document
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use simple_xml_serialize::document::Document;
+    /// let input = "<a  x = 'y' ><!-- note --><b/></a>";
+    /// let doc = Document::parse(input).unwrap();
+    /// assert_eq!(doc.root().get_attr("x"), Some("y"));
+    /// assert_eq!(doc.to_string(), input);
+    /// ```
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut tokens = Tokenizer::new(input);
+        let mut prolog = Vec::new();
+        let mut epilog = Vec::new();
+        let mut root: Option<Element> = None;
+        let mut stack: Vec<Element> = Vec::new();
+        let mut entities = Arc::new(HashMap::new());
+
+        // a byte order mark is kept as text so it is written back out
+        if input.starts_with('\u{feff}') {
+            tokens.pos = '\u{feff}'.len_utf8();
+            prolog.push(Node::Text('\u{feff}'.to_string()));
+        }
+
+        while let Some(token) = tokens.next_token() {
+            let node = match token? {
+                Token::StartTag(tag) => {
+                    if root.is_some() {
+                        return Err(tokens.error(tag.range.start, "unexpected element after the root element"));
+                    }
+                    let mut attrs = Vec::new();
+                    let mut last_end = tag.range.start + 1 + tag.name.len();
+                    for a in &tag.attrs {
+                        let value = parser::unescape(&tokens, a.value, a.value_range.start, &entities)?;
+                        attrs.push(Attr{name: a.name.to_string(), value, raw: input[last_end..a.range.end].to_string()});
+                        last_end = a.range.end;
+                    }
+                    let close_len = if tag.self_closing { 2 } else { 1 };
+                    let ele = Element{
+                        name: tag.name.to_string(),
+                        attrs,
+                        tag_tail: input[last_end..tag.range.end - close_len].to_string(),
+                        end_tag: None,
+                        children: Vec::new(),
+                        entities: entities.clone(),
+                    };
+                    if tag.self_closing {
+                        Node::Element(ele)
+                    } else {
+                        stack.push(ele);
+                        continue;
+                    }
+                },
+                Token::EndTag{name, range} => {
+                    let mut ele = match stack.pop() {
+                        Some(ele) => ele,
+                        None => return Err(tokens.error(range.start, format!("unexpected end tag `</{}>`", name))),
+                    };
+                    if ele.name != name {
+                        let msg = format!("expected `</{}>` but found `</{}>`", ele.name, name);
+                        return Err(tokens.error(range.start, msg));
+                    }
+                    ele.end_tag = Some(input[range].to_string());
+                    Node::Element(ele)
+                },
+                Token::Text(range) => {
+                    let raw = &input[range.clone()];
+                    if stack.is_empty() {
+                        if let Some(i) = raw.find(|c| !parser::is_xml_whitespace(c)) {
+                            return Err(tokens.error(range.start + i, "text is not allowed outside of the root element"));
+                        }
+                    } else {
+                        // only to check that the entity references are valid
+                        parser::unescape(&tokens, raw, range.start, &entities)?;
+                    }
+                    Node::Text(raw.to_string())
+                },
+                Token::CData(range) => {
+                    if stack.is_empty() {
+                        return Err(tokens.error(range.start, "CDATA is not allowed outside of the root element"));
+                    }
+                    Node::CData(input[range].to_string())
+                },
+                Token::Comment(range) => Node::Comment(input[range].to_string()),
+                Token::ProcessingInstruction(range) => Node::ProcessingInstruction(input[range].to_string()),
+                Token::Doctype{body, range} => {
+                    if root.is_some() || !stack.is_empty() {
+                        return Err(tokens.error(range.start, "unexpected document type declaration"));
+                    }
+                    entities = Arc::new(dtd::parse_doctype(&tokens, body, range.start + "<!DOCTYPE".len(), None)?.entities);
+                    Node::Doctype(input[range].to_string())
+                },
+            };
+
+            match (stack.last_mut(), node) {
+                (Some(parent), node) => parent.children.push(node),
+                (None, Node::Element(ele)) => root = Some(ele),
+                (None, node) if root.is_none() => prolog.push(node),
+                (None, node) => epilog.push(node),
+            }
+        }
+
+        if let Some(ele) = stack.last() {
+            return Err(tokens.error(input.len(), format!("unclosed element `<{}>`", ele.name)));
+        }
+        match root {
+            Some(root) => Ok(Document{prolog, root, epilog}),
+            None => Err(tokens.error(input.len(), "no root element found")),
+        }
+    }
+
+    pub fn root(&self) -> &Element {
+        &self.root
+    }
+
+    pub fn root_mut(&mut self) -> &mut Element {
+        &mut self.root
+    }
+
+    /// Converts the document's root into an `XMLElement`, dropping all formatting
+    pub fn to_xml_element(&self) -> XMLElement {
+        XMLElement::from(&self.root)
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for n in &self.prolog {
+            write!(f, "{}", n)?;
+        }
+        write!(f, "{}", self.root)?;
+        for n in &self.epilog {
+            write!(f, "{}", n)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Node::Element(e) => write!(f, "{}", e),
+            Node::Text(s) | Node::CData(s) | Node::Comment(s) | Node::ProcessingInstruction(s) | Node::Doctype(s) => {
+                write!(f, "{}", s)
+            },
+        }
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}", self.name)?;
+        for a in &self.attrs {
+            write!(f, "{}", a.raw)?;
+        }
+        write!(f, "{}", self.tag_tail)?;
+        match &self.end_tag {
+            None => write!(f, "/>"),
+            Some(end_tag) => {
+                write!(f, ">")?;
+                for c in &self.children {
+                    write!(f, "{}", c)?;
+                }
+                write!(f, "{}", end_tag)
+            },
+        }
+    }
+}
+
+impl From<&Element> for XMLElement {
+    fn from(e: &Element) -> Self {
+        let mut ele = XMLElement::new(&e.name);
+        for a in &e.attrs {
+            ele.add_attr(&a.name, &a.value);
+        }
+        ele.add_elements(e.elements());
+        if let Some(text) = e.text() {
+            ele.set_text(text);
+        }
+        ele
+    }
+}
+
+impl From<&XMLElement> for Element {
+    fn from(e: &XMLElement) -> Self {
+        let mut ele = Element{
+            name: e.name.clone(),
+            attrs: Vec::new(),
+            tag_tail: String::new(),
+            end_tag: None,
+            children: Vec::new(),
+            entities: Arc::new(HashMap::new()),
+        };
+        if let Some(attrs) = &e.attrs {
+            for a in attrs {
+                ele.set_attr(&a.name, &a.value);
+            }
+        }
+        if let Some(contents) = &e.contents {
+            for c in contents {
+                ele.push_child(Node::Element(Element::from(c)));
+            }
+        }
+        if let Some(text) = &e.text {
+            ele.set_text(text);
+        }
+        ele
+    }
+}
+
+impl Element {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Renames the element, updating both its start and end tag
+    pub fn set_name(&mut self, name: &str) {
+        self.name = String::from(name);
+        if self.end_tag.is_some() {
+            self.end_tag = Some(format!("</{}>", name));
+        }
+    }
+
+    pub fn attrs(&self) -> &[Attr] {
+        &self.attrs
+    }
+
+    /// Returns the decoded value of the attribute with the given name
+    pub fn get_attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|a| a.name == name).map(|a| a.value.as_str())
+    }
+
+    /// Changes the value of an attribute, or adds it after the existing ones if there is none with that name.
+    /// An existing attribute keeps its position, surrounding whitespace and quote character.
+    /// # Arguments
+    ///
+    /// * `attr` - A string slice that holds the name of the attribute
+    /// * `attr_val` - Any type that implements ToString; the value of the attribute
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use simple_xml_serialize::document::Document;
+    /// let mut doc = Document::parse("<a\n  x='1'\n  y=\"2\"/>").unwrap();
+    /// doc.root_mut().set_attr("x", "it's");
+    /// doc.root_mut().set_attr("z", 3);
+    /// assert_eq!(doc.to_string(), "<a\n  x='it&apos;s'\n  y=\"2\" z=\"3\"/>");
+    /// ```
+    pub fn set_attr(&mut self, attr: &str, attr_val: impl ToString) {
+        let value = attr_val.to_string();
+        match self.attrs.iter_mut().find(|a| a.name == attr) {
+            Some(a) => {
+                // the value cannot contain its own quote character so the last two quotes delimit it
+                let quote = if a.raw.ends_with('\'') { '\'' } else { '"' };
+                let open = a.raw[..a.raw.len() - 1].rfind(quote).unwrap_or(0);
+                a.raw = format!("{}{}{}", &a.raw[..=open], escape_attr(&value, quote), quote);
+                a.value = value;
+            },
+            None => {
+                let raw = format!(" {}=\"{}\"", attr, escape_attr(&value, '"'));
+                self.attrs.push(Attr{name: String::from(attr), value, raw});
+            },
+        }
+    }
+
+    /// Removes the attribute with the given name. Returns whether there was one.
+    pub fn remove_attr(&mut self, attr: &str) -> bool {
+        let len = self.attrs.len();
+        self.attrs.retain(|a| a.name != attr);
+        len != self.attrs.len()
+    }
+
+    /// Iterates over the child elements, skipping text, comments and other nodes
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|c| match c {
+            Node::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// Iterates mutably over the child elements, skipping text, comments and other nodes
+    pub fn elements_mut(&mut self) -> impl Iterator<Item = &mut Element> {
+        self.children.iter_mut().filter_map(|c| match c {
+            Node::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// Returns the first child element with the given name
+    pub fn find(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|e| e.name == name)
+    }
+
+    /// Returns the first child element with the given name
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Element> {
+        self.elements_mut().find(|e| e.name == name)
+    }
+
+    /// Adds an element after the last child element. When the existing children are each on their
+    /// own line, the new one is indented the same way.
+    /// # Arguments
+    ///
+    /// * `new_ele` - Any type that implements `Into<XMLElement>`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use simple_xml_serialize::XMLElement;
+    /// use simple_xml_serialize::document::Document;
+    /// let mut doc = Document::parse("<list>\n\t<item/>\n</list>").unwrap();
+    /// doc.root_mut().add_element(XMLElement::new("item").text(2));
+    /// assert_eq!(doc.to_string(), "<list>\n\t<item/>\n\t<item>2</item>\n</list>");
+    /// ```
+    pub fn add_element(&mut self, new_ele: impl Into<XMLElement>) {
+        let new_ele: XMLElement = new_ele.into();
+        self.push_child(Node::Element(Element::from(&new_ele)));
+    }
+
+    /// Removes the child element at the given index, counting only elements. The whitespace
+    /// that indented it is removed as well.
+    /// # Example
+    ///
+    /// ```
+    /// use simple_xml_serialize::document::Document;
+    /// let mut doc = Document::parse("<list>\n  <a/>\n  <b/>\n</list>").unwrap();
+    /// let removed = doc.root_mut().remove_element(0).unwrap();
+    /// assert_eq!(removed.name(), "a");
+    /// assert_eq!(doc.to_string(), "<list>\n  <b/>\n</list>");
+    /// ```
+    pub fn remove_element(&mut self, index: usize) -> Option<Element> {
+        let pos = self.children.iter()
+            .enumerate()
+            .filter(|(_, c)| matches!(c, Node::Element(_)))
+            .nth(index)
+            .map(|(i, _)| i)?;
+        let removed = self.children.remove(pos);
+        if pos > 0 && self.is_whitespace_at(pos - 1) {
+            self.children.remove(pos - 1);
+        }
+        match removed {
+            Node::Element(e) => Some(e),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Removes every child element with the given name. Returns how many were removed.
+    pub fn remove_elements(&mut self, name: &str) -> usize {
+        let mut removed = 0;
+        loop {
+            let index = match self.elements().position(|e| e.name == name) {
+                Some(index) => index,
+                None => break,
+            };
+            self.remove_element(index);
+            removed += 1;
+        }
+        removed
+    }
+
+    /// The decoded character data of this element, joined and trimmed the same way `parser::parse` does
+    pub fn text(&self) -> Option<String> {
+        let mut text = String::new();
+        for c in &self.children {
+            match c {
+                Node::Text(raw) => {
+                    let tokens = Tokenizer::new(raw);
+                    // the text was checked when it was parsed or escaped when it was set, so only text
+                    // pushed into `children` by hand can fail here and it is left as is
+                    text.push_str(&parser::unescape(&tokens, raw, 0, &self.entities).unwrap_or_else(|_| raw.clone()));
+                },
+                Node::CData(raw) => text.push_str(raw),
+                _ => {},
+            }
+        }
+        let text = text.trim_matches(parser::is_xml_whitespace);
+        if text.is_empty() {
+            None
+        } else {
+            Some(text.to_string())
+        }
+    }
+
+    /// Replaces the character data of this element. Whitespace used to lay out child elements is kept.
+    /// Like `XMLElement`, a `<![CDATA[...]]>` section in the text is written as is.
+    pub fn set_text(&mut self, text: impl ToString) {
+        let (before_cdata, opt_cdata) = crate::split_cdata(&text.to_string());
+        let mut new_text = vec![Node::Text(escape_text(&before_cdata))];
+        if let Some((cdata, after_cdata)) = opt_cdata {
+            new_text.push(Node::CData(cdata));
+            new_text.push(Node::Text(escape_text(&after_cdata)));
+        }
+        new_text.retain(|n| !matches!(n, Node::Text(t) if t.is_empty()));
+        let is_content = |c: &Node| match c {
+            Node::Text(t) => !t.trim_matches(parser::is_xml_whitespace).is_empty(),
+            Node::CData(_) => true,
+            _ => false,
+        };
+        match self.children.iter().position(is_content) {
+            Some(first) => {
+                let inserted = new_text.len();
+                self.children.splice(first..=first, new_text);
+                let mut i = first + inserted;
+                while i < self.children.len() {
+                    if is_content(&self.children[i]) {
+                        self.children.remove(i);
+                    } else {
+                        i += 1;
+                    }
+                }
+            },
+            None if self.elements().next().is_none() => {
+                self.open();
+                self.children = new_text;
+            },
+            None => {
+                let mut nodes = new_text.into_iter();
+                if let Some(first) = nodes.next() {
+                    let at = self.push_child(first) + 1;
+                    self.children.splice(at..at, nodes);
+                }
+            },
+        }
+    }
+
+    /// Turns a self-closing element into one with an end tag
+    fn open(&mut self) {
+        if self.end_tag.is_none() {
+            self.end_tag = Some(format!("</{}>", self.name));
+        }
+    }
+
+    fn is_whitespace_at(&self, index: usize) -> bool {
+        match self.children.get(index) {
+            Some(Node::Text(t)) => t.chars().all(parser::is_xml_whitespace),
+            _ => false,
+        }
+    }
+
+    /// Inserts a node after the last child element, copying the indentation in front of it. Returns where it was inserted.
+    fn push_child(&mut self, node: Node) -> usize {
+        self.open();
+        let last = self.children.iter().rposition(|c| matches!(c, Node::Element(_)));
+        match last {
+            Some(last) => {
+                let mut insert = vec![node];
+                if last > 0 && self.is_whitespace_at(last - 1) {
+                    insert.insert(0, self.children[last - 1].clone());
+                }
+                let at = last + 1;
+                let inserted_at = at + insert.len() - 1;
+                self.children.splice(at..at, insert);
+                inserted_at
+            },
+            None => {
+                // keep the whitespace in front of the end tag at the end
+                let at = if !self.children.is_empty() && self.is_whitespace_at(self.children.len() - 1) {
+                    self.children.len() - 1
+                } else {
+                    self.children.len()
+                };
+                self.children.insert(at, node);
+                at
+            },
+        }
+    }
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn escape_attr(value: &str, quote: char) -> String {
+    let value = value.replace('&', "&amp;").replace('<', "&lt;");
+    match quote {
+        '\'' => value.replace('\'', "&apos;"),
+        _ => value.replace('"', "&quot;"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pom() -> String {
+        let pom = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- keep me -->
+<project xmlns="http://maven.apache.org/POM/4.0.0"
+         version = '1.0' >
+    <dependencies>
+        <dependency  scope="test" />  <!-- junit -->
+        <dependency><![CDATA[ x < y ]]></dependency>
+    </dependencies>
+    <name>A &amp; B</name >
+</project>
+"#;
+        pom.replace('\n', "\r\n")
+    }
+
+    #[test]
+    fn document_round_trip() {
+        let doc = Document::parse(&pom()).unwrap();
+        assert_eq!(doc.to_string(), pom());
+
+        let with_bom = format!("\u{feff}{}", pom());
+        assert_eq!(Document::parse(&with_bom).unwrap().to_string(), with_bom);
+
+        let doctype = "<!DOCTYPE a [ <!ENTITY x \"]>\"> <!-- ' --> ]><a/>";
+        assert_eq!(Document::parse(doctype).unwrap().to_string(), doctype);
+    }
+
+    #[test]
+    fn document_read() {
+        let doc = Document::parse(&pom()).unwrap();
+        let root = doc.root();
+        assert_eq!(root.get_attr("version"), Some("1.0"));
+        assert_eq!(root.find("name").unwrap().text(), Some(String::from("A & B")));
+        let deps = root.find("dependencies").unwrap();
+        assert_eq!(deps.elements().count(), 2);
+        assert_eq!(deps.elements().nth(1).unwrap().text(), Some(String::from("<![CDATA[ x < y ]]>")));
+
+        let expected = crate::parser::parse(&pom()).unwrap();
+        assert_eq!(doc.to_xml_element(), expected);
+    }
+
+    #[test]
+    fn document_edit_attrs() {
+        let mut doc = Document::parse(&pom()).unwrap();
+        doc.root_mut().set_attr("version", "2.0");
+        doc.root_mut().find_mut("name").unwrap().set_attr("lang", "en");
+        assert!(doc.root_mut().remove_attr("xmlns"));
+        assert!(!doc.root_mut().remove_attr("xmlns"));
+        let expected = pom().replace("version = '1.0' >", "version = '2.0' >")
+                          .replace("<name>", "<name lang=\"en\">")
+                          .replace(" xmlns=\"http://maven.apache.org/POM/4.0.0\"", "");
+        assert_eq!(doc.to_string(), expected);
+    }
+
+    #[test]
+    fn document_add_remove_children() {
+        let mut doc = Document::parse(&pom()).unwrap();
+        let deps = doc.root_mut().find_mut("dependencies").unwrap();
+        deps.add_element(XMLElement::new("dependency").attr("scope", "compile"));
+        assert!(deps.remove_element(0).is_some());
+        let expected = pom().replace("\r\n        <dependency  scope=\"test\" />", "")
+                            .replace("</dependency>\r\n", "</dependency>\r\n        <dependency scope=\"compile\"/>\r\n");
+        assert_eq!(doc.to_string(), expected);
+        assert_eq!(doc.root_mut().find_mut("dependencies").unwrap().remove_elements("dependency"), 2);
+
+        let mut doc = Document::parse("<a/>").unwrap();
+        doc.root_mut().add_element(XMLElement::new("b").text("1 < 2"));
+        assert_eq!(doc.to_string(), "<a><b>1 &lt; 2</b></a>");
+        assert!(doc.root_mut().remove_element(1).is_none());
+    }
+
+    #[test]
+    fn document_set_text() {
+        let mut doc = Document::parse("<a>\n  old\n  <b/>\n  <![CDATA[x]]>\n</a>").unwrap();
+        doc.root_mut().set_text("new & improved");
+        assert_eq!(doc.to_string(), "<a>new &amp; improved<b/>\n  \n</a>");
+        assert_eq!(doc.root().text(), Some(String::from("new & improved")));
+
+        let mut doc = Document::parse("<a x='1' />").unwrap();
+        doc.root_mut().set_text("t");
+        doc.root_mut().set_name("c");
+        assert_eq!(doc.to_string(), "<c x='1' >t</c>");
+    }
+
+    #[test]
+    fn document_text_matches_xml_element() {
+        let input = "<!DOCTYPE a [<!ENTITY co \"Acme &amp; Co\">]><a x='&co;'>&co; &lt;3</a>";
+        let doc = Document::parse(input).unwrap();
+        assert_eq!(doc.root().text(), Some(String::from("Acme & Co <3")));
+        assert_eq!(doc.to_xml_element(), crate::parser::parse(input).unwrap());
+
+        let ele = XMLElement::new("a").element(XMLElement::new("b")).text("1 < 2<![CDATA[ <raw> ]]>&");
+        let mut doc = Document::parse("<root>\n  <c/>\n</root>").unwrap();
+        doc.root_mut().add_element(&ele);
+        assert_eq!(doc.to_string(), format!("<root>\n  <c/>\n  {}\n</root>", ele));
+        let added = doc.root().find("a").unwrap();
+        assert_eq!(added.text(), ele.text);
+        assert_eq!(crate::parser::parse(&added.to_string()).unwrap(), ele);
+
+        let mut doc = Document::parse("<a>old<b/></a>").unwrap();
+        doc.root_mut().set_text("<![CDATA[&]]>new");
+        assert_eq!(doc.to_string(), "<a><![CDATA[&]]>new<b/></a>");
+        let mut doc = Document::parse("<a>\n  <b/>\n</a>").unwrap();
+        doc.root_mut().set_text("x<![CDATA[y]]>");
+        assert_eq!(doc.to_string(), "<a>\n  <b/>\n  x<![CDATA[y]]>\n</a>");
+    }
+
+    #[test]
+    fn document_errors() {
+        let err = Document::parse("<a>\n<b></a>").unwrap_err();
+        assert_eq!(err.to_string(), "expected `</b>` but found `</a>` at line 2, column 4");
+        assert!(Document::parse("<a>&bogus;</a>").is_err());
+        assert!(Document::parse("<a/><b/>").is_err());
+        assert!(Document::parse("<a>").is_err());
+    }
+}
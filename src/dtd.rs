@@ -0,0 +1,1285 @@
+/*!
+Document type definitions (DTDs): reading them, expanding the entities they declare and validating
+`XMLElement` trees against their `ELEMENT` and `ATTLIST` declarations.
+
+```rust
+use simple_xml_serialize::XMLElement;
+use simple_xml_serialize::dtd::Dtd;
+
+let dtd = Dtd::parse(r#"
+    <!ENTITY company "ACME &amp; Sons">
+    <!ELEMENT order (item+, note?)>
+    <!ATTLIST order status (open|closed) "open">
+    <!ELEMENT item EMPTY>
+    <!ATTLIST item sku CDATA #REQUIRED>
+    <!ELEMENT note (#PCDATA)>
+"#).unwrap();
+
+let order = XMLElement::new("order")
+                .attr("status", "pending")
+                .element(XMLElement::new("item"))
+                .element(XMLElement::new("item").attr("sku", "A-1"));
+
+let errors = dtd.validate(&order).unwrap_err();
+assert_eq!(errors[0].to_string(), "/order: value `pending` of attribute `status` is not one of (open|closed)");
+assert_eq!(errors[1].to_string(), "/order/item[1]: required attribute `sku` is missing");
+```
+
+Documents that carry a `<!DOCTYPE>` can be read with `parse_document`, which loads the external
+subset through a `DtdResolver`, expands the declared entities and hands back the `Dtd` for validation.
+Parameter entities, conditional sections and external general entities are supported. Entities
+whose replacement text contains markup are not; they are reported as parse errors.
+*/
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::parser::{self, ParseError, SourceMap, Tokenizer};
+use crate::validation::{ElementPath, Reporter, ValidationError};
+use crate::XMLElement;
+
+/// Loads external DTD subsets and entities referenced by a system identifier
+pub trait DtdResolver {
+    /// Returns the text identified by `system_id`, IE the contents of `<!DOCTYPE a SYSTEM "a.dtd">`'s `a.dtd`.
+    /// A relative `system_id` declared in an external subset or entity is given relative to the document rather
+    /// than the file declaring it, IE `modules/b.ent` for `b.ent` declared in `modules/a.dtd`.
+    fn resolve(&self, public_id: Option<&str>, system_id: &str) -> io::Result<String>;
+}
+
+/// Resolves system identifiers as paths relative to a directory on the local filesystem.
+/// Anything that looks like a URL other than `file://`, and any path that leads outside of the directory,
+/// IE an absolute path or one going up with `..`, is refused.
+#[derive(Clone,PartialEq,Debug)]
+pub struct FileResolver {
+    base_dir: PathBuf,
+}
+
+impl FileResolver {
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        FileResolver{base_dir: base_dir.into()}
+    }
+}
+
+impl DtdResolver for FileResolver {
+    fn resolve(&self, _public_id: Option<&str>, system_id: &str) -> io::Result<String> {
+        let path = system_id.trim_start_matches("file://");
+        if path.contains("://") {
+            let msg = format!("only local files can be loaded, not `{}`", system_id);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        // symlinks and `..` are resolved before checking, so neither can be used to leave the directory
+        let base_dir = self.base_dir.canonicalize()?;
+        let full_path = base_dir.join(path).canonicalize()?;
+        if !full_path.starts_with(&base_dir) {
+            let msg = format!("`{}` is outside of `{}`", system_id, base_dir.display());
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, msg));
+        }
+        fs::read_to_string(full_path)
+    }
+}
+
+/// What an element may contain, from `<!ELEMENT name ...>`
+#[derive(Clone,PartialEq,Debug)]
+pub enum ContentModel {
+    Empty,
+    Any,
+    /// `(#PCDATA|a|b)*`: text mixed with any number of the listed elements
+    Mixed(Vec<String>),
+    /// Only elements, in the order given by the particle
+    Children(ContentParticle),
+}
+
+/// A name or group in an element content model, with how often it may occur
+#[derive(Clone,PartialEq,Debug)]
+pub struct ContentParticle {
+    pub kind: ParticleKind,
+    pub occurs: Occurs,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub enum ParticleKind {
+    Name(String),
+    /// `(a, b, c)`
+    Seq(Vec<ContentParticle>),
+    /// `(a | b | c)`
+    Choice(Vec<ContentParticle>),
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum Occurs {
+    Once,
+    /// `?`
+    Optional,
+    /// `*`
+    ZeroOrMore,
+    /// `+`
+    OneOrMore,
+}
+
+/// One attribute from an `<!ATTLIST ...>` declaration
+#[derive(Clone,PartialEq,Debug)]
+pub struct AttributeDecl {
+    pub name: String,
+    pub kind: AttributeType,
+    pub default: AttributeDefault,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub enum AttributeType {
+    CData,
+    Id,
+    IdRef,
+    IdRefs,
+    Entity,
+    Entities,
+    NmToken,
+    NmTokens,
+    Notation(Vec<String>),
+    /// `(a|b|c)`
+    Enumeration(Vec<String>),
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub enum AttributeDefault {
+    Required,
+    Implied,
+    /// The attribute must always have this value
+    Fixed(String),
+    /// The value to use when the attribute is missing
+    Default(String),
+}
+
+/// The declarations of a DTD. Where something is declared more than once the first declaration is used.
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct Dtd {
+    /// The root element named by `<!DOCTYPE name ...>`, if the DTD came from a document
+    pub root: Option<String>,
+    pub elements: HashMap<String, ContentModel>,
+    /// Attribute declarations for each element name
+    pub attributes: HashMap<String, Vec<AttributeDecl>>,
+    /// General entities and their replacement text
+    pub entities: HashMap<String, String>,
+    /// Entities declared with `NDATA`, which may only be named by `ENTITY` attributes
+    pub unparsed_entities: HashSet<String>,
+    parameter_entities: HashMap<String, ParameterEntity>,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+enum ParameterEntity {
+    Internal(String),
+    External{public_id: Option<String>, system_id: String},
+}
+
+/// A document read by `parse_document` along with its DTD
+#[derive(Clone,PartialEq,Debug)]
+pub struct ParsedDocument {
+    pub root: XMLElement,
+    /// `None` when the document has no `<!DOCTYPE>`
+    pub dtd: Option<Dtd>,
+    pub source_map: SourceMap,
+}
+
+/// Parses a document, loading its external DTD subset with `resolver` and expanding all declared entities
+/// # Arguments
+///
+/// * `input` - A string slice holding the document
+/// * `resolver` - Loads the external subset and external entities, IE a `FileResolver`
+///
+/// # Example
+///
+/// ```
+/// use simple_xml_serialize::dtd::{self, FileResolver};
+/// # let dir = std::env::temp_dir().join("sxs_dtd_doc_example");
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// # std::fs::write(dir.join("note.dtd"), "<!ENTITY me 'Bob'><!ELEMENT note (#PCDATA)>").unwrap();
+/// let input = "<!DOCTYPE note SYSTEM \"note.dtd\">\n<note>\n  Hi &me;\n  <extra/>\n</note>";
+/// let doc = dtd::parse_document(input, &FileResolver::new(&dir)).unwrap();
+/// assert_eq!(doc.root.text.as_deref(), Some("Hi Bob"));
+///
+/// let errors = doc.dtd.unwrap().validate_with_positions(&doc.root, &doc.source_map).unwrap_err();
+/// assert_eq!(errors[0].to_string(), "/note: element `extra` is not allowed in `note` at line 2, column 1");
+/// assert_eq!(errors[1].to_string(), "/note/extra: element `extra` is not declared at line 4, column 3");
+/// ```
+pub fn parse_document(input: &str, resolver: &dyn DtdResolver) -> Result<ParsedDocument, ParseError> {
+    let (root, source_map, dtd) = parser::parse_full(input, true, Some(resolver))?;
+    Ok(ParsedDocument{root, dtd, source_map})
+}
+
+/// Reads the body of a `<!DOCTYPE ...>`, which starts at `offset` in the tokenizer's input
+pub(crate) fn parse_doctype(tokens: &Tokenizer, body: &str, offset: usize, resolver: Option<&dyn DtdResolver>) -> Result<Dtd, ParseError> {
+    let mut reader = DeclReader::new(body.to_string(), offset, tokens);
+    reader.ws();
+    let root = reader.name()?;
+    reader.ws();
+    let external = reader.external_id()?;
+    reader.ws();
+
+    let mut dtd_parser = DtdParser{dtd: Dtd::default(), resolver, depth: 0, base: String::new()};
+    dtd_parser.dtd.root = Some(root);
+    if reader.eat('[') {
+        let subset_start = reader.pos;
+        let subset_end = match body.rfind(']') {
+            Some(end) if end >= subset_start => end,
+            _ => return Err(tokens.error(offset + subset_start, "unterminated internal subset")),
+        };
+        dtd_parser.parse_subset(tokens, offset + subset_start, offset + subset_end)?;
+        reader.pos = subset_end + 1;
+        reader.ws();
+    }
+    if !reader.at_end() {
+        return Err(reader.error("unexpected content in document type declaration"));
+    }
+    // the internal subset is read first so that its declarations take precedence
+    if let (Some((public_id, system_id)), Some(resolver)) = (external, resolver) {
+        let text = resolver.resolve(public_id.as_deref(), &system_id)
+            .map_err(|e| tokens.error(offset, format!("could not load `{}`: {}", system_id, e)))?;
+        dtd_parser.parse_external(&text, &system_id, &system_id)?;
+    }
+    Ok(dtd_parser.dtd)
+}
+
+impl Dtd {
+    /// Parses a standalone DTD, such as the contents of a `.dtd` file. External parameter entities are skipped.
+    /// # Arguments
+    ///
+    /// * `text` - A string slice holding the declarations
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use simple_xml_serialize::dtd::{Dtd, ContentModel};
+    /// let dtd = Dtd::parse("<!ELEMENT br EMPTY>").unwrap();
+    /// assert_eq!(dtd.elements["br"], ContentModel::Empty);
+    /// ```
+    pub fn parse(text: &str) -> Result<Dtd, ParseError> {
+        let mut dtd_parser = DtdParser{dtd: Dtd::default(), resolver: None, depth: 0, base: String::new()};
+        let tokens = Tokenizer::new(text);
+        dtd_parser.parse_subset(&tokens, 0, text.len())?;
+        Ok(dtd_parser.dtd)
+    }
+
+    /// Parses a standalone DTD, loading external parameter entities with `resolver`
+    pub fn parse_with_resolver(text: &str, resolver: &dyn DtdResolver) -> Result<Dtd, ParseError> {
+        let mut dtd_parser = DtdParser{dtd: Dtd::default(), resolver: Some(resolver), depth: 0, base: String::new()};
+        let tokens = Tokenizer::new(text);
+        dtd_parser.parse_subset(&tokens, 0, text.len())?;
+        Ok(dtd_parser.dtd)
+    }
+
+    /// Checks an element tree against the declarations, returning every problem found
+    /// # Arguments
+    ///
+    /// * `root` - The root element of the document
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use simple_xml_serialize::XMLElement;
+    /// use simple_xml_serialize::dtd::Dtd;
+    /// let dtd = Dtd::parse("<!ELEMENT list (item*)><!ELEMENT item (#PCDATA)>").unwrap();
+    /// assert!(dtd.validate(&XMLElement::new("list").element(XMLElement::new("item"))).is_ok());
+    ///
+    /// let errors = dtd.validate(&XMLElement::new("list").text("loose")).unwrap_err();
+    /// assert_eq!(errors[0].message, "text is not allowed in element `list`");
+    /// ```
+    pub fn validate(&self, root: &XMLElement) -> Result<(), Vec<ValidationError>> {
+        self.validate_inner(root, None)
+    }
+
+    /// Same as `validate`, but adds the line and column to each error using the source map from
+    /// `parser::parse_with_positions` or `parse_document`
+    pub fn validate_with_positions(&self, root: &XMLElement, source_map: &SourceMap) -> Result<(), Vec<ValidationError>> {
+        self.validate_inner(root, Some(source_map))
+    }
+
+    fn validate_inner(&self, root: &XMLElement, source_map: Option<&SourceMap>) -> Result<(), Vec<ValidationError>> {
+        let mut v = Validator{dtd: self, reporter: Reporter::new(source_map), ids: HashSet::new(), idrefs: Vec::new()};
+        let path = ElementPath::root(root);
+        if let Some(name) = &self.root {
+            if name != &root.name {
+                v.reporter.element(&path, format!("root element should be `{}` but is `{}`", name, root.name));
+            }
+        }
+        v.element(root, &path);
+        let Validator{mut reporter, ids, idrefs, ..} = v;
+        for (path, attr, id) in idrefs {
+            if !ids.contains(&id) {
+                reporter.attr(&path, &attr, format!("attribute `{}` refers to unknown ID `{}`", attr, id));
+            }
+        }
+        reporter.finish()
+    }
+
+    /// Adds the declared default and fixed values for attributes missing from the tree
+    /// # Example
+    ///
+    /// ```
+    /// use simple_xml_serialize::XMLElement;
+    /// use simple_xml_serialize::dtd::Dtd;
+    /// let dtd = Dtd::parse("<!ATTLIST a x CDATA 'one' y CDATA #FIXED 'two' z CDATA #IMPLIED>").unwrap();
+    /// let mut ele = XMLElement::new("a").attr("x", "mine");
+    /// dtd.apply_defaults(&mut ele);
+    /// assert_eq!(ele.to_string(), r#"<a x="mine" y="two"/>"#);
+    /// ```
+    pub fn apply_defaults(&self, root: &mut XMLElement) {
+        if let Some(decls) = self.attributes.get(&root.name) {
+            for decl in decls {
+                let value = match &decl.default {
+                    AttributeDefault::Fixed(v) | AttributeDefault::Default(v) => v,
+                    _ => continue,
+                };
+                if !root.attrs.iter().flatten().any(|a| a.name == decl.name) {
+                    root.add_attr(&decl.name, value);
+                }
+            }
+        }
+        if let Some(contents) = &mut root.contents {
+            for c in contents {
+                self.apply_defaults(c);
+            }
+        }
+    }
+}
+
+impl fmt::Display for ContentModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContentModel::Empty => write!(f, "EMPTY"),
+            ContentModel::Any => write!(f, "ANY"),
+            ContentModel::Mixed(names) if names.is_empty() => write!(f, "(#PCDATA)"),
+            ContentModel::Mixed(names) => write!(f, "(#PCDATA|{})*", names.join("|")),
+            ContentModel::Children(cp) => write!(f, "{}", cp),
+        }
+    }
+}
+
+impl fmt::Display for ContentParticle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ParticleKind::Name(n) => write!(f, "{}", n)?,
+            ParticleKind::Seq(items) | ParticleKind::Choice(items) => {
+                let sep = if let ParticleKind::Seq(_) = self.kind { ", " } else { " | " };
+                let items: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+                write!(f, "({})", items.join(sep))?
+            },
+        }
+        match self.occurs {
+            Occurs::Once => Ok(()),
+            Occurs::Optional => write!(f, "?"),
+            Occurs::ZeroOrMore => write!(f, "*"),
+            Occurs::OneOrMore => write!(f, "+"),
+        }
+    }
+}
+
+impl ContentParticle {
+    /// Returns whether the particle matches the whole sequence of element names
+    pub fn matches(&self, names: &[&str]) -> bool {
+        self.ends(names, 0).contains(&names.len())
+    }
+
+    /// All the positions in `names` where a match of this particle starting at `start` can end
+    fn ends(&self, names: &[&str], start: usize) -> BTreeSet<usize> {
+        match self.occurs {
+            Occurs::Once => self.ends_once(names, start),
+            Occurs::Optional => {
+                let mut ends = self.ends_once(names, start);
+                ends.insert(start);
+                ends
+            },
+            Occurs::ZeroOrMore | Occurs::OneOrMore => {
+                let mut ends = BTreeSet::new();
+                if self.occurs == Occurs::ZeroOrMore {
+                    ends.insert(start);
+                }
+                let mut frontier = self.ends_once(names, start);
+                while !frontier.is_empty() {
+                    let new: Vec<usize> = frontier.into_iter().filter(|e| ends.insert(*e)).collect();
+                    frontier = new.iter().flat_map(|&e| self.ends_once(names, e)).collect();
+                }
+                ends
+            },
+        }
+    }
+
+    fn ends_once(&self, names: &[&str], start: usize) -> BTreeSet<usize> {
+        match &self.kind {
+            ParticleKind::Name(n) => {
+                let mut ends = BTreeSet::new();
+                if names.get(start) == Some(&n.as_str()) {
+                    ends.insert(start + 1);
+                }
+                ends
+            },
+            ParticleKind::Seq(items) => {
+                let mut ends: BTreeSet<usize> = Some(start).into_iter().collect();
+                for item in items {
+                    ends = ends.iter().flat_map(|&s| item.ends(names, s)).collect();
+                }
+                ends
+            },
+            ParticleKind::Choice(items) => items.iter().flat_map(|i| i.ends(names, start)).collect(),
+        }
+    }
+}
+
+struct Validator<'a> {
+    dtd: &'a Dtd,
+    reporter: Reporter<'a>,
+    ids: HashSet<String>,
+    /// (element, attribute, referenced id) to check once all IDs are known
+    idrefs: Vec<(ElementPath, String, String)>,
+}
+
+impl<'a> Validator<'a> {
+    fn element(&mut self, ele: &XMLElement, path: &ElementPath) {
+        self.attributes(ele, path);
+        let contents = ele.contents.as_deref().unwrap_or(&[]);
+        match self.dtd.elements.get(&ele.name) {
+            None => self.reporter.element(path, format!("element `{}` is not declared", ele.name)),
+            Some(ContentModel::Any) => {},
+            Some(ContentModel::Empty) => {
+                if !contents.is_empty() || ele.text.is_some() {
+                    self.reporter.element(path, format!("element `{}` must be empty", ele.name));
+                }
+            },
+            Some(ContentModel::Mixed(names)) => {
+                for c in contents.iter().filter(|c| !names.contains(&c.name)) {
+                    self.reporter.element(path, format!("element `{}` is not allowed in `{}`", c.name, ele.name));
+                }
+            },
+            Some(ContentModel::Children(cp)) => {
+                if ele.text.is_some() {
+                    self.reporter.element(path, format!("text is not allowed in element `{}`", ele.name));
+                }
+                let names: Vec<&str> = contents.iter().map(|c| c.name.as_str()).collect();
+                if !cp.matches(&names) {
+                    let msg = format!("content of `{}` should match `{}` but is `({})`", ele.name, cp, names.join(", "));
+                    self.reporter.element(path, msg);
+                }
+            },
+        }
+        for (i, c) in contents.iter().enumerate() {
+            self.element(c, &path.child(ele, i));
+        }
+    }
+
+    fn attributes(&mut self, ele: &XMLElement, path: &ElementPath) {
+        let empty = Vec::new();
+        let decls = self.dtd.attributes.get(&ele.name).unwrap_or(&empty);
+        for a in ele.attrs.iter().flatten() {
+            let decl = match decls.iter().find(|d| d.name == a.name) {
+                Some(decl) => decl,
+                None => {
+                    self.reporter.attr(path, &a.name, format!("attribute `{}` is not declared", a.name));
+                    continue;
+                },
+            };
+            if let Err(msg) = self.check_value(decl, &a.value, path) {
+                self.reporter.attr(path, &a.name, msg);
+            }
+        }
+        for decl in decls {
+            let present = ele.attrs.iter().flatten().any(|a| a.name == decl.name);
+            if !present && decl.default == AttributeDefault::Required {
+                self.reporter.element(path, format!("required attribute `{}` is missing", decl.name));
+            }
+        }
+    }
+
+    fn check_value(&mut self, decl: &AttributeDecl, value: &str, path: &ElementPath) -> Result<(), String> {
+        let name = &decl.name;
+        if let AttributeDefault::Fixed(fixed) = &decl.default {
+            if value != fixed {
+                return Err(format!("attribute `{}` must be `{}` but is `{}`", name, fixed, value));
+            }
+        }
+        let tokens: Vec<&str> = value.split(parser::is_xml_whitespace).filter(|t| !t.is_empty()).collect();
+        match &decl.kind {
+            AttributeType::CData => {},
+            AttributeType::Enumeration(allowed) | AttributeType::Notation(allowed) => {
+                if !allowed.iter().any(|a| a == value) {
+                    return Err(format!("value `{}` of attribute `{}` is not one of ({})", value, name, allowed.join("|")));
+                }
+            },
+            AttributeType::NmToken | AttributeType::NmTokens => {
+                let multiple = decl.kind == AttributeType::NmTokens;
+                if tokens.is_empty() || (!multiple && tokens.len() > 1) || !tokens.iter().all(|t| t.chars().all(parser::is_name_char)) {
+                    return Err(format!("value `{}` of attribute `{}` is not a valid {}", value, name, if multiple { "NMTOKENS" } else { "NMTOKEN" }));
+                }
+            },
+            AttributeType::Id => {
+                if !is_name(value) {
+                    return Err(format!("value `{}` of attribute `{}` is not a valid ID", value, name));
+                }
+                if !self.ids.insert(value.to_string()) {
+                    return Err(format!("ID `{}` is used more than once", value));
+                }
+            },
+            AttributeType::IdRef | AttributeType::IdRefs => {
+                let multiple = decl.kind == AttributeType::IdRefs;
+                if tokens.is_empty() || (!multiple && tokens.len() > 1) || !tokens.iter().all(|t| is_name(t)) {
+                    return Err(format!("value `{}` of attribute `{}` is not a valid {}", value, name, if multiple { "IDREFS" } else { "IDREF" }));
+                }
+                for t in tokens {
+                    self.idrefs.push((path.clone(), name.clone(), t.to_string()));
+                }
+            },
+            AttributeType::Entity | AttributeType::Entities => {
+                for t in tokens {
+                    if !self.dtd.unparsed_entities.contains(t) {
+                        return Err(format!("attribute `{}` names undeclared unparsed entity `{}`", name, t));
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    s.starts_with(parser::is_name_start_char) && s.chars().all(parser::is_name_char)
+}
+
+const MAX_EXTERNAL_DEPTH: usize = 16;
+
+struct DtdParser<'r> {
+    dtd: Dtd,
+    resolver: Option<&'r dyn DtdResolver>,
+    /// how many external subsets or parameter entities we are nested in
+    depth: usize,
+    /// the system identifier of the external subset or entity being read, empty in the document itself
+    base: String,
+}
+
+impl<'r> DtdParser<'r> {
+    /// Reads the markup declarations in `tokens.input[start..end]`
+    fn parse_subset(&mut self, tokens: &Tokenizer, start: usize, end: usize) -> Result<(), ParseError> {
+        let input = tokens.input;
+        let mut pos = start;
+        loop {
+            pos += input[pos..end].len() - input[pos..end].trim_start_matches(parser::is_xml_whitespace).len();
+            if pos >= end {
+                return Ok(());
+            }
+            let rest = &input[pos..end];
+            let find_end = |close: &str, what: &str| match rest.find(close) {
+                Some(i) => Ok(pos + i + close.len()),
+                None => Err(tokens.error(pos, format!("unterminated {}", what))),
+            };
+            if rest.starts_with("<!--") {
+                pos = find_end("-->", "comment")?;
+            } else if rest.starts_with("<?") {
+                pos = find_end("?>", "processing instruction")?;
+            } else if rest.starts_with('%') {
+                let semi = find_end(";", "parameter entity reference")?;
+                let name = &input[pos + 1..semi - 1];
+                self.include_parameter_entity(tokens, pos, name)?;
+                pos = semi;
+            } else if rest.starts_with("<![") {
+                pos = self.conditional_section(tokens, pos, end)?;
+            } else if rest.starts_with("<!") {
+                pos = self.declaration(tokens, pos, end)?;
+            } else {
+                let c = rest.chars().next().unwrap_or(' ');
+                return Err(tokens.error(pos, format!("unexpected `{}` in document type definition", c)));
+            }
+        }
+    }
+
+    fn include_parameter_entity(&mut self, tokens: &Tokenizer, pos: usize, name: &str) -> Result<(), ParseError> {
+        let text = match self.dtd.parameter_entities.get(name).cloned() {
+            Some(ParameterEntity::Internal(text)) => text,
+            Some(ParameterEntity::External{public_id, system_id}) => {
+                let resolver = match self.resolver {
+                    Some(resolver) => resolver,
+                    None => return Ok(()),
+                };
+                let text = resolver.resolve(public_id.as_deref(), &system_id)
+                    .map_err(|e| tokens.error(pos, format!("could not load `{}`: {}", system_id, e)))?;
+                return self.parse_external(&text, &system_id, &format!("%{};", name)).map_err(|e| tokens.error(pos, e.to_string()));
+            },
+            None => return Err(tokens.error(pos, format!("undeclared parameter entity `%{};`", name))),
+        };
+        self.parse_nested(&text, &format!("%{};", name)).map_err(|e| tokens.error(pos, e.to_string()))
+    }
+
+    /// Parses text loaded from `system_id`, which the system identifiers declared in it are relative to
+    fn parse_external(&mut self, text: &str, system_id: &str, what: &str) -> Result<(), ParseError> {
+        let outer = std::mem::replace(&mut self.base, system_id.to_string());
+        let result = self.parse_nested(text, what);
+        self.base = outer;
+        result
+    }
+
+    /// `system_id` as declared in the entity being read, made relative to the document instead.
+    /// Absolute paths and URLs are left alone
+    fn relative_to_base(&self, system_id: String) -> String {
+        let base_dir = match self.base.rfind('/') {
+            Some(i) => &self.base[..=i],
+            None => return system_id,
+        };
+        let path = system_id.trim_start_matches("file://");
+        if path.starts_with('/') || path.contains("://") {
+            return system_id;
+        }
+        format!("{}{}", base_dir, path)
+    }
+
+    /// Parses text that did not come from the current input. Errors are reported within `what`.
+    fn parse_nested(&mut self, text: &str, what: &str) -> Result<(), ParseError> {
+        if self.depth >= MAX_EXTERNAL_DEPTH {
+            return Err(ParseError{message: format!("`{}` is nested too deeply", what), position: Default::default()});
+        }
+        self.depth += 1;
+        let tokens = Tokenizer::new(text);
+        let result = self.parse_subset(&tokens, 0, text.len());
+        self.depth -= 1;
+        result.map_err(|e| ParseError{message: format!("in `{}`: {}", what, e), position: e.position})
+    }
+
+    /// Handles `<![INCLUDE[ ... ]]>` and `<![IGNORE[ ... ]]>`, returning where the section ends
+    fn conditional_section(&mut self, tokens: &Tokenizer, start: usize, end: usize) -> Result<usize, ParseError> {
+        let input = tokens.input;
+        let open = match input[start + 3..end].find('[') {
+            Some(i) => start + 3 + i,
+            None => return Err(tokens.error(start, "unterminated conditional section")),
+        };
+        let keyword = self.expand_parameter_entities(tokens, start, &input[start + 3..open])?;
+        // find the matching `]]>`, allowing for nested sections
+        let mut depth = 1;
+        let mut pos = open + 1;
+        let close = loop {
+            let rest = &input[pos..end];
+            match (rest.find("<!["), rest.find("]]>")) {
+                (Some(o), Some(c)) if o < c => {
+                    depth += 1;
+                    pos += o + 3;
+                },
+                (_, Some(c)) => {
+                    depth -= 1;
+                    pos += c + 3;
+                    if depth == 0 {
+                        break pos - 3;
+                    }
+                },
+                _ => return Err(tokens.error(start, "unterminated conditional section")),
+            }
+        };
+        match keyword.trim() {
+            "INCLUDE" => self.parse_subset(tokens, open + 1, close)?,
+            "IGNORE" => {},
+            other => return Err(tokens.error(start, format!("expected INCLUDE or IGNORE but found `{}`", other))),
+        }
+        Ok(close + 3)
+    }
+
+    /// Reads one `<!KEYWORD ...>` declaration and returns where it ends
+    fn declaration(&mut self, tokens: &Tokenizer, start: usize, end: usize) -> Result<usize, ParseError> {
+        let input = tokens.input;
+        let mut quote = None;
+        let mut close = None;
+        for (i, c) in input[start..end].char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {},
+                (None, '"') | (None, '\'') => quote = Some(c),
+                (None, '>') => {
+                    close = Some(start + i);
+                    break;
+                },
+                _ => {},
+            }
+        }
+        let close = close.ok_or_else(|| tokens.error(start, "unterminated markup declaration"))?;
+        let decl = &input[start + 2..close];
+        let keyword_len = decl.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(decl.len());
+        let body = self.expand_parameter_entities(tokens, start, &decl[keyword_len..])?;
+        let mut reader = DeclReader::new(body, start, tokens);
+        match &decl[..keyword_len] {
+            "ELEMENT" => self.element_decl(&mut reader)?,
+            "ATTLIST" => self.attlist_decl(&mut reader)?,
+            "ENTITY" => self.entity_decl(&mut reader)?,
+            "NOTATION" => {},
+            other => return Err(tokens.error(start, format!("unknown declaration `<!{}`", other))),
+        }
+        Ok(close + 1)
+    }
+
+    /// Replaces `%name;` references outside of quoted literals with the entity's text
+    fn expand_parameter_entities(&self, tokens: &Tokenizer, at: usize, text: &str) -> Result<String, ParseError> {
+        let mut ret = String::with_capacity(text.len());
+        let mut quote = None;
+        let mut chars = text.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (None, '"') | (None, '\'') => quote = Some(c),
+                (None, '%') if text[i + 1..].starts_with(parser::is_name_start_char) => {
+                    let semi = text[i..].find(';').map(|s| i + s)
+                        .ok_or_else(|| tokens.error(at, "unterminated parameter entity reference"))?;
+                    let name = &text[i + 1..semi];
+                    match self.dtd.parameter_entities.get(name) {
+                        Some(ParameterEntity::Internal(value)) => {
+                            ret.push(' ');
+                            ret.push_str(value);
+                            ret.push(' ');
+                        },
+                        Some(ParameterEntity::External{..}) => {
+                            return Err(tokens.error(at, format!("external parameter entity `%{};` cannot be used inside a declaration", name)));
+                        },
+                        None => return Err(tokens.error(at, format!("undeclared parameter entity `%{};`", name))),
+                    }
+                    for (j, _) in chars.by_ref() {
+                        if j == semi {
+                            break;
+                        }
+                    }
+                    continue;
+                },
+                _ => {},
+            }
+            ret.push(c);
+        }
+        Ok(ret)
+    }
+
+    fn element_decl(&mut self, r: &mut DeclReader) -> Result<(), ParseError> {
+        r.ws();
+        let name = r.name()?;
+        r.ws();
+        let model = if r.eat_word("EMPTY") {
+            ContentModel::Empty
+        } else if r.eat_word("ANY") {
+            ContentModel::Any
+        } else {
+            r.expect('(')?;
+            r.ws();
+            if r.eat_word("#PCDATA") {
+                let mut names = Vec::new();
+                loop {
+                    r.ws();
+                    if r.eat(')') {
+                        break;
+                    }
+                    r.expect('|')?;
+                    r.ws();
+                    names.push(r.name()?);
+                }
+                if !r.eat('*') && !names.is_empty() {
+                    return Err(r.error("mixed content with elements must end in `)*`"));
+                }
+                ContentModel::Mixed(names)
+            } else {
+                let group = self.group(r)?;
+                ContentModel::Children(group)
+            }
+        };
+        r.ws();
+        if !r.at_end() {
+            return Err(r.error(format!("unexpected content in declaration of element `{}`", name)));
+        }
+        self.dtd.elements.entry(name).or_insert(model);
+        Ok(())
+    }
+
+    /// Reads the rest of a `(...)` group whose opening parenthesis has been read
+    fn group(&mut self, r: &mut DeclReader) -> Result<ContentParticle, ParseError> {
+        let mut items = vec![self.particle(r)?];
+        let mut separator = None;
+        loop {
+            r.ws();
+            if r.eat(')') {
+                break;
+            }
+            let sep = match r.peek() {
+                Some(c) if c == '|' || c == ',' => c,
+                _ => return Err(r.error("expected `|`, `,` or `)` in content model")),
+            };
+            if separator.is_some_and(|s| s != sep) {
+                return Err(r.error("`|` and `,` cannot be mixed in one group"));
+            }
+            separator = Some(sep);
+            r.pos += 1;
+            r.ws();
+            items.push(self.particle(r)?);
+        }
+        let kind = match separator {
+            Some('|') => ParticleKind::Choice(items),
+            _ => ParticleKind::Seq(items),
+        };
+        Ok(ContentParticle{kind, occurs: r.occurs()})
+    }
+
+    fn particle(&mut self, r: &mut DeclReader) -> Result<ContentParticle, ParseError> {
+        if r.eat('(') {
+            r.ws();
+            self.group(r)
+        } else {
+            let name = r.name()?;
+            Ok(ContentParticle{kind: ParticleKind::Name(name), occurs: r.occurs()})
+        }
+    }
+
+    fn attlist_decl(&mut self, r: &mut DeclReader) -> Result<(), ParseError> {
+        r.ws();
+        let element = r.name()?;
+        loop {
+            r.ws();
+            if r.at_end() {
+                return Ok(());
+            }
+            let name = r.name()?;
+            r.ws();
+            let kind = if r.eat('(') {
+                AttributeType::Enumeration(r.name_list()?)
+            } else {
+                let word = r.name()?;
+                match word.as_str() {
+                    "CDATA" => AttributeType::CData,
+                    "ID" => AttributeType::Id,
+                    "IDREF" => AttributeType::IdRef,
+                    "IDREFS" => AttributeType::IdRefs,
+                    "ENTITY" => AttributeType::Entity,
+                    "ENTITIES" => AttributeType::Entities,
+                    "NMTOKEN" => AttributeType::NmToken,
+                    "NMTOKENS" => AttributeType::NmTokens,
+                    "NOTATION" => {
+                        r.ws();
+                        r.expect('(')?;
+                        AttributeType::Notation(r.name_list()?)
+                    },
+                    other => return Err(r.error(format!("unknown attribute type `{}`", other))),
+                }
+            };
+            r.ws();
+            let default = if r.eat_word("#REQUIRED") {
+                AttributeDefault::Required
+            } else if r.eat_word("#IMPLIED") {
+                AttributeDefault::Implied
+            } else if r.eat_word("#FIXED") {
+                r.ws();
+                AttributeDefault::Fixed(self.literal_value(r)?)
+            } else {
+                AttributeDefault::Default(self.literal_value(r)?)
+            };
+            let decls = self.dtd.attributes.entry(element.clone()).or_default();
+            if !decls.iter().any(|d| d.name == name) {
+                decls.push(AttributeDecl{name, kind, default});
+            }
+        }
+    }
+
+    /// Reads a quoted default value and decodes its entity references
+    fn literal_value(&self, r: &mut DeclReader) -> Result<String, ParseError> {
+        let raw = r.quoted()?;
+        parser::unescape(r.tokens, &raw, r.offset, &self.dtd.entities)
+    }
+
+    fn entity_decl(&mut self, r: &mut DeclReader) -> Result<(), ParseError> {
+        r.ws();
+        let parameter = r.eat('%');
+        r.ws();
+        let name = r.name()?;
+        r.ws();
+        let external = r.external_id()?;
+        let value = match &external {
+            None => Some(self.expand_parameter_entities_in_literal(r)?),
+            Some(_) => None,
+        };
+        r.ws();
+        let notation = if r.eat_word("NDATA") {
+            r.ws();
+            Some(r.name()?)
+        } else {
+            None
+        };
+        r.ws();
+        if !r.at_end() {
+            return Err(r.error(format!("unexpected content in declaration of entity `{}`", name)));
+        }
+
+        if parameter {
+            let entity = match (value, external) {
+                (Some(value), _) => ParameterEntity::Internal(value),
+                (None, Some((public_id, system_id))) => ParameterEntity::External{public_id, system_id: self.relative_to_base(system_id)},
+                (None, None) => unreachable!(),
+            };
+            self.dtd.parameter_entities.entry(name).or_insert(entity);
+        } else if notation.is_some() {
+            self.dtd.unparsed_entities.insert(name);
+        } else if !self.dtd.entities.contains_key(&name) {
+            match (value, external, self.resolver) {
+                (Some(value), _, _) => {
+                    self.dtd.entities.insert(name, value);
+                },
+                (None, Some((public_id, system_id)), Some(resolver)) => {
+                    let system_id = self.relative_to_base(system_id);
+                    let text = resolver.resolve(public_id.as_deref(), &system_id)
+                        .map_err(|e| r.error(format!("could not load `{}`: {}", system_id, e)))?;
+                    self.dtd.entities.insert(name, text);
+                },
+                // without a resolver external entities stay undeclared, so references to them are errors
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads an entity value literal, replacing the parameter entity references inside of it
+    fn expand_parameter_entities_in_literal(&self, r: &mut DeclReader) -> Result<String, ParseError> {
+        let raw = r.quoted()?;
+        let mut ret = String::new();
+        let mut rest = raw.as_str();
+        while let Some(i) = rest.find('%') {
+            ret.push_str(&rest[..i]);
+            let semi = rest[i..].find(';').map(|s| i + s).ok_or_else(|| r.error("unterminated parameter entity reference"))?;
+            let name = &rest[i + 1..semi];
+            match self.dtd.parameter_entities.get(name) {
+                Some(ParameterEntity::Internal(value)) => ret.push_str(value),
+                _ => return Err(r.error(format!("parameter entity `%{};` cannot be used here", name))),
+            }
+            rest = &rest[semi + 1..];
+        }
+        ret.push_str(rest);
+        Ok(ret)
+    }
+}
+
+/// A cursor over the text of one declaration. Errors are reported at the start of the declaration,
+/// since parameter entity expansion means the text may not line up with the input.
+struct DeclReader<'t, 'i> {
+    text: String,
+    pos: usize,
+    offset: usize,
+    tokens: &'t Tokenizer<'i>,
+}
+
+impl<'t, 'i> DeclReader<'t, 'i> {
+    fn new(text: String, offset: usize, tokens: &'t Tokenizer<'i>) -> Self {
+        DeclReader{text, pos: 0, offset, tokens}
+    }
+
+    fn error(&self, message: impl ToString) -> ParseError {
+        self.tokens.error(self.offset, message)
+    }
+
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.text.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches(parser::is_xml_whitespace).len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}` in declaration", c)))
+        }
+    }
+
+    /// Consumes `word` if it is next and is not just the start of a longer name
+    fn eat_word(&mut self, word: &str) -> bool {
+        let rest = self.rest();
+        if rest.starts_with(word) && !rest[word.len()..].starts_with(parser::is_name_char) {
+            self.pos += word.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn name(&mut self) -> Result<String, ParseError> {
+        let rest = self.rest();
+        let len = rest.find(|c| !parser::is_name_char(c)).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected a name in declaration"));
+        }
+        let name = rest[..len].to_string();
+        self.pos += len;
+        Ok(name)
+    }
+
+    /// Reads `a | b | c)` after an opening parenthesis
+    fn name_list(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut names = Vec::new();
+        loop {
+            self.ws();
+            names.push(self.name()?);
+            self.ws();
+            if self.eat(')') {
+                return Ok(names);
+            }
+            self.expect('|')?;
+        }
+    }
+
+    fn occurs(&mut self) -> Occurs {
+        if self.eat('?') {
+            Occurs::Optional
+        } else if self.eat('*') {
+            Occurs::ZeroOrMore
+        } else if self.eat('+') {
+            Occurs::OneOrMore
+        } else {
+            Occurs::Once
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, ParseError> {
+        let quote = match self.peek() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => return Err(self.error("expected a quoted value in declaration")),
+        };
+        let len = self.text[self.pos + 1..].find(quote).ok_or_else(|| self.error("unterminated quoted value"))?;
+        let value = self.text[self.pos + 1..self.pos + 1 + len].to_string();
+        self.pos += len + 2;
+        Ok(value)
+    }
+
+    /// Reads an optional `SYSTEM "uri"` or `PUBLIC "id" "uri"`
+    fn external_id(&mut self) -> Result<Option<(Option<String>, String)>, ParseError> {
+        if self.eat_word("SYSTEM") {
+            self.ws();
+            Ok(Some((None, self.quoted()?)))
+        } else if self.eat_word("PUBLIC") {
+            self.ws();
+            let public_id = self.quoted()?;
+            self.ws();
+            Ok(Some((Some(public_id), self.quoted()?)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MapResolver(HashMap<&'static str, &'static str>);
+
+    impl DtdResolver for MapResolver {
+        fn resolve(&self, _public_id: Option<&str>, system_id: &str) -> io::Result<String> {
+            self.0.get(system_id)
+                .map(|s| s.to_string())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found"))
+        }
+    }
+
+    fn names(n: &[&'static str]) -> Vec<String> {
+        n.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn dtd_parse_declarations() {
+        let dtd = Dtd::parse(r#"
+            <!-- a comment with a > in it -->
+            <!ENTITY % inline "b | c">
+            <!ELEMENT a ((%inline;)*, d?)+>
+            <!ELEMENT b (#PCDATA)>
+            <!ELEMENT c (#PCDATA | b)*>
+            <!ELEMENT d ANY>
+            <!ATTLIST a
+                id    ID        #REQUIRED
+                kind  (x|y)     "x"
+                ver   CDATA     #FIXED "1&amp;2"
+                refs  IDREFS    #IMPLIED>
+            <!ATTLIST a kind CDATA #IMPLIED>
+            <!NOTATION gif SYSTEM "image/gif">
+            <!ENTITY logo SYSTEM "logo.gif" NDATA gif>
+            <![IGNORE[ <!ELEMENT a EMPTY> <![INCLUDE[ ]]> ]]>
+            <?pi ignored?>
+        "#).unwrap();
+
+        assert_eq!(dtd.elements["a"].to_string(), "((b | c)*, d?)+");
+        assert_eq!(dtd.elements["b"], ContentModel::Mixed(Vec::new()));
+        assert_eq!(dtd.elements["c"], ContentModel::Mixed(names(&["b"])));
+        assert_eq!(dtd.elements["d"], ContentModel::Any);
+        let attrs = &dtd.attributes["a"];
+        assert_eq!(attrs.len(), 4);
+        assert_eq!(attrs[1], AttributeDecl{name: "kind".to_string(), kind: AttributeType::Enumeration(names(&["x", "y"])), default: AttributeDefault::Default("x".to_string())});
+        assert_eq!(attrs[2].default, AttributeDefault::Fixed("1&2".to_string()));
+        assert!(dtd.unparsed_entities.contains("logo"));
+    }
+
+    #[test]
+    fn dtd_parse_errors() {
+        let err = Dtd::parse("<!ELEMENT a EMPTY>\n  <!ELEMENT b (c | d, e)>").unwrap_err();
+        assert_eq!(err.to_string(), "`|` and `,` cannot be mixed in one group at line 2, column 3");
+        assert!(Dtd::parse("<!ELEMENT a (#PCDATA | b)>").is_err());
+        assert!(Dtd::parse("<!ATTLIST a b WRONG #IMPLIED>").is_err());
+        assert!(Dtd::parse("<!ELEMENT a (%undeclared;)>").is_err());
+        assert!(Dtd::parse("<!ELEMENT a EMPTY").is_err());
+        assert!(Dtd::parse("junk").is_err());
+    }
+
+    #[test]
+    fn dtd_content_models() {
+        let dtd = Dtd::parse("<!ELEMENT r ((a, b?) | c+)*>").unwrap();
+        let cp = match &dtd.elements["r"] {
+            ContentModel::Children(cp) => cp,
+            _ => panic!(),
+        };
+        assert!(cp.matches(&[]));
+        assert!(cp.matches(&["a", "c", "c", "a", "b"]));
+        assert!(!cp.matches(&["b"]));
+        assert!(!cp.matches(&["a", "b", "b"]));
+    }
+
+    #[test]
+    fn dtd_validate() {
+        let dtd = Dtd::parse(r#"
+            <!ELEMENT list (item+)>
+            <!ATTLIST list owner IDREF #IMPLIED>
+            <!ELEMENT item EMPTY>
+            <!ATTLIST item id ID #REQUIRED size NMTOKEN #IMPLIED>
+        "#).unwrap();
+        let ok = XMLElement::new("list")
+                    .attr("owner", "i2")
+                    .element(XMLElement::new("item").attr("id", "i1").attr("size", "10"))
+                    .element(XMLElement::new("item").attr("id", "i2"));
+        assert_eq!(dtd.validate(&ok), Ok(()));
+
+        let bad = XMLElement::new("list")
+                    .attr("owner", "nobody")
+                    .attr("extra", 1)
+                    .element(XMLElement::new("item").attr("id", "i1").attr("size", "a b").text("x"))
+                    .element(XMLElement::new("item").attr("id", "i1"))
+                    .element(XMLElement::new("other"));
+        let errors: Vec<String> = dtd.validate(&bad).unwrap_err().iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, vec![
+            "/list: attribute `extra` is not declared",
+            "/list: content of `list` should match `(item+)` but is `(item, item, other)`",
+            "/list/item[1]: value `a b` of attribute `size` is not a valid NMTOKEN",
+            "/list/item[1]: element `item` must be empty",
+            "/list/item[2]: ID `i1` is used more than once",
+            "/list/other: element `other` is not declared",
+            "/list: attribute `owner` refers to unknown ID `nobody`",
+        ]);
+    }
+
+    #[test]
+    fn dtd_parse_document_entities() {
+        let resolver = MapResolver(vec![
+            ("ext.dtd", "<!ENTITY % extra SYSTEM 'extra.ent'> %extra; <!ELEMENT doc (#PCDATA)> <!ENTITY who 'external'>"),
+            ("extra.ent", "<!ATTLIST doc lang CDATA 'en'>"),
+            ("chapter.txt", "chapter &who; text"),
+        ].into_iter().collect());
+        let input = r#"<?xml version="1.0"?>
+<!DOCTYPE doc SYSTEM "ext.dtd" [
+    <!ENTITY who "internal &amp; first">
+    <!ENTITY chapter SYSTEM "chapter.txt">
+]>
+<doc title="&who;">&chapter;</doc>"#;
+        let doc = parse_document(input, &resolver).unwrap();
+        assert_eq!(doc.root.attrs.as_ref().unwrap()[0].value, "internal & first");
+        assert_eq!(doc.root.text.as_deref(), Some("chapter internal & first text"));
+
+        let dtd = doc.dtd.unwrap();
+        assert_eq!(dtd.root.as_deref(), Some("doc"));
+        let mut root = doc.root.clone();
+        dtd.apply_defaults(&mut root);
+        assert_eq!(root.attrs.as_ref().unwrap()[1].value, "en");
+
+        let errors = dtd.validate_with_positions(&root, &doc.source_map).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "/doc: attribute `title` is not declared at line 6, column 6");
+
+        // the internal subset is always read by the plain parser, but external entities are not loaded
+        let err = parser::parse(input).unwrap_err();
+        assert_eq!(err.to_string(), "unknown entity `&chapter;` at line 6, column 20");
+        let ele = parser::parse(&input.replace("&chapter;", "")).unwrap();
+        assert_eq!(ele.attrs.unwrap()[0].value, "internal & first");
+        let err = parser::parse(&input.replace("&who;\"", "&other;\"")).unwrap_err();
+        assert_eq!(err.to_string(), "unknown entity `&other;` at line 6, column 13");
+    }
+
+    #[test]
+    fn dtd_entity_limits() {
+        let err = parser::parse("<!DOCTYPE a [<!ENTITY x '&y;'><!ENTITY y '&x;'>]><a>&x;</a>").unwrap_err();
+        assert_eq!(err.message, "entity `&x;` is recursive or nested too deeply");
+
+        let err = parser::parse("<!DOCTYPE a [<!ENTITY x '<b/>'>]><a>&x;</a>").unwrap_err();
+        assert_eq!(err.message, "entity `&x;` contains markup, which is not supported");
+
+        let mut laughs = String::from("<!DOCTYPE a [<!ENTITY l0 'lol'>");
+        for i in 1..10 {
+            laughs.push_str(&format!("<!ENTITY l{} '{}'>", i, format!("&l{};", i - 1).repeat(10)));
+        }
+        laughs.push_str("]><a>&l9;</a>");
+        let err = parser::parse(&laughs).unwrap_err();
+        assert_eq!(err.message, "expanding entities produced too much text");
+    }
+
+    #[test]
+    fn dtd_file_resolver() {
+        let dir = std::env::temp_dir().join("sxs_dtd_file_resolver");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.dtd"), "<!ELEMENT a EMPTY>").unwrap();
+        let resolver = FileResolver::new(&dir);
+        let doc = parse_document("<!DOCTYPE a SYSTEM \"a.dtd\"><a/>", &resolver).unwrap();
+        assert_eq!(doc.dtd.unwrap().elements["a"], ContentModel::Empty);
+
+        let err = parse_document("<!DOCTYPE a SYSTEM \"http://example.com/a.dtd\"><a/>", &resolver).unwrap_err();
+        assert!(err.message.starts_with("could not load `http://example.com/a.dtd`"));
+        assert!(parse_document("<!DOCTYPE a SYSTEM \"missing.dtd\"><a/>", &resolver).is_err());
+
+        let inner = dir.join("inner");
+        fs::create_dir_all(inner.join("sub")).unwrap();
+        fs::write(inner.join("sub").join("b.dtd"), "<!ELEMENT b EMPTY>").unwrap();
+        let resolver = FileResolver::new(&inner);
+        assert!(parse_document("<!DOCTYPE b SYSTEM \"sub/../sub/b.dtd\"><b/>", &resolver).is_ok());
+        assert!(parse_document("<!DOCTYPE b SYSTEM \"file://sub/b.dtd\"><b/>", &resolver).is_ok());
+        for outside in ["../a.dtd", "sub/../../a.dtd"] {
+            let input = format!("<!DOCTYPE a SYSTEM \"{}\"><a/>", outside);
+            let err = parse_document(&input, &resolver).unwrap_err();
+            assert!(err.message.contains("is outside of"), "{}", err.message);
+        }
+        let absolute = format!("<!DOCTYPE a SYSTEM \"{}\"><a/>", dir.join("a.dtd").display());
+        assert!(parse_document(&absolute, &resolver).unwrap_err().message.contains("is outside of"));
+        let absolute = format!("<!DOCTYPE a SYSTEM \"file://{}\"><a/>", dir.join("a.dtd").display());
+        assert!(parse_document(&absolute, &resolver).unwrap_err().message.contains("is outside of"));
+
+        // system ids are relative to the file declaring them, and still can't leave the directory
+        let modules = inner.join("modules");
+        fs::create_dir_all(&modules).unwrap();
+        fs::write(inner.join("parts.ent"), "<!ELEMENT wrong EMPTY>").unwrap();
+        fs::write(modules.join("main.dtd"), concat!(
+            "<!ENTITY % parts SYSTEM 'parts.ent'> %parts; <!ENTITY % shared SYSTEM '../sub/b.dtd'> %shared;",
+            "<!ENTITY note SYSTEM 'note.txt'>")).unwrap();
+        fs::write(modules.join("parts.ent"), "<!ELEMENT m (#PCDATA)>").unwrap();
+        fs::write(modules.join("note.txt"), "from a module").unwrap();
+        fs::write(modules.join("escape.dtd"), "<!ENTITY % up SYSTEM '../../a.dtd'> %up;").unwrap();
+        let doc = parse_document("<!DOCTYPE m SYSTEM \"modules/main.dtd\"><m>&note;</m>", &resolver).unwrap();
+        assert_eq!(doc.root.text.as_deref(), Some("from a module"));
+        let dtd = doc.dtd.unwrap();
+        assert!(dtd.elements.contains_key("m") && dtd.elements.contains_key("b") && !dtd.elements.contains_key("wrong"));
+        let err = parse_document("<!DOCTYPE a SYSTEM \"modules/escape.dtd\"><a/>", &resolver).unwrap_err();
+        assert!(err.message.contains("could not load `modules/../../a.dtd`"), "{}", err.message);
+        assert!(err.message.contains("is outside of"), "{}", err.message);
+    }
+}
@@ -0,0 +1,570 @@
+/*!
+Reading structs back out of `XMLElement`s, the reverse of the `From` implementations used for serialization.
+
+`#[xml_element(..., from_xml)]` from `simple_xml_serialize_macro` implements `FromXml` along with `From`. It can
+also be implemented by hand with an `ElementReader`, which keeps track of what has been read so anything left
+over is reported.
+
+```rust
+use simple_xml_serialize::XMLElement;
+use simple_xml_serialize::from_xml::{ElementReader, FromXml, FromXmlError};
+
+struct Point {
+    lat: f32,
+    label: Option<String>,
+}
+
+impl FromXml for Point {
+    fn element_name() -> &'static str {
+        "point"
+    }
+
+    fn from_xml(element: &XMLElement) -> Result<Self, FromXmlError> {
+        let mut reader = ElementReader::new(element);
+        let lat = reader.attr("lat")?;
+        let label = reader.optional_text()?;
+        reader.finish()?;
+        Ok(Point{lat, label})
+    }
+}
+
+let point = Point::from_xml(&"<point lat='43.38'>home</point>".parse().unwrap()).unwrap();
+assert_eq!(point.lat, 43.38);
+assert_eq!(point.label, Some("home".to_string()));
+
+let error = Point::from_xml(&"<point lat='north' alt='3'/>".parse().unwrap()).err().unwrap();
+assert_eq!(error.to_string(), "/point: value `north` of attribute `lat` is not valid: invalid float literal");
+```
+*/
+
+use std::error::Error;
+use std::fmt;
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+use crate::XMLElement;
+
+/// Types that can be read from an `XMLElement`
+pub trait FromXml: Sized {
+    /// The name of the element this type is written as, used to find it among its siblings when a field is not renamed
+    fn element_name() -> &'static str;
+
+    /// Whether a child element called `name` holds this type. Defaults to comparing against `element_name`.
+    fn matches_name(name: &str) -> bool {
+        name == Self::element_name()
+    }
+
+    /// Reads the value from `element`. The name of `element` itself is not checked, as fields may be renamed.
+    fn from_xml(element: &XMLElement) -> Result<Self, FromXmlError>;
+
+    /// Reads the value from the parts of an element `reader` hasn't read yet, leaving the rest, for values flattened
+    /// into another. Defaults to reading the whole element with `from_xml`.
+    fn read_flattened(reader: &mut ElementReader) -> Result<Self, FromXmlError> {
+        let value = Self::from_xml(reader.element)?;
+        reader.use_everything();
+        Ok(value)
+    }
+}
+
+/// Why an element could not be read. The path starts at the element given to `FromXml::from_xml`.
+#[derive(Clone,PartialEq,Debug)]
+pub struct FromXmlError {
+    /// The path to the element with the problem, IE `/order/item[2]`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for FromXmlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl Error for FromXmlError {}
+
+/// A `bool` written as `1` or `0`, as `#[sxs_type_attr(bool_as = "numeric")]` fields are. Reads `true` and `false` too.
+///
+/// # Example
+///
+/// ```
+/// # use simple_xml_serialize::from_xml::NumericBool;
+/// assert_eq!(NumericBool(true).to_string(), "1");
+/// assert_eq!("0".parse(), Ok(NumericBool(false)));
+/// assert_eq!("true".parse(), Ok(NumericBool(true)));
+/// ```
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub struct NumericBool(pub bool);
+
+impl fmt::Display for NumericBool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", if self.0 { "1" } else { "0" })
+    }
+}
+
+impl FromStr for NumericBool {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" | "true" => Ok(NumericBool(true)),
+            "0" | "false" => Ok(NumericBool(false)),
+            _ => Err("expected `1` or `0`".to_string()),
+        }
+    }
+}
+
+/// Reads the attributes, child elements and text of an element one field at a time, remembering what was used
+/// so `finish` can report anything unexpected. Errors are given the path of the element being read.
+pub struct ElementReader<'a> {
+    element: &'a XMLElement,
+    used_attrs: Vec<bool>,
+    used_contents: Vec<bool>,
+    used_text: bool,
+}
+
+impl<'a> ElementReader<'a> {
+    /// Starts reading `element`
+    pub fn new(element: &'a XMLElement) -> Self {
+        ElementReader{
+            element,
+            used_attrs: vec![false; element.attrs.as_ref().map_or(0, |a| a.len())],
+            used_contents: vec![false; element.contents.as_ref().map_or(0, |c| c.len())],
+            used_text: false,
+        }
+    }
+
+    /// An error with the path of the element being read
+    pub fn error(&self, message: impl ToString) -> FromXmlError {
+        FromXmlError{path: format!("/{}", self.element.name), message: message.to_string()}
+    }
+
+    /// Parses the attribute `name`, failing if it is missing
+    /// # Arguments
+    ///
+    /// * `name` - The name of the attribute
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use simple_xml_serialize::XMLElement;
+    /// # use simple_xml_serialize::from_xml::ElementReader;
+    /// let ele = XMLElement::new("person").attr("age", 28);
+    /// let mut reader = ElementReader::new(&ele);
+    /// assert_eq!(reader.attr::<u8>("age"), Ok(28));
+    /// assert_eq!(reader.attr::<u8>("height").unwrap_err().to_string(), "/person: missing attribute `height`");
+    /// ```
+    pub fn attr<T>(&mut self, name: &str) -> Result<T, FromXmlError> where T: FromStr, T::Err: fmt::Display {
+        self.attr_with(name, str::parse)
+    }
+
+    /// Parses the attribute `name`, if it is there
+    pub fn optional_attr<T>(&mut self, name: &str) -> Result<Option<T>, FromXmlError> where T: FromStr, T::Err: fmt::Display {
+        self.optional_attr_with(name, str::parse)
+    }
+
+    /// Same as `attr`, but parses the value with `parse` rather than `FromStr`
+    /// # Arguments
+    ///
+    /// * `name` - The name of the attribute
+    /// * `parse` - Parses the value of the attribute
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use simple_xml_serialize::XMLElement;
+    /// # use simple_xml_serialize::from_xml::ElementReader;
+    /// let ele = XMLElement::new("person").attr("age", "28y");
+    /// let mut reader = ElementReader::new(&ele);
+    /// let age = reader.attr_with("age", |value| value.trim_end_matches('y').parse::<u8>());
+    /// assert_eq!(age, Ok(28));
+    /// ```
+    pub fn attr_with<T, E, F>(&mut self, name: &str, parse: F) -> Result<T, FromXmlError>
+        where E: fmt::Display, F: FnOnce(&str) -> Result<T, E>
+    {
+        match self.optional_attr_with(name, parse)? {
+            Some(value) => Ok(value),
+            None => Err(self.error(format!("missing attribute `{}`", name))),
+        }
+    }
+
+    /// Same as `optional_attr`, but parses the value with `parse` rather than `FromStr`
+    pub fn optional_attr_with<T, E, F>(&mut self, name: &str, parse: F) -> Result<Option<T>, FromXmlError>
+        where E: fmt::Display, F: FnOnce(&str) -> Result<T, E>
+    {
+        let attrs = self.element.attrs.as_deref().unwrap_or(&[]);
+        let index = match attrs.iter().position(|a| a.name == name) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        self.used_attrs[index] = true;
+        let value = &attrs[index].value;
+        parse(value).map(Some).map_err(|e| self.error(format!("value `{}` of attribute `{}` is not valid: {}", value, name, e)))
+    }
+
+    /// Reads the first child element that has not been read yet and holds a `T`, failing if there is none
+    /// # Arguments
+    ///
+    /// * `name` - The name of the child element, or `None` to use `T::matches_name`
+    pub fn element<T: FromXml>(&mut self, name: Option<&str>) -> Result<T, FromXmlError> {
+        match self.optional_element(name)? {
+            Some(value) => Ok(value),
+            None => Err(self.error(format!("missing element `{}`", name.unwrap_or(T::element_name())))),
+        }
+    }
+
+    /// Reads the first child element that has not been read yet and holds a `T`, if there is one
+    pub fn optional_element<T: FromXml>(&mut self, name: Option<&str>) -> Result<Option<T>, FromXmlError> {
+        let contents = self.element.contents.as_deref().unwrap_or(&[]);
+        let index = (0..contents.len()).find(|i| !self.used_contents[*i] && Self::matches::<T>(&contents[*i], name));
+        match index {
+            Some(index) => self.read_child(index).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads every child element that has not been read yet and holds a `T`
+    pub fn elements<T: FromXml>(&mut self, name: Option<&str>) -> Result<Vec<T>, FromXmlError> {
+        let contents = self.element.contents.as_deref().unwrap_or(&[]);
+        let mut values = Vec::new();
+        for (index, child) in contents.iter().enumerate() {
+            if !self.used_contents[index] && Self::matches::<T>(child, name) {
+                values.push(self.read_child(index)?);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Same as `elements`, but gives `None` when there are no matching child elements
+    pub fn optional_elements<T: FromXml>(&mut self, name: Option<&str>) -> Result<Option<Vec<T>>, FromXmlError> {
+        let values = self.elements(name)?;
+        Ok(if values.is_empty() { None } else { Some(values) })
+    }
+
+    /// Reads the items of the first child element called `wrapper` that has not been read yet, if there is one.
+    /// Anything else inside the wrapper is an error.
+    /// # Arguments
+    ///
+    /// * `wrapper` - The name of the element around the items
+    /// * `name` - The name of the items, or `None` to use `T::matches_name`
+    pub fn wrapped_elements<T: FromXml>(&mut self, wrapper: &str, name: Option<&str>) -> Result<Option<Vec<T>>, FromXmlError> {
+        let contents = self.element.contents.as_deref().unwrap_or(&[]);
+        match (0..contents.len()).find(|i| !self.used_contents[*i] && contents[*i].name == wrapper) {
+            Some(index) => self.read_child_with(index, |wrapper| {
+                let mut reader = ElementReader::new(wrapper);
+                let items = reader.elements(name)?;
+                reader.finish()?;
+                Ok(Some(items))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads every child element called `entry` that has not been read yet as a key and value, with the key in the
+    /// attribute `key_attr` and the value in the text. Anything else inside an entry is an error.
+    /// # Arguments
+    ///
+    /// * `entry` - The name of the element written for each entry
+    /// * `key_attr` - The attribute holding the key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::collections::BTreeMap;
+    /// # use simple_xml_serialize::XMLElement;
+    /// # use simple_xml_serialize::from_xml::ElementReader;
+    /// let ele = XMLElement::new("config").element(XMLElement::new("property").attr("name", "port").text(80));
+    /// let mut reader = ElementReader::new(&ele);
+    /// let properties: BTreeMap<String, u16> = reader.text_entries("property", "name").unwrap();
+    /// assert_eq!(properties["port"], 80);
+    /// ```
+    pub fn text_entries<K, V, M>(&mut self, entry: &str, key_attr: &str) -> Result<M, FromXmlError>
+        where K: FromStr, K::Err: fmt::Display, V: FromStr, V::Err: fmt::Display, M: FromIterator<(K, V)>
+    {
+        self.entries_with(entry, |reader| Ok((reader.attr(key_attr)?, reader.text()?)))
+    }
+
+    /// Same as `text_entries`, but with the value read from the only child element of each entry
+    pub fn element_entries<K, V, M>(&mut self, entry: &str, key_attr: &str) -> Result<M, FromXmlError>
+        where K: FromStr, K::Err: fmt::Display, V: FromXml, M: FromIterator<(K, V)>
+    {
+        self.entries_with(entry, |reader| Ok((reader.attr(key_attr)?, reader.element(None)?)))
+    }
+
+    /// Parses every attribute that has not been read yet as a key and value. Namespace declarations are left alone.
+    pub fn attr_entries<K, V, M>(&mut self) -> Result<M, FromXmlError>
+        where K: FromStr, K::Err: fmt::Display, V: FromStr, V::Err: fmt::Display, M: FromIterator<(K, V)>
+    {
+        let attrs = self.element.attrs.as_deref().unwrap_or(&[]);
+        let mut entries = Vec::new();
+        for (index, a) in attrs.iter().enumerate() {
+            if self.used_attrs[index] || a.name == "xmlns" || a.name.starts_with("xmlns:") {
+                continue;
+            }
+            self.used_attrs[index] = true;
+            let key = a.name.parse().map_err(|e| self.error(format!("attribute name `{}` is not valid: {}", a.name, e)))?;
+            let value = a.value.parse().map_err(|e| self.error(format!("value `{}` of attribute `{}` is not valid: {}", a.value, a.name, e)))?;
+            entries.push((key, value));
+        }
+        Ok(entries.into_iter().collect())
+    }
+
+    fn entries_with<T, M, F>(&mut self, entry: &str, read: F) -> Result<M, FromXmlError>
+        where M: FromIterator<T>, F: Fn(&mut ElementReader) -> Result<T, FromXmlError>
+    {
+        let contents = self.element.contents.as_deref().unwrap_or(&[]);
+        let mut entries = Vec::new();
+        for (index, child) in contents.iter().enumerate() {
+            if !self.used_contents[index] && child.name == entry {
+                entries.push(self.read_child_with(index, |child| {
+                    let mut reader = ElementReader::new(child);
+                    let value = read(&mut reader)?;
+                    reader.finish()?;
+                    Ok(value)
+                })?);
+            }
+        }
+        Ok(entries.into_iter().collect())
+    }
+
+    /// Reads the first child element called `name` that has not been read yet with `read`, failing if there is none.
+    /// For values read from elements without implementing `FromXml`, like the variants of an enum.
+    /// # Arguments
+    ///
+    /// * `name` - The name of the child element
+    /// * `read` - Reads the value from the child element
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use simple_xml_serialize::XMLElement;
+    /// # use simple_xml_serialize::from_xml::ElementReader;
+    /// let ele = XMLElement::new("shape").element(XMLElement::new("circle").attr("r", 2));
+    /// let mut reader = ElementReader::new(&ele);
+    /// let radius = reader.element_with("circle", |circle| ElementReader::new(circle).attr::<u32>("r"));
+    /// assert_eq!(radius, Ok(2));
+    /// ```
+    pub fn element_with<T, F>(&mut self, name: &str, read: F) -> Result<T, FromXmlError>
+        where F: FnOnce(&XMLElement) -> Result<T, FromXmlError>
+    {
+        match self.optional_element_with(name, read)? {
+            Some(value) => Ok(value),
+            None => Err(self.error(format!("missing element `{}`", name))),
+        }
+    }
+
+    /// Same as `element_with`, but gives `None` when there is no such child element
+    pub fn optional_element_with<T, F>(&mut self, name: &str, read: F) -> Result<Option<T>, FromXmlError>
+        where F: FnOnce(&XMLElement) -> Result<T, FromXmlError>
+    {
+        let contents = self.element.contents.as_deref().unwrap_or(&[]);
+        match (0..contents.len()).find(|i| !self.used_contents[*i] && contents[*i].name == name) {
+            Some(index) => self.read_child_with(index, read).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn use_everything(&mut self) {
+        self.used_attrs.iter_mut().for_each(|used| *used = true);
+        self.used_contents.iter_mut().for_each(|used| *used = true);
+        self.used_text = true;
+    }
+
+    fn matches<T: FromXml>(child: &XMLElement, name: Option<&str>) -> bool {
+        match name {
+            Some(name) => child.name == name,
+            None => T::matches_name(&child.name),
+        }
+    }
+
+    fn read_child<T: FromXml>(&mut self, index: usize) -> Result<T, FromXmlError> {
+        self.read_child_with(index, T::from_xml)
+    }
+
+    fn read_child_with<T, F>(&mut self, index: usize, read: F) -> Result<T, FromXmlError>
+        where F: FnOnce(&XMLElement) -> Result<T, FromXmlError>
+    {
+        self.used_contents[index] = true;
+        let contents = self.element.contents.as_deref().unwrap_or(&[]);
+        let child = &contents[index];
+        read(child).map_err(|mut e| {
+            // swap the child's own first segment for one with its index among same named siblings
+            let mut segment = format!("/{}", child.name);
+            let rest = e.path.strip_prefix(&segment).unwrap_or(&e.path).to_string();
+            if contents.iter().filter(|c| c.name == child.name).count() > 1 {
+                let nth = contents[..index].iter().filter(|c| c.name == child.name).count() + 1;
+                segment.push_str(&format!("[{}]", nth));
+            }
+            e.path = format!("/{}{}{}", self.element.name, segment, rest);
+            e
+        })
+    }
+
+    /// Parses the text of the element. Missing text is parsed as an empty string.
+    pub fn text<T>(&mut self) -> Result<T, FromXmlError> where T: FromStr, T::Err: fmt::Display {
+        self.text_with(str::parse)
+    }
+
+    /// Parses the text of the element, if it has any
+    pub fn optional_text<T>(&mut self) -> Result<Option<T>, FromXmlError> where T: FromStr, T::Err: fmt::Display {
+        self.optional_text_with(str::parse)
+    }
+
+    /// Same as `text`, but parses the text with `parse` rather than `FromStr`
+    pub fn text_with<T, E, F>(&mut self, parse: F) -> Result<T, FromXmlError>
+        where E: fmt::Display, F: FnOnce(&str) -> Result<T, E>
+    {
+        self.used_text = true;
+        let text = self.element.text.as_deref().unwrap_or("");
+        parse(text).map_err(|e| self.error(format!("text `{}` is not valid: {}", text, e)))
+    }
+
+    /// Same as `optional_text`, but parses the text with `parse` rather than `FromStr`
+    pub fn optional_text_with<T, E, F>(&mut self, parse: F) -> Result<Option<T>, FromXmlError>
+        where E: fmt::Display, F: FnOnce(&str) -> Result<T, E>
+    {
+        match self.element.text {
+            Some(_) => self.text_with(parse).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Fails on the first attribute, child element or text that was not read. Namespace declarations and
+    /// whitespace are ignored.
+    pub fn finish(self) -> Result<(), FromXmlError> {
+        let attrs = self.element.attrs.as_deref().unwrap_or(&[]);
+        let unexpected = attrs.iter().zip(&self.used_attrs)
+            .find(|(a, used)| !**used && a.name != "xmlns" && !a.name.starts_with("xmlns:"));
+        if let Some((a, _)) = unexpected {
+            return Err(self.error(format!("unexpected attribute `{}`", a.name)));
+        }
+        let contents = self.element.contents.as_deref().unwrap_or(&[]);
+        if let Some((c, _)) = contents.iter().zip(&self.used_contents).find(|(_, used)| !**used) {
+            return Err(self.error(format!("unexpected element `{}`", c.name)));
+        }
+        match &self.element.text {
+            Some(text) if !self.used_text && !text.trim().is_empty() => Err(self.error(format!("unexpected text `{}`", text.trim()))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(PartialEq,Debug)]
+    struct Item {
+        sku: String,
+        qty: u32,
+    }
+
+    impl FromXml for Item {
+        fn element_name() -> &'static str {
+            "item"
+        }
+
+        fn from_xml(element: &XMLElement) -> Result<Self, FromXmlError> {
+            let mut reader = ElementReader::new(element);
+            let sku = reader.attr("sku")?;
+            let qty = reader.text()?;
+            reader.finish()?;
+            Ok(Item{sku, qty})
+        }
+    }
+
+    #[derive(PartialEq,Debug)]
+    struct Order {
+        id: u32,
+        note: Option<String>,
+        first: Item,
+        items: Vec<Item>,
+        gifts: Option<Vec<Item>>,
+    }
+
+    impl FromXml for Order {
+        fn element_name() -> &'static str {
+            "order"
+        }
+
+        fn from_xml(element: &XMLElement) -> Result<Self, FromXmlError> {
+            let mut reader = ElementReader::new(element);
+            let id = reader.attr("id")?;
+            let note = reader.optional_attr("note")?;
+            let first = reader.element(Some("first"))?;
+            let items = reader.elements(None)?;
+            let gifts = reader.optional_elements(Some("gift"))?;
+            reader.finish()?;
+            Ok(Order{id, note, first, items, gifts})
+        }
+    }
+
+    fn read(xml: &str) -> Result<Order, String> {
+        Order::from_xml(&xml.parse().unwrap()).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn from_xml_reads_fields() {
+        let order = read(r#"<order id="7"><item sku="a">1</item><first sku="f">3</first><item sku="b">2</item></order>"#).unwrap();
+        assert_eq!(order, Order{
+            id: 7,
+            note: None,
+            first: Item{sku: "f".to_string(), qty: 3},
+            items: vec![Item{sku: "a".to_string(), qty: 1}, Item{sku: "b".to_string(), qty: 2}],
+            gifts: None,
+        });
+    }
+
+    #[test]
+    fn from_xml_error_paths() {
+        assert_eq!(read(r#"<order><first sku="f">3</first></order>"#).unwrap_err(), "/order: missing attribute `id`");
+        assert_eq!(read(r#"<order id="1"/>"#).unwrap_err(), "/order: missing element `first`");
+        assert_eq!(read(r#"<order id="1" extra="x"><first sku="f">3</first></order>"#).unwrap_err(), "/order: unexpected attribute `extra`");
+        assert_eq!(read(r#"<order id="1"><first sku="f">3</first><other/></order>"#).unwrap_err(), "/order: unexpected element `other`");
+        assert_eq!(read(r#"<order id="1"><first sku="f">3</first>stray</order>"#).unwrap_err(), "/order: unexpected text `stray`");
+        assert_eq!(read(r#"<order id="1"><first>3</first></order>"#).unwrap_err(), "/order/first: missing attribute `sku`");
+        assert_eq!(read(r#"<order id="1"><first sku="f">3</first><item sku="a">1</item><item sku="b">x</item></order>"#).unwrap_err(),
+                   "/order/item[2]: text `x` is not valid: invalid digit found in string");
+    }
+
+    #[test]
+    fn from_xml_wrapped_elements() {
+        let ele: XMLElement = r#"<order><items><item sku="a">1</item><item sku="b">2</item></items></order>"#.parse().unwrap();
+        let mut reader = ElementReader::new(&ele);
+        let items: Option<Vec<Item>> = reader.wrapped_elements("items", None).unwrap();
+        assert_eq!(items.map(|items| items.len()), Some(2));
+        assert_eq!(reader.wrapped_elements::<Item>("gifts", Some("gift")), Ok(None));
+        assert_eq!(reader.finish(), Ok(()));
+
+        let ele: XMLElement = r#"<order><items><item sku="a">1</item><other/></items></order>"#.parse().unwrap();
+        let error = ElementReader::new(&ele).wrapped_elements::<Item>("items", None).unwrap_err();
+        assert_eq!(error.to_string(), "/order/items: unexpected element `other`");
+    }
+
+    #[test]
+    fn from_xml_read_flattened_defaults_to_whole_element() {
+        let ele: XMLElement = r#"<item sku="a">1</item>"#.parse().unwrap();
+        let mut reader = ElementReader::new(&ele);
+        assert_eq!(Item::read_flattened(&mut reader), Ok(Item{sku: "a".to_string(), qty: 1}));
+        assert_eq!(reader.finish(), Ok(()));
+    }
+
+    #[test]
+    fn from_xml_entries() {
+        use std::collections::BTreeMap;
+
+        let ele: XMLElement = r#"<order a="1" b="2"><line sku="x">3</line><line sku="y">4</line></order>"#.parse().unwrap();
+        let mut reader = ElementReader::new(&ele);
+        let lines: Vec<(String, u32)> = reader.text_entries("line", "sku").unwrap();
+        assert_eq!(lines, vec![("x".to_string(), 3), ("y".to_string(), 4)]);
+        let attrs: BTreeMap<String, u8> = reader.attr_entries().unwrap();
+        assert_eq!(attrs.len(), 2);
+        assert_eq!(reader.finish(), Ok(()));
+
+        let ele: XMLElement = r#"<order a="x"><line sku="x">3</line><line>4</line></order>"#.parse().unwrap();
+        let mut reader = ElementReader::new(&ele);
+        assert_eq!(reader.text_entries::<String, u32, Vec<_>>("line", "sku").unwrap_err().to_string(),
+                   "/order/line[2]: missing attribute `sku`");
+        assert_eq!(reader.attr_entries::<String, u8, Vec<_>>().unwrap_err().to_string(),
+                   "/order: value `x` of attribute `a` is not valid: invalid digit found in string");
+    }
+}
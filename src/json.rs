@@ -0,0 +1,420 @@
+/*!
+Converting between `XMLElement` trees and `serde_json::Value`s, enabled with the `json` feature.
+
+There is no single way to map XML onto JSON, so `JsonOptions` picks one of the common conventions and can then
+be adjusted:
+
+* `JsonOptions::badgerfish()` keeps everything: the root element is wrapped in an object, attributes become keys
+  prefixed with `@` and text goes under `$`. `<a id="1">hi</a>` becomes `{"a":{"@id":"1","$":"hi"}}`.
+* `JsonOptions::parker()` keeps only the shape of the data: the root name and attributes are dropped, elements holding
+  only text become plain values and empty elements become `null`. `<a><b>hi</b></a>` becomes `{"b":"hi"}`.
+* `JsonOptions::gdata()` is like BadgerFish, with unprefixed attributes and text under `$t`.
+  `<a id="1">hi</a>` becomes `{"a":{"id":"1","$t":"hi"}}`.
+
+In every convention, child elements become keys named after them. When a name appears more than once its elements
+are gathered into an array, which `JsonOptions::arrays` can change so that single elements are arrays too.
+With `typed_numbers`, text and attribute values that read back exactly as a JSON number or boolean are written
+as one instead of as a string.
+
+```rust
+# #[cfg(feature = "json")] {
+use simple_xml_serialize::XMLElement;
+use simple_xml_serialize::json::{from_json, to_json, JsonOptions};
+
+let order: XMLElement = r#"<order id="7"><item>a</item><item>b</item><total>9.5</total></order>"#.parse().unwrap();
+
+let badgerfish = JsonOptions::badgerfish();
+let json = to_json(&order, &badgerfish);
+assert_eq!(json.to_string(), r#"{"order":{"@id":"7","item":[{"$":"a"},{"$":"b"}],"total":{"$":"9.5"}}}"#);
+assert_eq!(from_json(&json, &badgerfish).unwrap(), order);
+
+let parker = JsonOptions::parker().typed_numbers(true);
+assert_eq!(to_json(&order, &parker).to_string(), r#"{"item":["a","b"],"total":9.5}"#);
+# }
+```
+
+# Lossy cases
+
+Converting to JSON and back gives an equal tree except in these cases:
+
+* JSON objects do not keep the order of their keys, unless `serde_json` is built with its `preserve_order` feature.
+  Otherwise attributes, and groups of child elements with the same name, come back in alphabetical order.
+  Children with the same name always keep their order among themselves.
+* Elements with different names that were interleaved, IE `<b/><c/><b/>`, come back grouped by name as `<b/><b/><c/>`.
+* With `typed_numbers`, values are only typed when they read back the same, so `007` and `1e3` stay strings.
+  Otherwise all values are written as strings. Numbers and booleans read from JSON always become their usual text.
+* Parker drops the root element's name, every attribute, and the text of elements that also have children.
+  It cannot tell `<a/>` from `<a></a>`. Reading Parker JSON back gives the root the name set with `JsonOptions::root`.
+* In GData, an attribute and a child element with the same name share a key, and the child element wins. Reading
+  GData back, simple values become attributes and objects become elements, so an array of simple values cannot be
+  told apart from repeated attributes. Such arrays are read as repeated elements holding text.
+* BadgerFish and GData cannot hold an attribute whose name, with its prefix, equals the text key.
+*/
+
+use std::error;
+use std::fmt;
+
+use serde_json::{Map, Number, Value};
+
+use crate::XMLElement;
+
+/// One of the well known ways of writing XML as JSON, used as a starting point for `JsonOptions`
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub enum Convention {
+    BadgerFish,
+    Parker,
+    GData,
+}
+
+/// When child elements are written as a JSON array
+#[derive(Clone,PartialEq,Debug)]
+pub enum Arrays {
+    /// Only when an element has several children with the same name
+    Auto,
+    /// Always, even for a single child
+    Always,
+    /// Always for children with these names, otherwise like `Auto`
+    Named(Vec<String>),
+}
+
+/// How elements are written as JSON. Start from `badgerfish`, `parker` or `gdata` and adjust with the builder methods.
+#[derive(Clone,PartialEq,Debug)]
+pub struct JsonOptions {
+    convention: Convention,
+    attr_prefix: Option<String>,
+    text_key: Option<String>,
+    arrays: Arrays,
+    typed_numbers: bool,
+    root: String,
+}
+
+impl JsonOptions {
+    /// The defaults of a convention. See the module documentation for what each one does.
+    pub fn new(convention: Convention) -> Self {
+        let (attr_prefix, text_key) = match convention {
+            Convention::BadgerFish => (Some("@"), Some("$")),
+            Convention::Parker => (None, None),
+            Convention::GData => (Some(""), Some("$t")),
+        };
+        JsonOptions{
+            convention,
+            attr_prefix: attr_prefix.map(String::from),
+            text_key: text_key.map(String::from),
+            arrays: Arrays::Auto,
+            typed_numbers: false,
+            root: "root".to_string(),
+        }
+    }
+
+    /// The BadgerFish convention: attributes prefixed with `@`, text under `$`
+    pub fn badgerfish() -> Self {
+        Self::new(Convention::BadgerFish)
+    }
+
+    /// The Parker convention: no root name, no attributes, text as plain values
+    pub fn parker() -> Self {
+        Self::new(Convention::Parker)
+    }
+
+    /// The GData convention: attributes without a prefix, text under `$t`
+    pub fn gdata() -> Self {
+        Self::new(Convention::GData)
+    }
+
+    /// Sets the prefix of attribute keys, or drops attributes when `None`
+    /// # Arguments
+    ///
+    /// * `prefix` - The prefix, which may be empty to make simple values attributes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "json")] {
+    /// # use simple_xml_serialize::XMLElement;
+    /// use simple_xml_serialize::json::{to_json, JsonOptions};
+    /// let options = JsonOptions::badgerfish().attr_prefix(Some("-"));
+    /// assert_eq!(to_json(&XMLElement::new("a").attr("id", 1), &options).to_string(), r#"{"a":{"-id":"1"}}"#);
+    /// # }
+    /// ```
+    pub fn attr_prefix(mut self, prefix: Option<&str>) -> Self {
+        self.attr_prefix = prefix.map(String::from);
+        self
+    }
+
+    /// Sets the key text is written under. With `None`, elements holding only text are written as plain values,
+    /// and the text of other elements is dropped.
+    pub fn text_key(mut self, key: Option<&str>) -> Self {
+        self.text_key = key.map(String::from);
+        self
+    }
+
+    /// Sets when child elements are written as arrays
+    pub fn arrays(mut self, arrays: Arrays) -> Self {
+        self.arrays = arrays;
+        self
+    }
+
+    /// Writes values that read back exactly as a JSON number or boolean as one, instead of as a string
+    pub fn typed_numbers(mut self, typed: bool) -> Self {
+        self.typed_numbers = typed;
+        self
+    }
+
+    /// Sets the name given to the root element when reading JSON that has no root name, as written by Parker
+    pub fn root(mut self, name: &str) -> Self {
+        self.root = name.to_string();
+        self
+    }
+
+    fn wraps_root(&self) -> bool {
+        self.convention != Convention::Parker
+    }
+
+    fn always_array(&self, name: &str) -> bool {
+        match &self.arrays {
+            Arrays::Auto => false,
+            Arrays::Always => true,
+            Arrays::Named(names) => names.iter().any(|n| n == name),
+        }
+    }
+}
+
+/// Why a JSON value could not be read as XML
+#[derive(Clone,PartialEq,Debug)]
+pub struct JsonError {
+    /// The path to the element being built, IE `/order/item`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl error::Error for JsonError {}
+
+/// Writes an element tree as JSON
+/// # Arguments
+///
+/// * `element` - The root of the tree
+/// * `options` - The convention to use
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "json")] {
+/// # use simple_xml_serialize::XMLElement;
+/// use simple_xml_serialize::json::{to_json, JsonOptions};
+/// let ele = XMLElement::new("person").attr("age", 28).text("John Doe");
+/// assert_eq!(to_json(&ele, &JsonOptions::gdata()).to_string(), r#"{"person":{"$t":"John Doe","age":"28"}}"#);
+/// # }
+/// ```
+pub fn to_json(element: &XMLElement, options: &JsonOptions) -> Value {
+    let value = element_to_value(element, options);
+    if options.wraps_root() {
+        let mut root = Map::new();
+        root.insert(element.name.clone(), value);
+        Value::Object(root)
+    } else {
+        value
+    }
+}
+
+fn scalar_to_value(text: &str, options: &JsonOptions) -> Value {
+    if options.typed_numbers {
+        match text {
+            "true" => return Value::Bool(true),
+            "false" => return Value::Bool(false),
+            _ => {},
+        }
+        // only type numbers that are written back the same way
+        if let Ok(number) = text.parse::<Number>() {
+            if number.to_string() == text {
+                return Value::Number(number);
+            }
+        }
+    }
+    Value::String(text.to_string())
+}
+
+fn element_to_value(element: &XMLElement, options: &JsonOptions) -> Value {
+    let attrs = match options.attr_prefix {
+        Some(_) => element.attrs.as_deref().unwrap_or(&[]),
+        None => &[],
+    };
+    let contents = element.contents.as_deref().unwrap_or(&[]);
+
+    if options.text_key.is_none() && attrs.is_empty() && contents.is_empty() {
+        return match &element.text {
+            Some(text) => scalar_to_value(text, options),
+            None => Value::Null,
+        };
+    }
+
+    let mut map = Map::new();
+    if let Some(prefix) = &options.attr_prefix {
+        for a in attrs {
+            map.insert(format!("{}{}", prefix, a.name), scalar_to_value(&a.value, options));
+        }
+    }
+    let mut names: Vec<&str> = Vec::new();
+    for c in contents {
+        if !names.contains(&c.name.as_str()) {
+            names.push(&c.name);
+        }
+    }
+    for name in names {
+        let mut items: Vec<Value> = contents.iter().filter(|c| c.name == name).map(|c| element_to_value(c, options)).collect();
+        let value = if items.len() == 1 && !options.always_array(name) { items.remove(0) } else { Value::Array(items) };
+        map.insert(name.to_string(), value);
+    }
+    if let (Some(key), Some(text)) = (&options.text_key, &element.text) {
+        map.insert(key.clone(), scalar_to_value(text, options));
+    }
+    Value::Object(map)
+}
+
+/// Reads an element tree from JSON written with the same convention
+/// # Arguments
+///
+/// * `value` - The JSON to read
+/// * `options` - The convention it was written with
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "json")] {
+/// use simple_xml_serialize::json::{from_json, JsonOptions};
+/// let json = serde_json::json!({"item": ["a", "b"], "total": 9.5});
+/// let ele = from_json(&json, &JsonOptions::parker().root("order")).unwrap();
+/// assert_eq!(ele.to_string(), "<order><item>a</item><item>b</item><total>9.5</total></order>");
+/// # }
+/// ```
+pub fn from_json(value: &Value, options: &JsonOptions) -> Result<XMLElement, JsonError> {
+    if !options.wraps_root() {
+        return value_to_element(&options.root, value, &format!("/{}", options.root), options);
+    }
+    match value {
+        Value::Object(map) if map.len() == 1 => {
+            let (name, inner) = map.iter().next().unwrap_or_else(|| unreachable!());
+            value_to_element(name, inner, &format!("/{}", name), options)
+        },
+        _ => Err(JsonError{path: String::new(), message: "expected an object with a single key naming the root element".to_string()}),
+    }
+}
+
+fn value_to_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn value_to_element(name: &str, value: &Value, path: &str, options: &JsonOptions) -> Result<XMLElement, JsonError> {
+    let error = |message: String| JsonError{path: path.to_string(), message};
+    let mut element = XMLElement::new(name);
+    let map = match value {
+        Value::Null => return Ok(element),
+        Value::Array(_) => return Err(error("arrays must hold the elements of a key, not be nested in one another".to_string())),
+        Value::Object(map) => map,
+        scalar => return Ok(element.text(value_to_text(scalar).unwrap_or_default())),
+    };
+
+    for (key, value) in map {
+        if options.text_key.as_deref() == Some(key.as_str()) {
+            let text = value_to_text(value).ok_or_else(|| error(format!("text under `{}` must be a simple value", key)))?;
+            element.set_text(text);
+            continue;
+        }
+        match options.attr_prefix.as_deref() {
+            Some("") if value_to_text(value).is_some() => {
+                element.add_attr(key, value_to_text(value).unwrap_or_default());
+                continue;
+            },
+            Some(prefix) if !prefix.is_empty() && key.starts_with(prefix) => {
+                let text = value_to_text(value).ok_or_else(|| error(format!("attribute `{}` must be a simple value", key)))?;
+                element.add_attr(&key[prefix.len()..], text);
+                continue;
+            },
+            _ => {},
+        }
+        let child_path = format!("{}/{}", path, key);
+        match value {
+            Value::Array(items) => {
+                for item in items {
+                    element.add_element(value_to_element(key, item, &child_path, options)?);
+                }
+            },
+            value => element.add_element(value_to_element(key, value, &child_path, options)?),
+        }
+    }
+    Ok(element)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> XMLElement {
+        XMLElement::new("order")
+            .attr("id", "007")
+            .attr("paid", "true")
+            .element(XMLElement::new("item").attr("sku", "a").text("2"))
+            .element(XMLElement::new("item").attr("sku", "b").text("1.5"))
+            .element(XMLElement::new("note"))
+            .text("rush")
+    }
+
+    #[test]
+    fn json_badgerfish() {
+        let options = JsonOptions::badgerfish().typed_numbers(true);
+        let json = to_json(&sample(), &options);
+        assert_eq!(json, json!({"order": {
+            "@id": "007", "@paid": true, "$": "rush", "note": {},
+            "item": [{"@sku": "a", "$": 2}, {"@sku": "b", "$": 1.5}],
+        }}));
+        assert_eq!(from_json(&json, &options).unwrap(), sample());
+    }
+
+    #[test]
+    fn json_parker() {
+        let options = JsonOptions::parker().root("order");
+        let json = to_json(&sample(), &options);
+        assert_eq!(json, json!({"item": ["2", "1.5"], "note": null}));
+        let expected = XMLElement::new("order")
+            .element(XMLElement::new("item").text("2"))
+            .element(XMLElement::new("item").text("1.5"))
+            .element(XMLElement::new("note"));
+        assert_eq!(from_json(&json, &options).unwrap(), expected);
+
+        let options = options.arrays(Arrays::Named(vec!["note".to_string()]));
+        assert_eq!(to_json(&sample(), &options), json!({"item": ["2", "1.5"], "note": [null]}));
+    }
+
+    #[test]
+    fn json_gdata() {
+        let options = JsonOptions::gdata().arrays(Arrays::Always);
+        let json = to_json(&sample(), &options);
+        assert_eq!(json, json!({"order": {
+            "id": "007", "paid": "true", "$t": "rush", "note": [{}],
+            "item": [{"sku": "a", "$t": "2"}, {"sku": "b", "$t": "1.5"}],
+        }}));
+        assert_eq!(from_json(&json, &options).unwrap(), sample());
+    }
+
+    #[test]
+    fn json_errors() {
+        let options = JsonOptions::badgerfish();
+        assert_eq!(from_json(&json!({"a": 1, "b": 2}), &options).unwrap_err().message,
+                   "expected an object with a single key naming the root element");
+        assert_eq!(from_json(&json!({"a": {"b": {"@c": {}}}}), &options).unwrap_err().to_string(),
+                   "/a/b: attribute `@c` must be a simple value");
+        assert_eq!(from_json(&json!({"a": {"b": [[1]]}}), &options).unwrap_err().to_string(),
+                   "/a/b: arrays must hold the elements of a key, not be nested in one another");
+    }
+}
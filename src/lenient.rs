@@ -0,0 +1,481 @@
+/*!
+Best-effort parsing of malformed XML.
+
+Real world feeds are not always well formed. Where `parser::parse` would give up, `lenient::parse`
+repairs what it can, builds an `XMLElement` tree anyway and reports every repair as a `Diagnostic`
+so it can be logged or turned into an error later. This mode is never used implicitly; call it
+only for inputs you know to be unreliable.
+
+```rust
+use simple_xml_serialize::lenient;
+
+let input = "<feed><Item>Fish & Chips<br></item><item>x</item></feed><junk>";
+let (ele, diagnostics) = lenient::parse(input);
+assert_eq!(ele.unwrap().to_string(),
+           "<feed><Item><br/>Fish &amp; Chips</Item><item>x</item></feed>");
+for d in &diagnostics {
+    println!("{}", d); // IE "treated stray `&` as text at line 1, column 18"
+}
+assert_eq!(diagnostics.len(), 4);
+```
+
+The repairs made are:
+* a stray `&` or an unknown entity reference is kept as literal text
+* a `<` that does not start valid markup is kept as literal text
+* attributes without quotes or without a value are accepted, duplicate attributes are dropped
+* end tags match their start tag ignoring ASCII case
+* elements that are still open when an enclosing element ends are closed empty where they were opened,
+  leaving their text and children to the enclosing element in the order they were read
+* elements that are still open when the input ends are closed there
+* end tags that do not match any open element are ignored
+* text and markup before or after the root element is discarded
+*/
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::parser::{self, Position, Token, Tokenizer};
+use crate::{XMLAttr, XMLElement};
+
+/// The kind of repair a `Diagnostic` describes
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum DiagnosticKind {
+    /// A `&` that does not start an entity reference was treated as text
+    StrayAmpersand,
+    /// An entity reference other than the predefined ones was kept as text
+    UnknownEntity,
+    /// A `<` that does not start valid markup was treated as text
+    StrayLessThan,
+    /// A start tag was missing quotes, values or whitespace, or had duplicate attributes
+    MalformedTag,
+    /// An end tag matched its start tag only when ignoring case
+    CaseMismatch,
+    /// An element was closed without an end tag
+    AutoClosed,
+    /// An end tag without a matching start tag was ignored
+    UnmatchedEndTag,
+    /// A comment, CDATA section or similar was not terminated and runs to the end of the input
+    Unterminated,
+    /// Text or elements outside of the root element were discarded
+    OutsideRoot,
+}
+
+/// A repair made while parsing, along with where it was made
+#[derive(Clone,PartialEq,Debug)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    pub position: Position,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+/// Parses a possibly malformed XML document, repairing problems instead of failing on them
+/// # Arguments
+///
+/// * `input` - A string slice holding the document
+///
+/// # Returns
+///
+/// The root element, or `None` if the input contains no element at all, and a diagnostic for each
+/// repair that was made. Well formed input gives the same element as `parser::parse` and no diagnostics.
+///
+/// # Example
+///
+/// ```
+/// use simple_xml_serialize::lenient;
+/// use simple_xml_serialize::lenient::DiagnosticKind;
+/// let (ele, diagnostics) = lenient::parse("<p>one <br>two</p>");
+/// assert_eq!(ele.unwrap().to_string(), "<p><br/>one two</p>");
+/// assert_eq!(diagnostics[0].kind, DiagnosticKind::AutoClosed);
+/// assert_eq!(diagnostics[0].to_string(), "closed `<br>` at `</p>` at line 1, column 15");
+/// ```
+pub fn parse(input: &str) -> (Option<XMLElement>, Vec<Diagnostic>) {
+    let mut p = LenientParser{tokens: Tokenizer::new(input), diagnostics: Vec::new(), stack: Vec::new(), root: None};
+    if input.starts_with('\u{feff}') {
+        p.tokens.pos = '\u{feff}'.len_utf8();
+    }
+    p.parse();
+    (p.root, p.diagnostics)
+}
+
+/// An attribute's name, its raw value and where the value starts
+type LooseAttr<'a> = (&'a str, &'a str, usize);
+
+struct OpenElement {
+    ele: XMLElement,
+    text: String,
+    start: usize,
+}
+
+struct LenientParser<'a> {
+    tokens: Tokenizer<'a>,
+    diagnostics: Vec<Diagnostic>,
+    stack: Vec<OpenElement>,
+    root: Option<XMLElement>,
+}
+
+impl<'a> LenientParser<'a> {
+    fn diagnose(&mut self, kind: DiagnosticKind, offset: usize, message: impl ToString) {
+        let position = self.tokens.position(offset);
+        self.diagnostics.push(Diagnostic{kind, message: message.to_string(), position});
+    }
+
+    fn input(&self) -> &'a str {
+        self.tokens.input
+    }
+
+    fn parse(&mut self) {
+        loop {
+            let start = self.tokens.pos;
+            let token = match self.tokens.next_token() {
+                None => break,
+                Some(Ok(token)) => token,
+                Some(Err(_)) => {
+                    // start over from the same place and repair whatever the tokenizer rejected
+                    self.tokens.pos = start;
+                    self.recover(start);
+                    continue;
+                },
+            };
+            match token {
+                Token::StartTag(tag) => {
+                    let attrs = tag.attrs.iter().map(|a| (a.name, a.value, a.value_range.start)).collect();
+                    self.start_element(tag.name, attrs, tag.self_closing, tag.range.start);
+                },
+                Token::EndTag{name, range} => self.end_element(name, range),
+                Token::Text(range) => {
+                    let raw = &self.input()[range.clone()];
+                    self.text(raw, range.start, true);
+                },
+                Token::CData(range) => {
+                    let raw = &self.input()[range.clone()];
+                    self.text(raw, range.start, false);
+                },
+                Token::ProcessingInstruction(_) | Token::Comment(_) | Token::Doctype{..} => {},
+            }
+        }
+        while let Some(open) = self.stack.last() {
+            let (name, start) = (open.ele.name.clone(), open.start);
+            self.diagnose(DiagnosticKind::AutoClosed, start, format!("closed `<{}>` at the end of the input", name));
+            self.close();
+        }
+    }
+
+    /// Handles markup at `start` that the tokenizer could not read
+    fn recover(&mut self, start: usize) {
+        let rest = &self.input()[start..];
+        let unterminated = [("<!--", "comment"), ("<![CDATA[", "CDATA section"), ("<!DOCTYPE", "document type declaration"), ("<?", "processing instruction")];
+        for (open, what) in unterminated.iter() {
+            if rest.starts_with(open) {
+                self.diagnose(DiagnosticKind::Unterminated, start, format!("unterminated {} runs to the end of the input", what));
+                if *open == "<![CDATA[" {
+                    let cdata = format!("{}]]>", rest);
+                    self.text(&cdata, start, false);
+                }
+                self.tokens.pos = self.input().len();
+                return;
+            }
+        }
+
+        if rest.starts_with("</") {
+            let len = rest.find('>').map(|i| i + 1).unwrap_or(rest.len());
+            let name = rest[2..len].trim_end_matches('>').trim();
+            let name = name.split(parser::is_xml_whitespace).next().unwrap_or("");
+            self.tokens.pos = start + len;
+            if name.is_empty() {
+                self.diagnose(DiagnosticKind::UnmatchedEndTag, start, "ignored end tag without a name");
+            } else {
+                self.diagnose(DiagnosticKind::MalformedTag, start, format!("repaired malformed end tag `</{}>`", name));
+                self.end_element(name, start..start + len);
+            }
+            return;
+        }
+
+        match self.read_lenient_start_tag(start) {
+            Some((name, attrs, self_closing)) => {
+                self.diagnose(DiagnosticKind::MalformedTag, start, format!("repaired malformed start tag `<{}>`", name));
+                self.start_element(name, attrs, self_closing, start);
+            },
+            None => {
+                self.tokens.pos = start + 1;
+                self.diagnose(DiagnosticKind::StrayLessThan, start, "treated stray `<` as text");
+                self.text("&lt;", start, true);
+            },
+        }
+    }
+
+    /// Reads a start tag with unquoted or missing attribute values. Stops early at a `<` or the end of the input.
+    fn read_lenient_start_tag(&mut self, start: usize) -> Option<(&'a str, Vec<LooseAttr<'a>>, bool)> {
+        let input = self.input();
+        let name_len = input[start + 1..]
+            .find(|c| !parser::is_name_char(c))
+            .unwrap_or(input.len() - start - 1);
+        let name = &input[start + 1..start + 1 + name_len];
+        if !name.starts_with(parser::is_name_start_char) {
+            return None;
+        }
+        let mut pos = start + 1 + name_len;
+        let mut attrs = Vec::new();
+        let skip_ws = |pos: usize| pos + input[pos..].len() - input[pos..].trim_start_matches(parser::is_xml_whitespace).len();
+        loop {
+            pos = skip_ws(pos);
+            let rest = &input[pos..];
+            if rest.starts_with("/>") {
+                self.tokens.pos = pos + 2;
+                return Some((name, attrs, true));
+            }
+            if rest.starts_with('>') || rest.starts_with('<') || rest.is_empty() {
+                self.tokens.pos = if rest.starts_with('>') { pos + 1 } else { pos };
+                return Some((name, attrs, false));
+            }
+            let attr_len = rest.find(|c| c == '=' || c == '>' || c == '<' || c == '/' || parser::is_xml_whitespace(c)).unwrap_or(rest.len());
+            if attr_len == 0 {
+                // a stray `/` or `=`
+                pos += 1;
+                continue;
+            }
+            let attr_name = &rest[..attr_len];
+            pos = skip_ws(pos + attr_len);
+            let (value, value_start) = if input[pos..].starts_with('=') {
+                pos = skip_ws(pos + 1);
+                let rest = &input[pos..];
+                match rest.chars().next() {
+                    Some(q) if q == '"' || q == '\'' => {
+                        let len = rest[1..].find(q).unwrap_or(rest.len() - 1);
+                        let value = (&rest[1..1 + len], pos + 1);
+                        pos += (len + 2).min(rest.len());
+                        value
+                    },
+                    _ => {
+                        let len = rest.char_indices()
+                            .find(|&(i, c)| c == '>' || c == '<' || parser::is_xml_whitespace(c) || rest[i..].starts_with("/>"))
+                            .map(|(i, _)| i)
+                            .unwrap_or(rest.len());
+                        let value = (&rest[..len], pos);
+                        pos += len;
+                        value
+                    },
+                }
+            } else {
+                // a value-less attribute like `<input disabled>` gets its own name as the value
+                (attr_name, pos)
+            };
+            attrs.push((attr_name, value, value_start));
+        }
+    }
+
+    fn start_element(&mut self, name: &str, attrs: Vec<LooseAttr>, self_closing: bool, start: usize) {
+        if self.root.is_some() {
+            self.diagnose(DiagnosticKind::OutsideRoot, start, format!("discarded `<{}>` after the root element", name));
+            // nothing after the root element is kept, so skip straight to the end
+            self.tokens.pos = self.input().len();
+            return;
+        }
+        let mut ele = XMLElement::new(name);
+        for (attr, value, value_start) in attrs {
+            if ele.attrs.iter().flatten().any(|a| a.name == attr) {
+                self.diagnose(DiagnosticKind::MalformedTag, start, format!("dropped duplicate attribute `{}`", attr));
+                continue;
+            }
+            let value = self.unescape(value, value_start);
+            ele.attrs.get_or_insert_with(Vec::new).push(XMLAttr{name: attr.to_string(), value});
+        }
+        self.stack.push(OpenElement{ele, text: String::new(), start});
+        if self_closing {
+            self.close();
+        }
+    }
+
+    fn end_element(&mut self, name: &str, range: Range<usize>) {
+        let found = self.stack.iter().rposition(|o| o.ele.name.eq_ignore_ascii_case(name));
+        let index = match found {
+            Some(index) => index,
+            None => {
+                self.diagnose(DiagnosticKind::UnmatchedEndTag, range.start, format!("ignored unmatched end tag `</{}>`", name));
+                return;
+            },
+        };
+        while self.stack.len() > index + 1 {
+            let open_name = self.stack.last().map(|o| o.ele.name.clone()).unwrap_or_default();
+            self.diagnose(DiagnosticKind::AutoClosed, range.start, format!("closed `<{}>` at `</{}>`", open_name, name));
+            self.close_empty();
+        }
+        if self.stack[index].ele.name != name {
+            let msg = format!("matched `</{}>` to `<{}>` ignoring case", name, self.stack[index].ele.name);
+            self.diagnose(DiagnosticKind::CaseMismatch, range.start, msg);
+        }
+        self.close();
+    }
+
+    fn text(&mut self, raw: &str, start: usize, decode: bool) {
+        if self.stack.is_empty() {
+            if let Some(i) = raw.find(|c| !parser::is_xml_whitespace(c)) {
+                let place = if self.root.is_some() { "after" } else { "before" };
+                self.diagnose(DiagnosticKind::OutsideRoot, start + i, format!("discarded text {} the root element", place));
+            }
+            return;
+        }
+        let text = if decode { self.unescape(raw, start) } else { raw.to_string() };
+        if let Some(open) = self.stack.last_mut() {
+            open.text.push_str(&text);
+        }
+    }
+
+    /// Decodes entity references, keeping anything that is not a valid reference as literal text
+    fn unescape(&mut self, raw: &str, offset: usize) -> String {
+        let mut ret = String::with_capacity(raw.len());
+        let mut rest = raw;
+        while let Some(amp) = rest.find('&') {
+            ret.push_str(&rest[..amp]);
+            let at = offset + (raw.len() - rest.len()) + amp;
+            let after = &rest[amp + 1..];
+            let name_len = after.find(|c: char| !(c.is_alphanumeric() || c == '#')).unwrap_or(after.len());
+            if name_len > 0 && after[name_len..].starts_with(';') {
+                let entity = &after[..name_len];
+                match parser::decode_entity(entity) {
+                    Some(c) => ret.push(c),
+                    None => {
+                        self.diagnose(DiagnosticKind::UnknownEntity, at, format!("kept unknown entity `&{};` as text", entity));
+                        ret.push_str(&rest[amp..amp + name_len + 2]);
+                    },
+                }
+                rest = &after[name_len + 1..];
+            } else {
+                self.diagnose(DiagnosticKind::StrayAmpersand, at, "treated stray `&` as text");
+                ret.push('&');
+                rest = after;
+            }
+        }
+        ret.push_str(rest);
+        ret
+    }
+
+    fn close(&mut self) {
+        let mut open = match self.stack.pop() {
+            Some(open) => open,
+            None => return,
+        };
+        let text = open.text.trim_matches(parser::is_xml_whitespace);
+        if !text.is_empty() {
+            open.ele.text = Some(text.to_string());
+        }
+        match self.stack.last_mut() {
+            Some(parent) => parent.ele.add_element(open.ele),
+            None => self.root = Some(open.ele),
+        }
+    }
+
+    /// Closes the innermost element as if it had been empty, giving what was read inside it to its parent.
+    /// There is always a parent, as only elements inside the one being ended are closed this way.
+    fn close_empty(&mut self) {
+        let open = match self.stack.pop() {
+            Some(open) => open,
+            None => return,
+        };
+        let mut ele = open.ele;
+        let children = ele.contents.take();
+        if let Some(parent) = self.stack.last_mut() {
+            parent.ele.add_element(ele);
+            for child in children.into_iter().flatten() {
+                parent.ele.add_element(child);
+            }
+            parent.text.push_str(&open.text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(diagnostics: &[Diagnostic]) -> Vec<DiagnosticKind> {
+        diagnostics.iter().map(|d| d.kind).collect()
+    }
+
+    #[test]
+    fn lenient_well_formed() {
+        let input = r#"<?xml version="1.0"?><!-- c --><a x="1"><b>&lt;&#65;</b><![CDATA[<]]></a>"#;
+        let (ele, diagnostics) = parse(input);
+        assert_eq!(ele.unwrap(), parser::parse(input).unwrap());
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn lenient_ampersands() {
+        let (ele, diagnostics) = parse("<a t=\"R&D\">Fish & Chips &nbsp;&amp;</a>");
+        let ele = ele.unwrap();
+        assert_eq!(ele.attrs.unwrap()[0].value, "R&D");
+        assert_eq!(ele.text.unwrap(), "Fish & Chips &nbsp;&");
+        use DiagnosticKind::*;
+        assert_eq!(kinds(&diagnostics), vec![StrayAmpersand, StrayAmpersand, UnknownEntity]);
+        assert_eq!(diagnostics[1].position.column, 17);
+    }
+
+    #[test]
+    fn lenient_auto_close() {
+        let (ele, diagnostics) = parse("<ul>\n<li>one\n<li>two<br>\n</ul>");
+        let expected = XMLElement::new("ul")
+                        .element(XMLElement::new("li"))
+                        .element(XMLElement::new("li"))
+                        .element(XMLElement::new("br"))
+                        .text("one\ntwo");
+        assert_eq!(ele.unwrap(), expected);
+        assert_eq!(kinds(&diagnostics), vec![DiagnosticKind::AutoClosed; 3]);
+        assert_eq!(diagnostics[2].to_string(), "closed `<li>` at `</ul>` at line 4, column 1");
+
+        // what followed an unclosed element stays with its parent, after the text before it
+        let (ele, _) = parse("<p>one <br>two <b>bold</b> three</p>");
+        let expected = XMLElement::new("p")
+                        .element(XMLElement::new("br"))
+                        .element(XMLElement::new("b").text("bold"))
+                        .text("one two  three");
+        assert_eq!(ele.unwrap(), expected);
+
+        let (ele, diagnostics) = parse("<a><b>text");
+        assert_eq!(ele.unwrap(), XMLElement::new("a").element(XMLElement::new("b").text("text")));
+        assert_eq!(diagnostics[0].to_string(), "closed `<b>` at the end of the input at line 1, column 4");
+    }
+
+    #[test]
+    fn lenient_case_and_unmatched() {
+        let (ele, diagnostics) = parse("<Root><Item>1</ITEM></span></root>");
+        assert_eq!(ele.unwrap(), XMLElement::new("Root").element(XMLElement::new("Item").text(1)));
+        use DiagnosticKind::*;
+        assert_eq!(kinds(&diagnostics), vec![CaseMismatch, UnmatchedEndTag, CaseMismatch]);
+    }
+
+    #[test]
+    fn lenient_malformed_tags() {
+        let (ele, diagnostics) = parse("<form action=/go x='1' x=2><input disabled type=text/>a < b</form>");
+        let ele = ele.unwrap();
+        let expected = XMLElement::new("form")
+                        .attr("action", "/go")
+                        .attr("x", 1)
+                        .element(XMLElement::new("input").attr("disabled", "disabled").attr("type", "text"))
+                        .text("a < b");
+        assert_eq!(ele, expected);
+        use DiagnosticKind::*;
+        assert_eq!(kinds(&diagnostics), vec![MalformedTag, MalformedTag, MalformedTag, StrayLessThan]);
+    }
+
+    #[test]
+    fn lenient_outside_root() {
+        let (ele, diagnostics) = parse("junk<a/>more<b>ignored</b>");
+        assert_eq!(ele.unwrap(), XMLElement::new("a"));
+        use DiagnosticKind::*;
+        assert_eq!(kinds(&diagnostics), vec![OutsideRoot, OutsideRoot, OutsideRoot]);
+
+        let (ele, diagnostics) = parse("<a>x<!-- never closed");
+        assert_eq!(ele.unwrap(), XMLElement::new("a").text("x"));
+        assert_eq!(kinds(&diagnostics), vec![Unterminated, AutoClosed]);
+
+        let (ele, diagnostics) = parse("no elements here");
+        assert_eq!(ele, None);
+        assert_eq!(kinds(&diagnostics), vec![OutsideRoot]);
+    }
+}
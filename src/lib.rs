@@ -2,6 +2,7 @@
 # Simple XML Serialization
 This is a Rust crate for serialization of data to XML. `XMLElement`s can either be built
 manually, or the `simple_xml_serialize_macro` crate can be used to generate `From` implementations for structs. 
+XML text can be read back into `XMLElement`s with the `parser` module, and from there into structs with `from_xml::FromXml`.
 
 ## Example Usage
 ```rust
@@ -37,10 +38,38 @@ fn main() {
 */
 
 use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod codegen;
+pub mod document;
+pub mod dtd;
+pub mod from_xml;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod lenient;
+pub mod parser;
+mod pattern;
+pub mod rng;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod validation;
+pub mod xsd;
+
+pub use from_xml::{FromXml, FromXmlError};
+pub use rng::validate_rng;
 
 /// The basic type this crate provides. Functions are provided for setting/adding to the fields in this struct.
 /// Any manipulation past that is left to the user by accessing the fields directly.
+///
+/// With the `serde` feature, `XMLElement` and `XMLAttr` implement `Serialize` and `Deserialize` as plain structs
+/// holding their fields in the order they are declared, IE in JSON:
+/// `{"name":"a","contents":null,"text":"hi","attrs":[{"name":"id","value":"1"}]}`.
+/// This representation is stable, so trees stored by one version can be read by later ones, including through
+/// formats like bincode that rely on field order.
 #[derive(Clone,PartialEq,Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XMLElement {
     /// The tag for this element node. IE `<myelement/>`
     pub name: String,
@@ -53,6 +82,7 @@ pub struct XMLElement {
 }
 
 impl fmt::Display for XMLElement {
+    #[allow(clippy::needless_borrow)]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut ret = String::new();
         ret.push('<');
@@ -105,6 +135,7 @@ impl fmt::Display for XMLElement {
 }
 
 
+#[allow(clippy::needless_return, clippy::needless_borrows_for_generic_args)]
 fn split_cdata(text: &str) -> (String, Option<(String, String)>) {
     let cdata_start = "<![CDATA[";
     let cdata_end = "]]>";
@@ -128,6 +159,43 @@ impl From<&XMLElement> for XMLElement {
     }
 }
 
+impl FromStr for XMLElement {
+    type Err = parser::ParseError;
+
+    /// Parses an XML document into its root element. See the `parser` module for details.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parser::parse(s)
+    }
+}
+
+/// Anything a reference to can be turned into an `XMLElement`, IE everything with a `From<&T> for XMLElement` impl.
+/// `#[xml_element]` bounds the type parameters of generic structs with this rather than with `Into<XMLElement>`
+/// directly, which would have every `XMLElement::from` call in the crate consider the generic impls recursively.
+///
+/// # Example
+///
+/// ```rust
+/// use simple_xml_serialize::{ToXMLElement, XMLElement};
+///
+/// fn wrap<T: ToXMLElement>(name: &str, value: &T) -> XMLElement {
+///     let mut ele = XMLElement::new(name);
+///     ele.add_element(value.to_xml_element());
+///     ele
+/// }
+///
+/// let inner = XMLElement::new("inner");
+/// assert_eq!(wrap("outer", &inner).to_string(), "<outer><inner/></outer>");
+/// ```
+pub trait ToXMLElement {
+    fn to_xml_element(&self) -> XMLElement;
+}
+
+impl<T: ?Sized> ToXMLElement for T where for<'a> &'a T: Into<XMLElement> {
+    fn to_xml_element(&self) -> XMLElement {
+        self.into()
+    }
+}
+
 impl XMLElement {
 
     /// Constructs a new XMLElement with the given name and `None` for the rest of the fields
@@ -277,6 +345,7 @@ impl XMLElement {
     /// ele.add_element(MyPoint{});
     /// assert_eq!(ele.to_string(), String::from("<name><point/></name>"));
     /// ```
+    #[allow(clippy::vec_init_then_push)]
     pub fn add_element(&mut self, new_ele: impl Into<XMLElement>) {
         if let Some(ref mut ele_vec) = self.contents {
             ele_vec.push(new_ele.into());
@@ -431,6 +500,7 @@ impl XMLElement {
     /// </name>"#);
     /// assert_eq!(ele.to_string_pretty("\n", "  "), expected);
     /// ```
+    #[allow(clippy::needless_borrow)]
     pub fn to_string_pretty(&self, newline: &str, indent: &str) -> String {
         let mut ret = String::new();
         ret.push('<');
@@ -525,6 +595,7 @@ impl XMLElement {
 
 /// A key/value pair that is serialized inside the opening tag of an XMLElement.
 #[derive(Clone,PartialEq,Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XMLAttr {
     pub name: String,
     pub value: String,
@@ -898,6 +969,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_borrow)]
     fn test_split_cdata() {
         let input = "<![CDATA[]]>";
         let (before_cdata, opt_cdata) = split_cdata(&input);
@@ -954,4 +1026,23 @@ mod tests {
         assert_eq!(before_cdata, "");
         assert_eq!(opt_cdata, Some((String::from("<![CDATA[hel<![CDATA[lo]]>"), String::from("world"))));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn xmlelement_serde_formats() {
+        let ele = XMLElement::new("order")
+            .attr("id", 7)
+            .element(XMLElement::new("item").text("a < b"))
+            .element(XMLElement::new("empty"))
+            .text("<![CDATA[raw]]>");
+
+        let json = serde_json::to_string(&XMLElement::new("a").attr("id", 1).text("hi")).unwrap();
+        assert_eq!(json, r#"{"name":"a","contents":null,"text":"hi","attrs":[{"name":"id","value":"1"}]}"#);
+        assert_eq!(serde_json::from_str::<XMLElement>(&serde_json::to_string(&ele).unwrap()).unwrap(), ele);
+
+        assert_eq!(bincode::deserialize::<XMLElement>(&bincode::serialize(&ele).unwrap()).unwrap(), ele);
+
+        assert_eq!(rmp_serde::from_slice::<XMLElement>(&rmp_serde::to_vec(&ele).unwrap()).unwrap(), ele);
+        assert_eq!(rmp_serde::from_slice::<XMLElement>(&rmp_serde::to_vec_named(&ele).unwrap()).unwrap(), ele);
+    }
 }
\ No newline at end of file
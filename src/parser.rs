@@ -0,0 +1,767 @@
+/*!
+Reading XML text back into `XMLElement` trees.
+
+The parser is strict: input that is not well formed is rejected with a `ParseError` that says where
+the problem was found. Positions of elements and attributes can optionally be recorded in a
+`SourceMap`, which is useful for pointing users at the offending line of a config file.
+
+```rust
+use simple_xml_serialize::parser;
+
+let input = "<person age=\"28\">\n  <name>John Doe</name>\n</person>";
+let (ele, source_map) = parser::parse_with_positions(input).unwrap();
+
+let name = &ele.contents.as_ref().unwrap()[0];
+let pos = source_map.position_of(&ele, name).unwrap();
+assert_eq!(pos.start_tag.start.to_string(), "line 2, column 3");
+```
+
+Since `XMLElement` keeps its text separately from its child elements, text is collected per element,
+trimmed of surrounding whitespace, and whitespace-only text is dropped. Comments, processing
+instructions and the document type declaration are skipped, though entities declared in the internal
+DTD subset are expanded (see the `dtd` module for external subsets and validation). CDATA sections
+are kept verbatim in the text so they serialize the same way they were read.
+*/
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use crate::dtd::{self, Dtd, DtdResolver};
+use crate::{XMLAttr, XMLElement};
+
+/// A location in the parsed input. Lines and columns start at 1 and columns count characters, not bytes.
+#[derive(Clone,Copy,PartialEq,Eq,Debug,Default)]
+pub struct Position {
+    /// Byte offset from the start of the input
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// A section of the parsed input. `end` points just past the last character.
+#[derive(Clone,Copy,PartialEq,Eq,Debug,Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Where an element, its end tag and each of its attributes were found in the input.
+#[derive(Clone,PartialEq,Debug)]
+pub struct ElementPosition {
+    /// The opening tag including its attributes. IE `<myelement attr1="hello">`
+    pub start_tag: Span,
+    /// The closing tag. `None` for self-closing elements like `<myelement/>`
+    pub end_tag: Option<Span>,
+    /// The span of each `name="value"` pair, in the order they appear in the start tag
+    pub attrs: Vec<(String, Span)>,
+}
+
+impl ElementPosition {
+    /// Returns the span of the attribute with the given name, if the element had one
+    pub fn attr(&self, name: &str) -> Option<&Span> {
+        self.attrs.iter().find(|(n, _)| n == name).map(|(_, s)| s)
+    }
+}
+
+/// Side table of element positions filled in by `parse_with_positions`.
+///
+/// Positions are keyed by the path of child indices from the root, so they stay valid when the
+/// parsed tree is moved or cloned, but not once elements are added or removed.
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct SourceMap {
+    positions: HashMap<Vec<usize>, ElementPosition>,
+}
+
+impl SourceMap {
+    /// Looks up the position of an element that lives somewhere inside of `root`
+    /// # Arguments
+    ///
+    /// * `root` - The element returned by `parse_with_positions`, or a clone of it
+    /// * `ele` - A reference to `root` itself or to any element nested within it
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use simple_xml_serialize::parser;
+    /// let (root, map) = parser::parse_with_positions("<a>\n<b x=\"1\"/></a>").unwrap();
+    /// let b = &root.contents.as_ref().unwrap()[0];
+    /// let pos = map.position_of(&root, b).unwrap();
+    /// assert_eq!(pos.start_tag.start.line, 2);
+    /// assert_eq!(pos.attr("x").unwrap().start.column, 4);
+    /// ```
+    pub fn position_of(&self, root: &XMLElement, ele: &XMLElement) -> Option<&ElementPosition> {
+        path_to(root, ele).and_then(|path| self.position_at(&path))
+    }
+
+    /// Looks up the position of an element by its path of child indices from the root.
+    /// An empty path refers to the root itself.
+    pub fn position_at(&self, path: &[usize]) -> Option<&ElementPosition> {
+        self.positions.get(path)
+    }
+
+    pub(crate) fn insert(&mut self, path: Vec<usize>, pos: ElementPosition) {
+        self.positions.insert(path, pos);
+    }
+}
+
+/// Finds the path of child indices leading from `root` to `ele`, comparing by address
+pub(crate) fn path_to(root: &XMLElement, ele: &XMLElement) -> Option<Vec<usize>> {
+    if std::ptr::eq(root, ele) {
+        return Some(Vec::new());
+    }
+    if let Some(contents) = &root.contents {
+        for (i, c) in contents.iter().enumerate() {
+            if let Some(mut path) = path_to(c, ele) {
+                path.insert(0, i);
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+/// The error returned when the input is not well formed XML
+#[derive(Clone,PartialEq,Debug)]
+pub struct ParseError {
+    pub message: String,
+    pub position: Position,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a complete XML document into its root `XMLElement`
+/// # Arguments
+///
+/// * `input` - A string slice holding the document
+///
+/// # Example
+///
+/// ```
+/// use simple_xml_serialize::parser;
+/// let ele = parser::parse(r#"<?xml version="1.0"?><person age="28">John Doe</person>"#).unwrap();
+/// assert_eq!(ele.to_string(), r#"<person age="28">John Doe</person>"#);
+///
+/// let err = parser::parse("<person>\n  <name>\n</person>").unwrap_err();
+/// assert_eq!(err.to_string(), "expected `</name>` but found `</person>` at line 3, column 1");
+/// ```
+pub fn parse(input: &str) -> Result<XMLElement, ParseError> {
+    parse_full(input, false, None).map(|(ele, _, _)| ele)
+}
+
+/// Parses a complete XML document into its root `XMLElement`, recording where every element and
+/// attribute was found
+/// # Arguments
+///
+/// * `input` - A string slice holding the document
+///
+/// # Example
+///
+/// ```
+/// use simple_xml_serialize::parser;
+/// let (ele, map) = parser::parse_with_positions("<person>\n  <name/>\n</person>").unwrap();
+/// let pos = map.position_at(&[]).unwrap();
+/// assert_eq!(pos.end_tag.unwrap().start.to_string(), "line 3, column 1");
+/// ```
+pub fn parse_with_positions(input: &str) -> Result<(XMLElement, SourceMap), ParseError> {
+    parse_full(input, true, None).map(|(ele, map, _)| (ele, map))
+}
+
+/// Parses a document along with its DTD. The external subset is only loaded when there is a `resolver`.
+pub(crate) fn parse_full(input: &str, record_positions: bool, resolver: Option<&dyn DtdResolver>) -> Result<(XMLElement, SourceMap, Option<Dtd>), ParseError> {
+    Parser{tokens: Tokenizer::new(input), record_positions, resolver, dtd: None, entities: HashMap::new()}.parse()
+}
+
+/// Maps byte offsets of an input to lines and columns
+#[derive(Clone,Debug)]
+pub(crate) struct LineIndex {
+    starts: Vec<usize>,
+    /// the last `(line, offset, column)` looked up. Positions are asked for close to each other, so counting
+    /// from here keeps long lines from being counted again for every position on them
+    last: Cell<(usize, usize, usize)>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(input: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(input.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex{starts, last: Cell::new((0, 0, 1))}
+    }
+
+    pub(crate) fn position(&self, input: &str, offset: usize) -> Position {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(l) => l,
+            Err(l) => l - 1,
+        };
+        let (last_line, last_offset, last_column) = self.last.get();
+        let column = if last_line != line {
+            input[self.starts[line]..offset].chars().count() + 1
+        } else if last_offset <= offset {
+            last_column + input[last_offset..offset].chars().count()
+        } else {
+            last_column - input[offset..last_offset].chars().count()
+        };
+        self.last.set((line, offset, column));
+        Position{offset, line: line + 1, column}
+    }
+}
+
+/// A single attribute as it appears in a start tag, before entity references are decoded
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) struct RawAttr<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) value: &'a str,
+    /// covers `name="value"`
+    pub(crate) range: Range<usize>,
+    pub(crate) value_range: Range<usize>,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) struct StartTag<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) attrs: Vec<RawAttr<'a>>,
+    pub(crate) self_closing: bool,
+    pub(crate) range: Range<usize>,
+}
+
+/// The pieces of markup the tokenizer splits a document into. Every token carries the byte range
+/// it was read from.
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) enum Token<'a> {
+    /// `<?xml version="1.0"?>` and any other processing instruction
+    ProcessingInstruction(Range<usize>),
+    Comment(Range<usize>),
+    /// `<!DOCTYPE ...>`. `body` is everything between `<!DOCTYPE` and the final `>`
+    Doctype { body: &'a str, range: Range<usize> },
+    StartTag(StartTag<'a>),
+    EndTag { name: &'a str, range: Range<usize> },
+    Text(Range<usize>),
+    /// The complete `<![CDATA[...]]>` section
+    CData(Range<usize>),
+}
+
+/// Splits a document into tokens, checking only the syntax of each token on its own
+#[derive(Clone,Debug)]
+pub(crate) struct Tokenizer<'a> {
+    pub(crate) input: &'a str,
+    pub(crate) pos: usize,
+    pub(crate) lines: LineIndex,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Tokenizer{input, pos: 0, lines: LineIndex::new(input)}
+    }
+
+    pub(crate) fn position(&self, offset: usize) -> Position {
+        self.lines.position(self.input, offset)
+    }
+
+    pub(crate) fn error(&self, offset: usize, message: impl ToString) -> ParseError {
+        ParseError{message: message.to_string(), position: self.position(offset)}
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    /// Returns the next token, or `None` at the end of the input
+    pub(crate) fn next_token(&mut self) -> Option<Result<Token<'a>, ParseError>> {
+        if self.pos >= self.input.len() {
+            return None;
+        }
+        let start = self.pos;
+        let rest = self.rest();
+        let token = if !rest.starts_with('<') {
+            let len = rest.find('<').unwrap_or(rest.len());
+            self.pos += len;
+            Ok(Token::Text(start..self.pos))
+        } else if rest.starts_with("<?") {
+            self.read_until(start, "?>", "processing instruction").map(Token::ProcessingInstruction)
+        } else if rest.starts_with("<!--") {
+            self.read_until(start, "-->", "comment").map(Token::Comment)
+        } else if rest.starts_with("<![CDATA[") {
+            self.read_until(start, "]]>", "CDATA section").map(Token::CData)
+        } else if rest.starts_with("<!DOCTYPE") {
+            self.read_doctype(start)
+        } else if rest.starts_with("</") {
+            self.read_end_tag(start)
+        } else {
+            self.read_start_tag(start).map(Token::StartTag)
+        };
+        Some(token)
+    }
+
+    fn read_until(&mut self, start: usize, end: &str, what: &str) -> Result<Range<usize>, ParseError> {
+        match self.rest().find(end) {
+            Some(i) => {
+                self.pos += i + end.len();
+                Ok(start..self.pos)
+            },
+            None => Err(self.error(start, format!("unterminated {}", what))),
+        }
+    }
+
+    fn read_doctype(&mut self, start: usize) -> Result<Token<'a>, ParseError> {
+        let body_start = start + "<!DOCTYPE".len();
+        let bytes = self.input.as_bytes();
+        let mut quote = None;
+        let mut in_subset = false;
+        let mut i = body_start;
+        while i < bytes.len() {
+            let c = bytes[i];
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {},
+                None => match c {
+                    b'"' | b'\'' => quote = Some(c),
+                    b'[' => in_subset = true,
+                    b']' => in_subset = false,
+                    // skip over comments in the internal subset since they may contain quotes
+                    b'<' if in_subset && self.input[i..].starts_with("<!--") => {
+                        match self.input[i..].find("-->") {
+                            Some(len) => i += len + 3,
+                            None => return Err(self.error(i, "unterminated comment")),
+                        }
+                        continue;
+                    },
+                    b'>' if !in_subset => {
+                        self.pos = i + 1;
+                        return Ok(Token::Doctype{body: &self.input[body_start..i], range: start..self.pos});
+                    },
+                    _ => {},
+                },
+            }
+            i += 1;
+        }
+        Err(self.error(start, "unterminated document type declaration"))
+    }
+
+    fn read_end_tag(&mut self, start: usize) -> Result<Token<'a>, ParseError> {
+        self.pos += 2;
+        let name = self.read_name()?;
+        self.skip_whitespace();
+        if !self.rest().starts_with('>') {
+            return Err(self.error(self.pos, format!("expected `>` to close `</{}`", name)));
+        }
+        self.pos += 1;
+        Ok(Token::EndTag{name, range: start..self.pos})
+    }
+
+    fn read_start_tag(&mut self, start: usize) -> Result<StartTag<'a>, ParseError> {
+        self.pos += 1;
+        let name = self.read_name()?;
+        let mut attrs: Vec<RawAttr<'a>> = Vec::new();
+        loop {
+            let had_whitespace = self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.pos += 2;
+                return Ok(StartTag{name, attrs, self_closing: true, range: start..self.pos});
+            }
+            if rest.starts_with('>') {
+                self.pos += 1;
+                return Ok(StartTag{name, attrs, self_closing: false, range: start..self.pos});
+            }
+            if rest.is_empty() {
+                return Err(self.error(start, format!("unterminated start tag `<{}`", name)));
+            }
+            if !had_whitespace {
+                return Err(self.error(self.pos, "expected whitespace before attribute"));
+            }
+            let attr = self.read_attr()?;
+            if attrs.iter().any(|a| a.name == attr.name) {
+                return Err(self.error(attr.range.start, format!("duplicate attribute `{}`", attr.name)));
+            }
+            attrs.push(attr);
+        }
+    }
+
+    fn read_attr(&mut self) -> Result<RawAttr<'a>, ParseError> {
+        let start = self.pos;
+        let name = self.read_name()?;
+        self.skip_whitespace();
+        if !self.rest().starts_with('=') {
+            return Err(self.error(self.pos, format!("expected `=` after attribute `{}`", name)));
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+        let quote = match self.rest().chars().next() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => return Err(self.error(self.pos, format!("expected quoted value for attribute `{}`", name))),
+        };
+        let value_start = self.pos + 1;
+        let len = match self.input[value_start..].find(quote) {
+            Some(len) => len,
+            None => return Err(self.error(self.pos, format!("unterminated value for attribute `{}`", name))),
+        };
+        let value = &self.input[value_start..value_start + len];
+        if let Some(i) = value.find('<') {
+            return Err(self.error(value_start + i, "`<` is not allowed in attribute values"));
+        }
+        self.pos = value_start + len + 1;
+        Ok(RawAttr{name, value, range: start..self.pos, value_range: value_start..value_start + len})
+    }
+
+    fn read_name(&mut self) -> Result<&'a str, ParseError> {
+        let rest = self.rest();
+        let len = rest.char_indices()
+            .find(|&(i, c)| !(is_name_char(c) && (i > 0 || is_name_start_char(c))))
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        if len == 0 {
+            let found = rest.chars().next().map(|c| format!("`{}`", c)).unwrap_or_else(|| "end of input".to_string());
+            return Err(self.error(self.pos, format!("expected a name but found {}", found)));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    /// returns whether any whitespace was skipped
+    fn skip_whitespace(&mut self) -> bool {
+        let rest = self.rest();
+        let trimmed = rest.trim_start_matches(is_xml_whitespace);
+        self.pos += rest.len() - trimmed.len();
+        rest.len() != trimmed.len()
+    }
+}
+
+pub(crate) fn is_xml_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\n' || c == '\r'
+}
+
+pub(crate) fn is_name_start_char(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == ':'
+}
+
+pub(crate) fn is_name_char(c: char) -> bool {
+    is_name_start_char(c) || c.is_numeric() || c == '-' || c == '.'
+}
+
+/// How deeply entities may refer to other entities
+const MAX_ENTITY_DEPTH: usize = 16;
+/// How much text expanding the entities of one value may produce, to stop "billion laughs" style input
+const MAX_ENTITY_EXPANSION: usize = 1 << 20;
+
+/// Replaces the predefined entities, character references and the general entities declared in a
+/// DTD in `raw` with the text they stand for. `offset` is where `raw` starts in the input and is only
+/// used for error positions.
+pub(crate) fn unescape(tokens: &Tokenizer, raw: &str, offset: usize, entities: &HashMap<String, String>) -> Result<String, ParseError> {
+    let mut budget = MAX_ENTITY_EXPANSION;
+    unescape_nested(tokens, raw, offset, entities, 0, &mut budget)
+}
+
+fn unescape_nested(tokens: &Tokenizer, raw: &str, offset: usize, entities: &HashMap<String, String>, depth: usize, budget: &mut usize) -> Result<String, ParseError> {
+    let mut ret = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(amp) = rest.find('&') {
+        ret.push_str(&rest[..amp]);
+        // errors inside of an entity's replacement text are reported at the outermost reference
+        let at = if depth == 0 { offset + (raw.len() - rest.len()) + amp } else { offset };
+        let semi = match rest[amp..].find(';') {
+            Some(s) => amp + s,
+            None => return Err(tokens.error(at, "unterminated entity reference")),
+        };
+        let entity = &rest[amp + 1..semi];
+        match (decode_entity(entity), entities.get(entity)) {
+            (Some(c), _) => ret.push(c),
+            (None, Some(replacement)) => {
+                if depth >= MAX_ENTITY_DEPTH {
+                    return Err(tokens.error(at, format!("entity `&{};` is recursive or nested too deeply", entity)));
+                }
+                if replacement.contains('<') {
+                    return Err(tokens.error(at, format!("entity `&{};` contains markup, which is not supported", entity)));
+                }
+                if replacement.len() > *budget {
+                    return Err(tokens.error(at, "expanding entities produced too much text"));
+                }
+                *budget -= replacement.len();
+                ret.push_str(&unescape_nested(tokens, replacement, at, entities, depth + 1, budget)?);
+            },
+            (None, None) => return Err(tokens.error(at, format!("unknown entity `&{};`", entity))),
+        }
+        rest = &rest[semi + 1..];
+    }
+    ret.push_str(rest);
+    Ok(ret)
+}
+
+/// Decodes the name between `&` and `;` of one of the predefined entities or a character reference
+pub(crate) fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "apos" => Some('\''),
+        "quot" => Some('"'),
+        _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16).ok().and_then(std::char::from_u32),
+        _ if entity.starts_with('#') => entity[1..].parse::<u32>().ok().and_then(std::char::from_u32),
+        _ => None,
+    }
+}
+
+/// An element that has been opened but not yet closed
+struct OpenElement {
+    ele: XMLElement,
+    text: String,
+    /// where the start tag begins, for errors about it never being closed
+    start: usize,
+    /// only filled in when recording positions
+    start_tag: Option<Span>,
+    attrs: Vec<(String, Span)>,
+    children: usize,
+}
+
+struct Parser<'a, 'r> {
+    tokens: Tokenizer<'a>,
+    record_positions: bool,
+    resolver: Option<&'r dyn DtdResolver>,
+    dtd: Option<Dtd>,
+    /// general entities declared by the DTD
+    entities: HashMap<String, String>,
+}
+
+impl<'a, 'r> Parser<'a, 'r> {
+    fn span(&self, range: &Range<usize>) -> Span {
+        Span{start: self.tokens.position(range.start), end: self.tokens.position(range.end)}
+    }
+
+    fn parse(mut self) -> Result<(XMLElement, SourceMap, Option<Dtd>), ParseError> {
+        let mut source_map = SourceMap::default();
+        let mut stack: Vec<OpenElement> = Vec::new();
+        let mut root: Option<XMLElement> = None;
+
+        while let Some(token) = self.tokens.next_token() {
+            match token? {
+                Token::ProcessingInstruction(range) => {
+                    if range.start != 0 && self.tokens.input[range.clone()].starts_with("<?xml ") {
+                        return Err(self.tokens.error(range.start, "the XML declaration must be at the start of the document"));
+                    }
+                },
+                Token::Comment(_) => {},
+                Token::Doctype{body, range} => {
+                    if self.dtd.is_some() || root.is_some() || !stack.is_empty() {
+                        return Err(self.tokens.error(range.start, "unexpected document type declaration"));
+                    }
+                    let dtd = dtd::parse_doctype(&self.tokens, body, range.start + "<!DOCTYPE".len(), self.resolver)?;
+                    self.entities = dtd.entities.clone();
+                    self.dtd = Some(dtd);
+                },
+                Token::StartTag(tag) => {
+                    if root.is_some() {
+                        return Err(self.tokens.error(tag.range.start, "unexpected element after the root element"));
+                    }
+                    let mut ele = XMLElement::new(tag.name);
+                    let start_tag = if self.record_positions { Some(self.span(&tag.range)) } else { None };
+                    let mut attr_spans = Vec::new();
+                    for a in &tag.attrs {
+                        let value = unescape(&self.tokens, a.value, a.value_range.start, &self.entities)?;
+                        ele.attrs.get_or_insert_with(Vec::new).push(XMLAttr{name: a.name.to_string(), value});
+                        if self.record_positions {
+                            attr_spans.push((a.name.to_string(), self.span(&a.range)));
+                        }
+                    }
+                    let open = OpenElement{
+                        ele,
+                        text: String::new(),
+                        start: tag.range.start,
+                        start_tag,
+                        attrs: attr_spans,
+                        children: 0,
+                    };
+                    stack.push(open);
+                    if tag.self_closing {
+                        root = self.close(&mut stack, None, &mut source_map);
+                    }
+                },
+                Token::EndTag{name, range} => {
+                    match stack.last() {
+                        Some(open) if open.ele.name == name => {},
+                        Some(open) => {
+                            let msg = format!("expected `</{}>` but found `</{}>`", open.ele.name, name);
+                            return Err(self.tokens.error(range.start, msg));
+                        },
+                        None => return Err(self.tokens.error(range.start, format!("unexpected end tag `</{}>`", name))),
+                    }
+                    root = self.close(&mut stack, Some(range), &mut source_map);
+                },
+                Token::Text(range) => {
+                    let raw = &self.tokens.input[range.clone()];
+                    match stack.last_mut() {
+                        Some(open) => {
+                            let text = unescape(&self.tokens, raw, range.start, &self.entities)?;
+                            open.text.push_str(&text);
+                        },
+                        None => {
+                            if let Some(i) = raw.find(|c| !is_xml_whitespace(c)) {
+                                return Err(self.tokens.error(range.start + i, "text is not allowed outside of the root element"));
+                            }
+                        },
+                    }
+                },
+                Token::CData(range) => {
+                    match stack.last_mut() {
+                        Some(open) => open.text.push_str(&self.tokens.input[range]),
+                        None => return Err(self.tokens.error(range.start, "CDATA is not allowed outside of the root element")),
+                    }
+                },
+            }
+        }
+
+        if let Some(open) = stack.last() {
+            let msg = format!("unclosed element `<{}>`", open.ele.name);
+            return Err(self.tokens.error(open.start, msg));
+        }
+        match root {
+            Some(root) => Ok((root, source_map, self.dtd)),
+            None => Err(self.tokens.error(self.tokens.input.len(), "no root element found")),
+        }
+    }
+
+    /// Pops the innermost open element and attaches it to its parent. Returns the element if it was the root.
+    fn close(&self, stack: &mut Vec<OpenElement>, end_tag: Option<Range<usize>>, source_map: &mut SourceMap) -> Option<XMLElement> {
+        let mut open = stack.pop().expect("close is only called with an open element");
+        let text = open.text.trim_matches(is_xml_whitespace);
+        if !text.is_empty() {
+            open.ele.text = Some(text.to_string());
+        }
+        if let Some(start_tag) = open.start_tag {
+            // each open ancestor's child count is the index of the child currently being read
+            let path: Vec<usize> = stack.iter().map(|o| o.children).collect();
+            let pos = ElementPosition{start_tag, end_tag: end_tag.map(|range| self.span(&range)), attrs: open.attrs};
+            source_map.insert(path, pos);
+        }
+        match stack.last_mut() {
+            Some(parent) => {
+                parent.children += 1;
+                parent.ele.add_element(open.ele);
+                None
+            },
+            None => Some(open.ele),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_simple() {
+        let ele = parse(r#"<person age="28"><person age="4">Jane Doe</person>John Doe</person>"#).unwrap();
+        let expected = XMLElement::new("person")
+                        .attr("age", 28)
+                        .element(XMLElement::new("person").attr("age", 4).text("Jane Doe"))
+                        .text("John Doe");
+        assert_eq!(ele, expected);
+    }
+
+    #[test]
+    fn parse_round_trip_pretty() {
+        let ele = XMLElement::new("test_element")
+                    .attr("a1", 42)
+                    .element(XMLElement::new("point").attr("lat", 12.3).text("point content"))
+                    .element(XMLElement::new("point").attr("lat", 32.1))
+                    .text("some content");
+        assert_eq!(parse(&ele.to_string_pretty_prolog("\n", "\t")).unwrap(), ele);
+        assert_eq!(parse(&ele.to_string()).unwrap(), ele);
+    }
+
+    #[test]
+    fn parse_entities_and_cdata() {
+        let ele = parse(r#"<a b="&quot;x&quot; &amp; y">1 &lt; 2 &#65;&#x42;<![CDATA[<raw>]]></a>"#).unwrap();
+        assert_eq!(ele.attrs.unwrap()[0].value, r#""x" & y"#);
+        assert_eq!(ele.text.unwrap(), "1 < 2 AB<![CDATA[<raw>]]>");
+    }
+
+    #[test]
+    fn parse_skips_prolog_and_comments() {
+        let input = "<?xml version=\"1.0\"?>\n<!DOCTYPE a [<!ELEMENT a (#PCDATA)>]>\n<!-- c -->\n<a><!-- inner --></a>\n<!-- trailing -->\n";
+        assert_eq!(parse(input).unwrap(), XMLElement::new("a"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = parse("<a>\n  <b>\n</a>").unwrap_err();
+        assert_eq!(err.message, "expected `</b>` but found `</a>`");
+        assert_eq!((err.position.line, err.position.column), (3, 1));
+
+        let err = parse("<a x=\"1\" x=\"2\"/>").unwrap_err();
+        assert_eq!(err.to_string(), "duplicate attribute `x` at line 1, column 10");
+
+        let err = parse("<a>\n<b>").unwrap_err();
+        assert_eq!(err.to_string(), "unclosed element `<b>` at line 2, column 1");
+
+        let err = parse("<a/><b/>").unwrap_err();
+        assert_eq!(err.position.column, 5);
+
+        let err = parse("<a>&nbsp;</a>").unwrap_err();
+        assert_eq!(err.to_string(), "unknown entity `&nbsp;` at line 1, column 4");
+
+        assert!(parse("").is_err());
+        assert!(parse("text<a/>").is_err());
+        assert!(parse("<a b=c/>").is_err());
+        assert!(parse("<a b=\"<\"/>").is_err());
+        assert!(parse("<a><!-- </a>").is_err());
+    }
+
+    #[test]
+    fn parse_positions() {
+        let input = "<root>\n  <child a=\"1\"  bb='2'/>\n  <child>\n    <ü x=\"y\"/>\n  </child>\n</root>";
+        let (root, map) = parse_with_positions(input).unwrap();
+
+        let pos = map.position_of(&root, &root).unwrap();
+        assert_eq!(pos.start_tag.start, Position{offset: 0, line: 1, column: 1});
+        assert_eq!(pos.start_tag.end.column, 7);
+        assert_eq!(pos.end_tag.unwrap().start.line, 6);
+
+        let children = root.contents.as_ref().unwrap();
+        let first = map.position_of(&root, &children[0]).unwrap();
+        assert_eq!(first.end_tag, None);
+        assert_eq!(first.attr("a").unwrap().start.column, 10);
+        assert_eq!(first.attr("bb").unwrap().start.column, 17);
+        assert_eq!(first.attr("bb").unwrap().end.column, 23);
+
+        let nested = &children[1].contents.as_ref().unwrap()[0];
+        let pos = map.position_of(&root, nested).unwrap();
+        assert_eq!(pos.start_tag.start.to_string(), "line 4, column 5");
+        // columns count characters, not bytes
+        assert_eq!(pos.attr("x").unwrap().start.column, 8);
+        assert_eq!(map.position_at(&[1, 0]), Some(pos));
+
+        let cloned = root.clone();
+        let nested = &cloned.contents.as_ref().unwrap()[1].contents.as_ref().unwrap()[0];
+        assert_eq!(map.position_of(&cloned, nested), Some(pos));
+        assert_eq!(map.position_of(&root, nested), None);
+    }
+
+    #[test]
+    fn line_index_counts_on_from_last_position() {
+        let input = "<ü a=\"é\"/>\n<b/><ü/>";
+        let lines = LineIndex::new(input);
+        assert_eq!(lines.position(input, 6).column, 6);
+        assert_eq!(lines.position(input, 10).column, 9);
+        // going back counts backwards on the same line
+        assert_eq!(lines.position(input, 3).column, 3);
+        assert_eq!((lines.position(input, 18).line, lines.position(input, 18).column), (2, 6));
+        assert_eq!(lines.position(input, 14).column, 2);
+
+        let one_line: String = (0..1000).map(|i| format!("<é n=\"{:03}\"/>", i)).collect();
+        let (root, map) = parse_with_positions(&format!("<r>{}</r>", one_line)).unwrap();
+        let last = &root.contents.as_ref().unwrap()[999];
+        assert_eq!(map.position_of(&root, last).unwrap().attr("n").unwrap().start.column, 4 + 999 * 12 + 3);
+    }
+}
@@ -0,0 +1,380 @@
+//! The regular expressions used by XML Schema `pattern` facets, which always match the whole value.
+//! Character classes (including subtraction like `[a-z-[aeiou]]`), the `\d \s \w \i \c` escapes, a
+//! handful of `\p{..}` categories, groups, alternation and all the quantifiers are supported.
+
+use std::collections::BTreeSet;
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) struct Pattern {
+    source: String,
+    node: Node,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+enum Node {
+    Set(CharSet),
+    Seq(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+#[derive(Clone,PartialEq,Debug)]
+struct CharSet {
+    negated: bool,
+    items: Vec<ClassItem>,
+    subtract: Option<Box<CharSet>>,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+enum ClassItem {
+    Range(char, char),
+    /// A multi-character escape, negated when written in upper case
+    Escape(Escape, bool),
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+enum Escape {
+    /// `.`, anything but a line break
+    Dot,
+    Digit,
+    Space,
+    Word,
+    NameStart,
+    NameChar,
+    Letter,
+    Upper,
+    Lower,
+    Number,
+    Punctuation,
+    Symbol,
+}
+
+impl Escape {
+    fn matches(self, c: char) -> bool {
+        match self {
+            Escape::Dot => c != '\n' && c != '\r',
+            Escape::Digit => c.is_numeric(),
+            Escape::Space => c == ' ' || c == '\t' || c == '\n' || c == '\r',
+            // everything but punctuation, separators and other characters
+            Escape::Word => !(Escape::Punctuation.matches(c) || c.is_whitespace() || c.is_control()),
+            Escape::NameStart => crate::parser::is_name_start_char(c),
+            Escape::NameChar => crate::parser::is_name_char(c),
+            Escape::Letter => c.is_alphabetic(),
+            Escape::Upper => c.is_uppercase(),
+            Escape::Lower => c.is_lowercase(),
+            Escape::Number => c.is_numeric(),
+            Escape::Punctuation => c.is_ascii_punctuation() && !Escape::Symbol.matches(c),
+            Escape::Symbol => "$+<=>^`|~".contains(c),
+        }
+    }
+}
+
+impl CharSet {
+    fn single(item: ClassItem) -> Self {
+        CharSet{negated: false, items: vec![item], subtract: None}
+    }
+
+    fn matches(&self, c: char) -> bool {
+        let in_items = self.items.iter().any(|item| match item {
+            ClassItem::Range(lo, hi) => *lo <= c && c <= *hi,
+            ClassItem::Escape(e, negated) => e.matches(c) != *negated,
+        });
+        let subtracted = self.subtract.as_ref().is_some_and(|s| s.matches(c));
+        in_items != self.negated && !subtracted
+    }
+}
+
+impl Node {
+    /// Adds every position where a match of this node starting at `start` can end
+    fn ends(&self, chars: &[char], start: usize, out: &mut BTreeSet<usize>) {
+        match self {
+            Node::Set(set) => {
+                if chars.get(start).is_some_and(|c| set.matches(*c)) {
+                    out.insert(start + 1);
+                }
+            },
+            Node::Seq(items) => {
+                let mut positions: BTreeSet<usize> = Some(start).into_iter().collect();
+                for item in items {
+                    let mut next = BTreeSet::new();
+                    for p in positions {
+                        item.ends(chars, p, &mut next);
+                    }
+                    positions = next;
+                }
+                out.extend(positions);
+            },
+            Node::Alt(items) => {
+                for item in items {
+                    item.ends(chars, start, out);
+                }
+            },
+            Node::Repeat(node, min, max) => {
+                let mut frontier: BTreeSet<usize> = Some(start).into_iter().collect();
+                let mut seen = BTreeSet::new();
+                let mut count = 0;
+                loop {
+                    if count >= *min {
+                        // once the minimum is met, positions already reached add nothing new
+                        frontier.retain(|p| seen.insert(*p));
+                        out.extend(frontier.iter().cloned());
+                    }
+                    if frontier.is_empty() || max.is_some_and(|m| count >= m) {
+                        return;
+                    }
+                    let mut next = BTreeSet::new();
+                    for p in &frontier {
+                        node.ends(chars, *p, &mut next);
+                    }
+                    frontier = next;
+                    count += 1;
+                }
+            },
+        }
+    }
+}
+
+impl Pattern {
+    pub(crate) fn new(source: &str) -> Result<Self, String> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut parser = PatternParser{chars: &chars, pos: 0};
+        let node = parser.alternation()?;
+        if parser.pos < chars.len() {
+            return Err(format!("unexpected `{}` in pattern `{}`", chars[parser.pos], source));
+        }
+        Ok(Pattern{source: source.to_string(), node})
+    }
+
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns whether the pattern matches all of `value`
+    pub(crate) fn is_match(&self, value: &str) -> bool {
+        let chars: Vec<char> = value.chars().collect();
+        let mut ends = BTreeSet::new();
+        self.node.ends(&chars, 0, &mut ends);
+        ends.contains(&chars.len())
+    }
+}
+
+struct PatternParser<'a> {
+    chars: &'a [char],
+    pos: usize,
+}
+
+impl<'a> PatternParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or("unexpected end of pattern")?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.sequence()?];
+        while self.eat('|') {
+            branches.push(self.sequence()?);
+        }
+        Ok(if branches.len() == 1 { branches.remove(0) } else { Node::Alt(branches) })
+    }
+
+    fn sequence(&mut self) -> Result<Node, String> {
+        let mut items = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            items.push(self.quantifier(atom)?);
+        }
+        Ok(Node::Seq(items))
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.next()? {
+            '(' => {
+                let inner = self.alternation()?;
+                if !self.eat(')') {
+                    return Err("unclosed group in pattern".to_string());
+                }
+                Ok(inner)
+            },
+            '[' => Ok(Node::Set(self.class()?)),
+            '.' => Ok(Node::Set(CharSet::single(ClassItem::Escape(Escape::Dot, false)))),
+            '\\' => Ok(Node::Set(CharSet::single(self.escape()?))),
+            c @ '?' | c @ '*' | c @ '+' | c @ '{' | c @ ']' | c @ '}' => Err(format!("unexpected `{}` in pattern", c)),
+            c => Ok(Node::Set(CharSet::single(ClassItem::Range(c, c)))),
+        }
+    }
+
+    fn quantifier(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('?') => (0, Some(1)),
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.number()?;
+                let max = if self.eat(',') {
+                    if self.peek() == Some('}') { None } else { Some(self.number()?) }
+                } else {
+                    Some(min)
+                };
+                if !self.eat('}') {
+                    return Err("unclosed quantifier in pattern".to_string());
+                }
+                return Ok(Node::Repeat(Box::new(atom), min, max));
+            },
+            _ => return Ok(atom),
+        };
+        self.pos += 1;
+        Ok(Node::Repeat(Box::new(atom), min, max))
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect::<String>().parse().map_err(|_| "expected a number in quantifier".to_string())
+    }
+
+    /// Reads the rest of a `[...]` class whose `[` has been read
+    fn class(&mut self) -> Result<CharSet, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut subtract = None;
+        loop {
+            match self.next()? {
+                ']' if !items.is_empty() => break,
+                '-' if self.peek() == Some('[') => {
+                    self.pos += 1;
+                    subtract = Some(Box::new(self.class()?));
+                    if !self.eat(']') {
+                        return Err("class subtraction must end the class".to_string());
+                    }
+                    break;
+                },
+                '\\' => {
+                    match self.escape()? {
+                        ClassItem::Range(c, _) => items.push(self.range_from(c)?),
+                        item => items.push(item),
+                    }
+                },
+                c => items.push(self.range_from(c)?),
+            }
+        }
+        Ok(CharSet{negated, items, subtract})
+    }
+
+    /// Reads the `-z` of `a-z` if it is there
+    fn range_from(&mut self, lo: char) -> Result<ClassItem, String> {
+        if self.peek() == Some('-') && !matches!(self.chars.get(self.pos + 1), Some(']') | Some('[') | None) {
+            self.pos += 1;
+            let hi = match self.next()? {
+                '\\' => match self.escape()? {
+                    ClassItem::Range(c, _) => c,
+                    _ => return Err("a range cannot end in a class escape".to_string()),
+                },
+                c => c,
+            };
+            if hi < lo {
+                return Err(format!("range `{}-{}` is out of order", lo, hi));
+            }
+            Ok(ClassItem::Range(lo, hi))
+        } else {
+            Ok(ClassItem::Range(lo, lo))
+        }
+    }
+
+    /// Reads what follows a `\`
+    fn escape(&mut self) -> Result<ClassItem, String> {
+        let c = self.next()?;
+        let escape = match c.to_ascii_lowercase() {
+            'd' => Escape::Digit,
+            's' => Escape::Space,
+            'w' => Escape::Word,
+            'i' => Escape::NameStart,
+            'c' => Escape::NameChar,
+            'p' => {
+                if !self.eat('{') {
+                    return Err("expected `{` after `\\p`".to_string());
+                }
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c != '}') {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                self.pos += 1;
+                match name.as_str() {
+                    "L" => Escape::Letter,
+                    "Lu" => Escape::Upper,
+                    "Ll" => Escape::Lower,
+                    "N" | "Nd" => Escape::Number,
+                    "P" => Escape::Punctuation,
+                    "S" => Escape::Symbol,
+                    _ => return Err(format!("unsupported category `\\p{{{}}}`", name)),
+                }
+            },
+            _ => {
+                let literal = match c {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    '\\' | '|' | '.' | '?' | '*' | '+' | '(' | ')' | '{' | '}' | '-' | '[' | ']' | '^' | '$' => c,
+                    _ => return Err(format!("unknown escape `\\{}` in pattern", c)),
+                };
+                return Ok(ClassItem::Range(literal, literal));
+            },
+        };
+        Ok(ClassItem::Escape(escape, c.is_ascii_uppercase()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, value: &str) -> bool {
+        Pattern::new(pattern).unwrap().is_match(value)
+    }
+
+    #[test]
+    fn pattern_matching() {
+        assert!(matches("[A-Z]{2}-\\d{3,4}", "AB-123"));
+        assert!(!matches("[A-Z]{2}-\\d{3,4}", "AB-12"));
+        assert!(!matches("[A-Z]{2}-\\d{3,4}", "AB-12345"));
+        assert!(matches("(ab|c)*d?", "abcab"));
+        assert!(matches("(ab|c)*d?", ""));
+        assert!(!matches("(ab|c)*d?", "abd d"));
+        assert!(matches("[a-z-[aeiou]]+", "xyz"));
+        assert!(!matches("[a-z-[aeiou]]+", "xaz"));
+        assert!(matches("[^\\s]+\\.xml", "a-b.xml"));
+        assert!(!matches("[^\\s]+\\.xml", "a b.xml"));
+        assert!(matches("\\i\\c*", "_name-1"));
+        assert!(matches("\\p{Lu}\\p{Ll}+", "Hello"));
+        assert!(matches("(a*)*b", "aaab"));
+    }
+
+    #[test]
+    fn pattern_errors() {
+        assert!(Pattern::new("(ab").is_err());
+        assert!(Pattern::new("a{2").is_err());
+        assert!(Pattern::new("*a").is_err());
+        assert!(Pattern::new("[z-a]").is_err());
+        assert!(Pattern::new("\\q").is_err());
+    }
+}
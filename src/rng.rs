@@ -0,0 +1,1382 @@
+/*!
+Validating `XMLElement` trees against RELAX NG grammars, in either the XML or the compact syntax.
+
+```rust
+use simple_xml_serialize::{validate_rng, XMLElement};
+use simple_xml_serialize::rng::RelaxNg;
+
+let schema = RelaxNg::parse_compact(r#"
+    start = element addressBook { card* }
+    card = element card {
+        attribute id { xsd:positiveInteger },
+        element name { text },
+        element email { text }+
+    }
+"#).unwrap();
+
+let book = XMLElement::new("addressBook")
+               .element(XMLElement::new("card")
+                            .attr("id", "first")
+                            .element(XMLElement::new("name").text("John"))
+                            .element(XMLElement::new("phone").text("555")));
+
+let errors = validate_rng(&book, &schema).unwrap_err();
+assert_eq!(errors[0].to_string(), "/addressBook/card: value `first` of attribute `id` does not match xsd:positiveInteger");
+assert_eq!(errors[1].to_string(), "/addressBook/card/phone: element `phone` is not allowed here, expected element `email`");
+```
+
+Validation follows the derivative algorithm described by James Clark, so every construct of the
+language is supported, with the `string` and `token` types and the XML Schema datatypes. As
+`XMLElement` has no notion of namespaces, names are matched on their local part and `ns`
+attributes are ignored. Since an element keeps its text apart from its children, text is validated
+as if it followed the last child element.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::parser::{self, SourceMap};
+use crate::validation::{ElementPath, Reporter, ValidationError};
+use crate::xsd::{self, Facets, SchemaError};
+use crate::{XMLAttr, XMLElement};
+
+const XSD_DATATYPES: &str = "http://www.w3.org/2001/XMLSchema-datatypes";
+
+/// A loaded RELAX NG grammar
+#[derive(Clone,Debug)]
+pub struct RelaxNg {
+    start: Rc<Pat>,
+    defines: HashMap<String, Rc<Pat>>,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+enum NameClass {
+    Name(String),
+    /// `*` or `ns:*`, as namespaces are not tracked, minus the exceptions
+    AnyName(Option<Box<NameClass>>),
+    Choice(Box<NameClass>, Box<NameClass>),
+}
+
+impl NameClass {
+    fn contains(&self, name: &str) -> bool {
+        match self {
+            NameClass::Name(n) => n == xsd::local(name),
+            NameClass::AnyName(except) => !except.as_ref().is_some_and(|e| e.contains(name)),
+            NameClass::Choice(a, b) => a.contains(name) || b.contains(name),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            NameClass::Name(n) => n.clone(),
+            NameClass::AnyName(_) => "*".to_string(),
+            NameClass::Choice(a, b) => format!("{}|{}", a.describe(), b.describe()),
+        }
+    }
+}
+
+#[derive(Clone,PartialEq,Debug)]
+enum Datatype {
+    /// The built-in `string` type, compared without whitespace normalization
+    String,
+    /// The built-in `token` type
+    Token,
+    /// One of the XML Schema types with its parameters
+    Xsd(String, Box<Facets>),
+}
+
+impl Datatype {
+    fn allows(&self, value: &str) -> bool {
+        match self {
+            Datatype::String | Datatype::Token => true,
+            Datatype::Xsd(name, facets) => xsd::check_datatype(name, facets, value).is_ok(),
+        }
+    }
+
+    fn equal(&self, a: &str, b: &str) -> bool {
+        match self {
+            Datatype::String => a == b,
+            Datatype::Token => xsd::normalize_space(a, "collapse") == xsd::normalize_space(b, "collapse"),
+            Datatype::Xsd(name, _) => {
+                let (a, b) = if name == "string" { (a.to_string(), b.to_string()) } else {
+                    (xsd::normalize_space(a, "collapse"), xsd::normalize_space(b, "collapse"))
+                };
+                match (a.parse::<f64>(), b.parse::<f64>()) {
+                    (Ok(x), Ok(y)) if name != "string" => x == y,
+                    _ => a == b,
+                }
+            },
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Datatype::String => "string".to_string(),
+            Datatype::Token => "token".to_string(),
+            Datatype::Xsd(name, _) => format!("xsd:{}", name),
+        }
+    }
+}
+
+#[derive(Clone,PartialEq,Debug)]
+enum Pat {
+    Empty,
+    NotAllowed,
+    Text,
+    Choice(Rc<Pat>, Rc<Pat>),
+    Interleave(Rc<Pat>, Rc<Pat>),
+    Group(Rc<Pat>, Rc<Pat>),
+    OneOrMore(Rc<Pat>),
+    List(Rc<Pat>),
+    Element(NameClass, Rc<Pat>),
+    Attribute(NameClass, Rc<Pat>),
+    Data(Datatype, Option<Rc<Pat>>),
+    Value(Datatype, String),
+    /// A reference to a named pattern, by its name within the whole grammar
+    Ref(String),
+    /// What is left of an element's content, followed by what may come after the element
+    After(Rc<Pat>, Rc<Pat>),
+}
+
+fn not_allowed() -> Rc<Pat> {
+    Rc::new(Pat::NotAllowed)
+}
+
+fn empty() -> Rc<Pat> {
+    Rc::new(Pat::Empty)
+}
+
+fn choice(a: Rc<Pat>, b: Rc<Pat>) -> Rc<Pat> {
+    match (&*a, &*b) {
+        (Pat::NotAllowed, _) => b,
+        (_, Pat::NotAllowed) => a,
+        _ if a == b => a,
+        _ => Rc::new(Pat::Choice(a, b)),
+    }
+}
+
+fn group(a: Rc<Pat>, b: Rc<Pat>) -> Rc<Pat> {
+    match (&*a, &*b) {
+        (Pat::NotAllowed, _) | (_, Pat::NotAllowed) => not_allowed(),
+        (Pat::Empty, _) => b,
+        (_, Pat::Empty) => a,
+        _ => Rc::new(Pat::Group(a, b)),
+    }
+}
+
+fn interleave(a: Rc<Pat>, b: Rc<Pat>) -> Rc<Pat> {
+    match (&*a, &*b) {
+        (Pat::NotAllowed, _) | (_, Pat::NotAllowed) => not_allowed(),
+        (Pat::Empty, _) => b,
+        (_, Pat::Empty) => a,
+        _ => Rc::new(Pat::Interleave(a, b)),
+    }
+}
+
+fn after(a: Rc<Pat>, b: Rc<Pat>) -> Rc<Pat> {
+    match (&*a, &*b) {
+        (Pat::NotAllowed, _) | (_, Pat::NotAllowed) => not_allowed(),
+        _ => Rc::new(Pat::After(a, b)),
+    }
+}
+
+fn one_or_more(p: Rc<Pat>) -> Rc<Pat> {
+    match &*p {
+        Pat::NotAllowed | Pat::Empty => p,
+        _ => Rc::new(Pat::OneOrMore(p)),
+    }
+}
+
+fn is_whitespace(s: &str) -> bool {
+    s.chars().all(parser::is_xml_whitespace)
+}
+
+/// Validates an element tree against a RELAX NG grammar, returning every problem found
+/// # Arguments
+///
+/// * `element` - The root element of the document
+/// * `schema` - A grammar from `RelaxNg::load`, `RelaxNg::parse_xml` or `RelaxNg::parse_compact`
+///
+/// # Example
+///
+/// ```
+/// use simple_xml_serialize::{validate_rng, XMLElement};
+/// use simple_xml_serialize::rng::RelaxNg;
+/// let schema = RelaxNg::parse_xml(r#"
+///     <element name="flag" xmlns="http://relaxng.org/ns/structure/1.0">
+///       <choice><value>on</value><value>off</value></choice>
+///     </element>"#).unwrap();
+/// assert!(validate_rng(&XMLElement::new("flag").text("on"), &schema).is_ok());
+///
+/// let errors = validate_rng(&XMLElement::new("flag").text("maybe"), &schema).unwrap_err();
+/// assert_eq!(errors[0].message, r#"value `maybe` of element `flag` does not match "on" | "off""#);
+/// ```
+pub fn validate_rng(element: &XMLElement, schema: &RelaxNg) -> Result<(), Vec<ValidationError>> {
+    schema.validate(element)
+}
+
+impl RelaxNg {
+    /// Loads a grammar from a file. Files ending in `.rnc` are read as compact syntax, anything else as XML.
+    /// Includes and external references are resolved relative to the file.
+    pub fn load(path: impl AsRef<Path>) -> Result<RelaxNg, SchemaError> {
+        let mut loader = Loader::default();
+        let scope = loader.new_scope(None);
+        let start = loader.external(path.as_ref(), scope)?;
+        loader.finish(start)
+    }
+
+    /// Reads a grammar in the XML syntax. Includes are resolved relative to the current directory.
+    pub fn parse_xml(text: &str) -> Result<RelaxNg, SchemaError> {
+        let root = parser::parse(text).map_err(|e| SchemaError{file: None, message: e.to_string()})?;
+        Self::from_element(&root)
+    }
+
+    /// Reads a grammar in the compact syntax. Includes are resolved relative to the current directory.
+    pub fn parse_compact(text: &str) -> Result<RelaxNg, SchemaError> {
+        let root = compact::parse(text).map_err(|message| SchemaError{file: None, message})?;
+        Self::from_element(&root)
+    }
+
+    fn from_element(root: &XMLElement) -> Result<RelaxNg, SchemaError> {
+        let mut loader = Loader{dir: PathBuf::from("."), ..Loader::default()};
+        let scope = loader.new_scope(None);
+        let start = loader.pattern(root, scope, "").map_err(|message| SchemaError{file: None, message})?;
+        loader.finish(start)
+    }
+
+    /// Checks an element tree against the grammar, returning every problem found
+    pub fn validate(&self, root: &XMLElement) -> Result<(), Vec<ValidationError>> {
+        self.validate_inner(root, None)
+    }
+
+    /// Same as `validate`, but adds the line and column to each error using the source map from `parser::parse_with_positions`
+    pub fn validate_with_positions(&self, root: &XMLElement, source_map: &SourceMap) -> Result<(), Vec<ValidationError>> {
+        self.validate_inner(root, Some(source_map))
+    }
+
+    fn validate_inner(&self, root: &XMLElement, source_map: Option<&SourceMap>) -> Result<(), Vec<ValidationError>> {
+        let mut v = Validator{rng: self, reporter: Reporter::new(source_map)};
+        v.element(self.start.clone(), root, &ElementPath::root(root));
+        v.reporter.finish()
+    }
+
+    fn deref(&self, name: &str) -> Rc<Pat> {
+        self.defines.get(name).cloned().unwrap_or_else(not_allowed)
+    }
+
+    fn nullable(&self, p: &Pat) -> bool {
+        match p {
+            Pat::Empty | Pat::Text => true,
+            Pat::Group(a, b) | Pat::Interleave(a, b) => self.nullable(a) && self.nullable(b),
+            Pat::Choice(a, b) => self.nullable(a) || self.nullable(b),
+            Pat::OneOrMore(p) => self.nullable(p),
+            Pat::Ref(name) => self.nullable(&self.deref(name)),
+            _ => false,
+        }
+    }
+
+    fn text_deriv(&self, p: &Rc<Pat>, s: &str) -> Rc<Pat> {
+        match &**p {
+            Pat::Choice(a, b) => choice(self.text_deriv(a, s), self.text_deriv(b, s)),
+            Pat::Interleave(a, b) => choice(interleave(self.text_deriv(a, s), b.clone()), interleave(a.clone(), self.text_deriv(b, s))),
+            Pat::Group(a, b) => {
+                let p1 = group(self.text_deriv(a, s), b.clone());
+                if self.nullable(a) { choice(p1, self.text_deriv(b, s)) } else { p1 }
+            },
+            Pat::After(a, b) => after(self.text_deriv(a, s), b.clone()),
+            Pat::OneOrMore(inner) => group(self.text_deriv(inner, s), choice(p.clone(), empty())),
+            Pat::Text => p.clone(),
+            Pat::Value(dt, v) => if dt.equal(v, s) { empty() } else { not_allowed() },
+            Pat::Data(dt, except) => {
+                let excluded = except.as_ref().is_some_and(|e| self.nullable(&self.text_deriv(e, s)));
+                if dt.allows(s) && !excluded { empty() } else { not_allowed() }
+            },
+            Pat::List(inner) => {
+                let mut d = inner.clone();
+                for word in s.split(parser::is_xml_whitespace).filter(|w| !w.is_empty()) {
+                    d = self.text_deriv(&d, word);
+                }
+                if self.nullable(&d) { empty() } else { not_allowed() }
+            },
+            Pat::Ref(name) => self.text_deriv(&self.deref(name), s),
+            _ => not_allowed(),
+        }
+    }
+
+    fn apply_after(&self, f: &dyn Fn(Rc<Pat>) -> Rc<Pat>, p: &Rc<Pat>) -> Rc<Pat> {
+        match &**p {
+            Pat::After(a, b) => after(a.clone(), f(b.clone())),
+            Pat::Choice(a, b) => choice(self.apply_after(f, a), self.apply_after(f, b)),
+            _ => not_allowed(),
+        }
+    }
+
+    fn start_tag_open_deriv(&self, p: &Rc<Pat>, name: &str) -> Rc<Pat> {
+        match &**p {
+            Pat::Choice(a, b) => choice(self.start_tag_open_deriv(a, name), self.start_tag_open_deriv(b, name)),
+            Pat::Element(nc, content) => if nc.contains(name) { after(content.clone(), empty()) } else { not_allowed() },
+            Pat::Interleave(a, b) => {
+                let left = self.apply_after(&|x| interleave(x, b.clone()), &self.start_tag_open_deriv(a, name));
+                let right = self.apply_after(&|x| interleave(a.clone(), x), &self.start_tag_open_deriv(b, name));
+                choice(left, right)
+            },
+            Pat::OneOrMore(inner) => self.apply_after(&|x| group(x, choice(p.clone(), empty())), &self.start_tag_open_deriv(inner, name)),
+            Pat::Group(a, b) => {
+                let x = self.apply_after(&|x| group(x, b.clone()), &self.start_tag_open_deriv(a, name));
+                if self.nullable(a) { choice(x, self.start_tag_open_deriv(b, name)) } else { x }
+            },
+            Pat::After(a, b) => self.apply_after(&|x| after(x, b.clone()), &self.start_tag_open_deriv(a, name)),
+            Pat::Ref(r) => self.start_tag_open_deriv(&self.deref(r), name),
+            _ => not_allowed(),
+        }
+    }
+
+    fn att_deriv(&self, p: &Rc<Pat>, att: &XMLAttr) -> Rc<Pat> {
+        match &**p {
+            Pat::After(a, b) => after(self.att_deriv(a, att), b.clone()),
+            Pat::Choice(a, b) => choice(self.att_deriv(a, att), self.att_deriv(b, att)),
+            Pat::Group(a, b) => choice(group(self.att_deriv(a, att), b.clone()), group(a.clone(), self.att_deriv(b, att))),
+            Pat::Interleave(a, b) => choice(interleave(self.att_deriv(a, att), b.clone()), interleave(a.clone(), self.att_deriv(b, att))),
+            Pat::OneOrMore(inner) => group(self.att_deriv(inner, att), choice(p.clone(), empty())),
+            Pat::Attribute(nc, value) => {
+                let matches = (self.nullable(value) && is_whitespace(&att.value)) || self.nullable(&self.text_deriv(value, &att.value));
+                if nc.contains(&att.name) && matches { empty() } else { not_allowed() }
+            },
+            Pat::Ref(r) => self.att_deriv(&self.deref(r), att),
+            _ => not_allowed(),
+        }
+    }
+
+    /// With `lenient` missing attributes are ignored, which lets validation carry on after reporting them
+    fn start_tag_close_deriv(&self, p: &Rc<Pat>, lenient: bool) -> Rc<Pat> {
+        match &**p {
+            Pat::After(a, b) => after(self.start_tag_close_deriv(a, lenient), b.clone()),
+            Pat::Choice(a, b) => choice(self.start_tag_close_deriv(a, lenient), self.start_tag_close_deriv(b, lenient)),
+            Pat::Group(a, b) => group(self.start_tag_close_deriv(a, lenient), self.start_tag_close_deriv(b, lenient)),
+            Pat::Interleave(a, b) => interleave(self.start_tag_close_deriv(a, lenient), self.start_tag_close_deriv(b, lenient)),
+            Pat::OneOrMore(inner) => one_or_more(self.start_tag_close_deriv(inner, lenient)),
+            Pat::Attribute(..) => if lenient { empty() } else { not_allowed() },
+            Pat::Ref(r) => {
+                let target = self.deref(r);
+                // references to elements stay as they are so recursive grammars are not expanded forever
+                if self.has_attributes(&target, 0) { self.start_tag_close_deriv(&target, lenient) } else { p.clone() }
+            },
+            _ => p.clone(),
+        }
+    }
+
+    /// Whether an attribute may appear at the top of `p`, outside of any element
+    fn has_attributes(&self, p: &Pat, depth: usize) -> bool {
+        match p {
+            Pat::Attribute(..) => true,
+            Pat::Choice(a, b) | Pat::Group(a, b) | Pat::Interleave(a, b) | Pat::After(a, b) => self.has_attributes(a, depth) || self.has_attributes(b, depth),
+            Pat::OneOrMore(inner) => self.has_attributes(inner, depth),
+            Pat::Ref(r) if depth < 64 => self.has_attributes(&self.deref(r), depth + 1),
+            _ => false,
+        }
+    }
+
+    /// With `lenient` unfinished content is given up on, which lets validation carry on after reporting it
+    fn end_tag_deriv(&self, p: &Rc<Pat>, lenient: bool) -> Rc<Pat> {
+        match &**p {
+            Pat::Choice(a, b) => choice(self.end_tag_deriv(a, lenient), self.end_tag_deriv(b, lenient)),
+            Pat::After(a, b) => if lenient || self.nullable(a) { b.clone() } else { not_allowed() },
+            _ => not_allowed(),
+        }
+    }
+
+    /// Describes what `p` accepts next, for error messages
+    fn expected(&self, p: &Pat, out: &mut Vec<String>, depth: usize) {
+        let mut push = |s: String| if !out.contains(&s) { out.push(s) };
+        match p {
+            Pat::Choice(a, b) | Pat::Interleave(a, b) => {
+                self.expected(a, out, depth);
+                self.expected(b, out, depth);
+            },
+            Pat::Group(a, b) => {
+                self.expected(a, out, depth);
+                if self.nullable(a) {
+                    self.expected(b, out, depth);
+                }
+            },
+            Pat::OneOrMore(inner) => self.expected(inner, out, depth),
+            Pat::After(a, _) => self.expected(a, out, depth),
+            Pat::Element(nc, _) => push(format!("element `{}`", nc.describe())),
+            Pat::Text => push("text".to_string()),
+            Pat::Data(..) | Pat::Value(..) | Pat::List(_) => push(self.describe(p, 0)),
+            Pat::Ref(r) if depth < 64 => self.expected(&self.deref(r), out, depth + 1),
+            _ => {},
+        }
+    }
+
+    /// Writes a pattern in the compact syntax, for error messages
+    fn describe(&self, p: &Pat, depth: usize) -> String {
+        if depth > 4 {
+            return "...".to_string();
+        }
+        let pair = |a: &Pat, op: &str, b: &Pat| format!("{}{}{}", self.describe(a, depth + 1), op, self.describe(b, depth + 1));
+        match p {
+            Pat::Empty => "empty".to_string(),
+            Pat::NotAllowed => "notAllowed".to_string(),
+            Pat::Text => "text".to_string(),
+            Pat::Choice(a, b) => pair(a, " | ", b),
+            Pat::Interleave(a, b) => pair(a, " & ", b),
+            Pat::Group(a, b) => pair(a, ", ", b),
+            Pat::OneOrMore(inner) => format!("({})+", self.describe(inner, depth + 1)),
+            Pat::List(inner) => format!("list {{ {} }}", self.describe(inner, depth + 1)),
+            Pat::Element(nc, _) => format!("element {} {{ ... }}", nc.describe()),
+            Pat::Attribute(nc, _) => format!("attribute {} {{ ... }}", nc.describe()),
+            Pat::Data(dt, _) => dt.describe(),
+            Pat::Value(_, v) => format!("{:?}", v),
+            Pat::Ref(r) => r.rsplit('#').next().unwrap_or(r).to_string(),
+            Pat::After(a, _) => self.describe(a, depth),
+        }
+    }
+
+    /// The names of attributes that `p` cannot do without
+    fn required_attributes(&self, p: &Pat, depth: usize) -> HashSet<String> {
+        match p {
+            Pat::Group(a, b) | Pat::Interleave(a, b) => {
+                let mut set = self.required_attributes(a, depth);
+                set.extend(self.required_attributes(b, depth));
+                set
+            },
+            Pat::Choice(a, b) => {
+                let other = self.required_attributes(b, depth);
+                self.required_attributes(a, depth).into_iter().filter(|n| other.contains(n)).collect()
+            },
+            Pat::OneOrMore(inner) => self.required_attributes(inner, depth),
+            Pat::After(a, _) => self.required_attributes(a, depth),
+            Pat::Attribute(NameClass::Name(n), _) => Some(n.clone()).into_iter().collect(),
+            Pat::Ref(r) if depth < 64 => self.required_attributes(&self.deref(r), depth + 1),
+            _ => HashSet::new(),
+        }
+    }
+
+    /// The value pattern of an attribute named `name` in `p`, for error messages
+    fn attribute_value(&self, p: &Pat, name: &str, depth: usize) -> Option<Rc<Pat>> {
+        match p {
+            Pat::Choice(a, b) | Pat::Group(a, b) | Pat::Interleave(a, b) => {
+                self.attribute_value(a, name, depth).or_else(|| self.attribute_value(b, name, depth))
+            },
+            Pat::OneOrMore(inner) | Pat::After(inner, _) => self.attribute_value(inner, name, depth),
+            Pat::Attribute(nc, value) if nc.contains(name) => Some(value.clone()),
+            Pat::Ref(r) if depth < 64 => self.attribute_value(&self.deref(r), name, depth + 1),
+            _ => None,
+        }
+    }
+}
+
+struct Validator<'r> {
+    rng: &'r RelaxNg,
+    reporter: Reporter<'r>,
+}
+
+impl<'r> Validator<'r> {
+    /// Returns the derivative of `p` with respect to `ele`. When `ele` does not match, the problem is
+    /// reported and validation carries on as if it had.
+    fn element(&mut self, p: Rc<Pat>, ele: &XMLElement, path: &ElementPath) -> Rc<Pat> {
+        let rng = self.rng;
+        let opened = rng.start_tag_open_deriv(&p, &ele.name);
+        if let Pat::NotAllowed = *opened {
+            let msg = format!("element `{}` is not allowed here{}", ele.name, self.expected(&p));
+            self.reporter.element(path, msg);
+            return p;
+        }
+
+        let mut with_attrs = opened;
+        let mut rejected = Vec::new();
+        for a in ele.attrs.iter().flatten().filter(|a| a.name != "xmlns" && !a.name.starts_with("xmlns:")) {
+            let next = rng.att_deriv(&with_attrs, a);
+            if let Pat::NotAllowed = *next {
+                let msg = match rng.attribute_value(&with_attrs, &a.name, 0) {
+                    Some(value) => format!("value `{}` of attribute `{}` does not match {}", a.value, a.name, rng.describe(&value, 0)),
+                    None => format!("attribute `{}` is not allowed", a.name),
+                };
+                self.reporter.attr(path, &a.name, msg);
+                rejected.push(a.name.as_str());
+            } else {
+                with_attrs = next;
+            }
+        }
+
+        let mut content = rng.start_tag_close_deriv(&with_attrs, false);
+        if let Pat::NotAllowed = *content {
+            // attributes with a bad value were reported already
+            let mut missing: Vec<String> = rng.required_attributes(&with_attrs, 0).into_iter()
+                                              .filter(|m| !rejected.contains(&m.as_str())).collect();
+            missing.sort();
+            if missing.is_empty() && rejected.is_empty() {
+                self.reporter.element(path, format!("element `{}` is missing an attribute", ele.name));
+            }
+            for m in missing {
+                self.reporter.element(path, format!("required attribute `{}` is missing", m));
+            }
+            content = rng.start_tag_close_deriv(&with_attrs, true);
+        }
+
+        let contents = ele.contents.as_deref().unwrap_or(&[]);
+        for (i, c) in contents.iter().enumerate() {
+            content = self.element(content, c, &path.child(ele, i));
+        }
+        match &ele.text {
+            Some(text) => {
+                let next = rng.text_deriv(&content, text);
+                if let Pat::NotAllowed = *next {
+                    let mut expected = Vec::new();
+                    rng.expected(&content, &mut expected, 0);
+                    let msg = if expected.is_empty() || expected.iter().all(|e| e.starts_with("element")) {
+                        format!("text is not allowed in element `{}`", ele.name)
+                    } else {
+                        let data: Vec<String> = expected.into_iter().filter(|e| !e.starts_with("element")).collect();
+                        format!("value `{}` of element `{}` does not match {}", text, ele.name, data.join(" | "))
+                    };
+                    self.reporter.element(path, msg);
+                } else {
+                    content = next;
+                }
+            },
+            // an empty element matches patterns that accept the empty string, like `xsd:string`
+            None if contents.is_empty() => content = choice(content.clone(), rng.text_deriv(&content, "")),
+            None => {},
+        }
+
+        let closed = rng.end_tag_deriv(&content, false);
+        if let Pat::NotAllowed = *closed {
+            let msg = format!("element `{}` is incomplete{}", ele.name, self.expected(&content));
+            self.reporter.element(path, msg);
+            return rng.end_tag_deriv(&content, true);
+        }
+        closed
+    }
+
+    fn expected(&self, p: &Pat) -> String {
+        let mut expected = Vec::new();
+        self.rng.expected(p, &mut expected, 0);
+        if expected.is_empty() {
+            String::new()
+        } else {
+            format!(", expected {}", expected.join(" or "))
+        }
+    }
+}
+
+/// Builds patterns from grammars in the XML syntax. Each grammar gets a scope number, and named
+/// patterns are stored as `scope#name` so nested and external grammars cannot clash.
+#[derive(Default)]
+struct Loader {
+    defines: HashMap<String, Rc<Pat>>,
+    /// the parent of each scope
+    scopes: Vec<Option<usize>>,
+    dir: PathBuf,
+    /// files currently being included, to catch include loops
+    loading: Vec<PathBuf>,
+}
+
+fn attr<'e>(e: &'e XMLElement, name: &str) -> Option<&'e str> {
+    e.attrs.iter().flatten().find(|a| a.name == name).map(|a| a.value.as_str())
+}
+
+fn children(e: &XMLElement) -> impl Iterator<Item = &XMLElement> {
+    e.contents.iter().flatten()
+}
+
+impl Loader {
+    fn new_scope(&mut self, parent: Option<usize>) -> usize {
+        self.scopes.push(parent);
+        self.scopes.len() - 1
+    }
+
+    fn finish(self, start: Rc<Pat>) -> Result<RelaxNg, SchemaError> {
+        let rng = RelaxNg{start, defines: self.defines};
+        let mut refs = Vec::new();
+        collect_refs(&rng.start, &mut refs);
+        for d in rng.defines.values() {
+            collect_refs(d, &mut refs);
+        }
+        if let Some(missing) = refs.iter().find(|r| !rng.defines.contains_key(*r)) {
+            let name = missing.rsplit('#').next().unwrap_or(missing);
+            return Err(SchemaError{file: None, message: format!("reference to undefined pattern `{}`", name)});
+        }
+        Ok(rng)
+    }
+
+    /// Loads a grammar file, returning its start pattern
+    fn external(&mut self, path: &Path, scope: usize) -> Result<Rc<Pat>, SchemaError> {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let error = |message: String| SchemaError{file: Some(path.clone()), message};
+        if self.loading.contains(&path) {
+            return Err(error("the file includes itself".to_string()));
+        }
+        let text = fs::read_to_string(&path).map_err(|e| error(e.to_string()))?;
+        let root = if path.extension().is_some_and(|e| e == "rnc") {
+            compact::parse(&text).map_err(error)?
+        } else {
+            parser::parse(&text).map_err(|e| error(e.to_string()))?
+        };
+        let dir = std::mem::replace(&mut self.dir, path.parent().map(|p| p.to_path_buf()).unwrap_or_default());
+        self.loading.push(path.clone());
+        let result = self.pattern(&root, scope, "");
+        self.loading.pop();
+        self.dir = dir;
+        result.map_err(|message| match message.strip_prefix("\u{0}") {
+            // errors from nested files already carry their file name
+            Some(nested) => SchemaError{file: None, message: nested.to_string()},
+            None => error(message),
+        })
+    }
+
+    fn load_nested(&mut self, href: &str, scope: usize) -> Result<Rc<Pat>, String> {
+        let path = self.dir.join(href);
+        self.external(&path, scope).map_err(|e| format!("\u{0}{}", e))
+    }
+
+    fn children_pattern(&mut self, e: &XMLElement, scope: usize, lib: &str) -> Result<Rc<Pat>, String> {
+        let mut p = empty();
+        for c in children(e) {
+            p = group(p, self.pattern(c, scope, lib)?);
+        }
+        Ok(p)
+    }
+
+    fn pattern(&mut self, e: &XMLElement, scope: usize, lib: &str) -> Result<Rc<Pat>, String> {
+        let lib = attr(e, "datatypeLibrary").unwrap_or(lib);
+        let fold = |loader: &mut Self, f: fn(Rc<Pat>, Rc<Pat>) -> Rc<Pat>| -> Result<Rc<Pat>, String> {
+            let mut items = Vec::new();
+            for c in children(e) {
+                items.push(loader.pattern(c, scope, lib)?);
+            }
+            let mut items = items.into_iter();
+            let first = items.next().ok_or_else(|| format!("`{}` must not be empty", e.name))?;
+            Ok(items.fold(first, f))
+        };
+        Ok(match xsd::local(&e.name) {
+            "element" | "attribute" => {
+                let mut rest: Vec<&XMLElement> = children(e).collect();
+                let nc = match attr(e, "name") {
+                    Some(name) => NameClass::Name(xsd::local(name.trim()).to_string()),
+                    None if !rest.is_empty() => name_class(rest.remove(0))?,
+                    None => return Err(format!("`{}` must have a name", e.name)),
+                };
+                let mut content = empty();
+                for c in rest {
+                    content = group(content, self.pattern(c, scope, lib)?);
+                }
+                if xsd::local(&e.name) == "element" {
+                    Rc::new(Pat::Element(nc, content))
+                } else {
+                    let value = if let Pat::Empty = *content { Rc::new(Pat::Text) } else { content };
+                    Rc::new(Pat::Attribute(nc, value))
+                }
+            },
+            "group" => self.children_pattern(e, scope, lib)?,
+            "choice" => fold(self, choice)?,
+            "interleave" => fold(self, interleave)?,
+            "optional" => choice(self.children_pattern(e, scope, lib)?, empty()),
+            "zeroOrMore" => choice(one_or_more(self.children_pattern(e, scope, lib)?), empty()),
+            "oneOrMore" => one_or_more(self.children_pattern(e, scope, lib)?),
+            "list" => Rc::new(Pat::List(self.children_pattern(e, scope, lib)?)),
+            "mixed" => interleave(self.children_pattern(e, scope, lib)?, Rc::new(Pat::Text)),
+            "empty" => empty(),
+            "text" => Rc::new(Pat::Text),
+            "notAllowed" => not_allowed(),
+            "ref" | "parentRef" => {
+                let name = attr(e, "name").ok_or("a reference must have a name")?;
+                let target = if xsd::local(&e.name) == "ref" {
+                    scope
+                } else {
+                    self.scopes[scope].ok_or("`parentRef` used outside of a nested grammar")?
+                };
+                Rc::new(Pat::Ref(format!("{}#{}", target, name.trim())))
+            },
+            "data" => {
+                let name = attr(e, "type").ok_or("`data` must have a type")?;
+                let mut facets = Facets::default();
+                let mut except = None;
+                for c in children(e) {
+                    match xsd::local(&c.name) {
+                        "param" => {
+                            let param = attr(c, "name").ok_or("`param` must have a name")?;
+                            facets.set(param, c.text.as_deref().unwrap_or(""))?;
+                        },
+                        "except" => except = Some(self.pattern(&XMLElement{name: "choice".to_string(), ..c.clone()}, scope, lib)?),
+                        other => return Err(format!("unexpected `{}` in `data`", other)),
+                    }
+                }
+                Rc::new(Pat::Data(datatype(lib, name.trim(), facets)?, except))
+            },
+            "value" => {
+                let dt = match attr(e, "type") {
+                    Some(name) => datatype(lib, name.trim(), Facets::default())?,
+                    None => Datatype::Token,
+                };
+                Rc::new(Pat::Value(dt, e.text.clone().unwrap_or_default()))
+            },
+            "externalRef" => {
+                let href = attr(e, "href").ok_or("`externalRef` must have an href")?;
+                let scope = self.new_scope(None);
+                self.load_nested(href, scope)?
+            },
+            "grammar" => {
+                let inner = self.new_scope(Some(scope));
+                self.grammar(e, inner, lib, &HashSet::new())?;
+                Rc::new(Pat::Ref(format!("{}#", inner)))
+            },
+            other => return Err(format!("unexpected `{}` in pattern", other)),
+        })
+    }
+
+    /// Reads the `start`, `define`, `div` and `include` elements of a grammar. Definitions named
+    /// in `skip` (with `""` for the start) were overridden by an include and are ignored.
+    fn grammar(&mut self, e: &XMLElement, scope: usize, lib: &str, skip: &HashSet<String>) -> Result<(), String> {
+        let lib = attr(e, "datatypeLibrary").unwrap_or(lib);
+        for c in children(e) {
+            match xsd::local(&c.name) {
+                "start" | "define" => {
+                    let name = match xsd::local(&c.name) {
+                        "start" => String::new(),
+                        _ => attr(c, "name").ok_or("`define` must have a name")?.trim().to_string(),
+                    };
+                    if skip.contains(&name) {
+                        continue;
+                    }
+                    let p = self.children_pattern(c, scope, attr(c, "datatypeLibrary").unwrap_or(lib))?;
+                    let key = format!("{}#{}", scope, name);
+                    let combined = match self.defines.remove(&key) {
+                        Some(old) if attr(c, "combine") == Some("interleave") => interleave(old, p),
+                        Some(old) => choice(old, p),
+                        None => p,
+                    };
+                    self.defines.insert(key, combined);
+                },
+                "div" => self.grammar(c, scope, lib, skip)?,
+                "include" => {
+                    let href = attr(c, "href").ok_or("`include` must have an href")?;
+                    let mut overrides = skip.clone();
+                    for o in children(c) {
+                        match xsd::local(&o.name) {
+                            "start" => { overrides.insert(String::new()); },
+                            "define" => { overrides.extend(attr(o, "name").map(|n| n.trim().to_string())); },
+                            _ => {},
+                        }
+                    }
+                    let path = self.dir.join(href);
+                    let included = self.load_grammar_file(&path, scope, &overrides)?;
+                    if !included {
+                        return Err(format!("`{}` is not a grammar", href));
+                    }
+                    self.grammar(c, scope, lib, skip)?;
+                },
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the definitions of an included file into `scope`. Returns false when the file is not a grammar.
+    fn load_grammar_file(&mut self, path: &Path, scope: usize, skip: &HashSet<String>) -> Result<bool, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let root = if path.extension().is_some_and(|e| e == "rnc") {
+            compact::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?
+        } else {
+            parser::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?
+        };
+        if xsd::local(&root.name) != "grammar" {
+            return Ok(false);
+        }
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if self.loading.contains(&canonical) {
+            return Err(format!("{}: the file includes itself", path.display()));
+        }
+        let dir = std::mem::replace(&mut self.dir, path.parent().map(|p| p.to_path_buf()).unwrap_or_default());
+        self.loading.push(canonical);
+        let result = self.grammar(&root, scope, "", skip);
+        self.loading.pop();
+        self.dir = dir;
+        result.map(|_| true)
+    }
+}
+
+fn collect_refs(p: &Pat, out: &mut Vec<String>) {
+    match p {
+        Pat::Ref(r) => out.push(r.clone()),
+        Pat::Choice(a, b) | Pat::Interleave(a, b) | Pat::Group(a, b) | Pat::After(a, b) => {
+            collect_refs(a, out);
+            collect_refs(b, out);
+        },
+        Pat::OneOrMore(a) | Pat::List(a) | Pat::Element(_, a) | Pat::Attribute(_, a) | Pat::Data(_, Some(a)) => collect_refs(a, out),
+        _ => {},
+    }
+}
+
+fn name_class(e: &XMLElement) -> Result<NameClass, String> {
+    let except = |e: &XMLElement| -> Result<Option<Box<NameClass>>, String> {
+        match children(e).find(|c| xsd::local(&c.name) == "except") {
+            Some(x) => {
+                let mut names = children(x).map(name_class);
+                let first = names.next().ok_or("`except` must not be empty")??;
+                let all = names.try_fold(first, |a, b| b.map(|b| NameClass::Choice(Box::new(a), Box::new(b))))?;
+                Ok(Some(Box::new(all)))
+            },
+            None => Ok(None),
+        }
+    };
+    match xsd::local(&e.name) {
+        "name" => Ok(NameClass::Name(xsd::local(e.text.as_deref().unwrap_or("").trim()).to_string())),
+        "anyName" | "nsName" => Ok(NameClass::AnyName(except(e)?)),
+        "choice" => {
+            let mut names = children(e).map(name_class);
+            let first = names.next().ok_or("`choice` must not be empty")??;
+            names.try_fold(first, |a, b| b.map(|b| NameClass::Choice(Box::new(a), Box::new(b))))
+        },
+        other => Err(format!("unexpected `{}` in name class", other)),
+    }
+}
+
+fn datatype(lib: &str, name: &str, facets: Facets) -> Result<Datatype, String> {
+    match (lib, name) {
+        ("", "string") => Ok(Datatype::String),
+        ("", "token") => Ok(Datatype::Token),
+        ("", other) => Err(format!("unknown built-in datatype `{}`", other)),
+        (XSD_DATATYPES, name) if xsd::is_builtin(name) => Ok(Datatype::Xsd(name.to_string(), Box::new(facets))),
+        (XSD_DATATYPES, name) => Err(format!("unknown XML Schema datatype `{}`", name)),
+        (other, _) => Err(format!("unsupported datatype library `{}`", other)),
+    }
+}
+
+/// Translates the compact syntax into the equivalent XML syntax
+mod compact {
+    use super::XSD_DATATYPES;
+    use crate::XMLElement;
+    use std::collections::HashMap;
+
+    #[derive(Clone,PartialEq,Debug)]
+    enum Tok {
+        /// An identifier or keyword, and whether it was escaped with `\`
+        Ident(String, bool),
+        /// `prefix:local`
+        CName(String, String),
+        /// `prefix:*`
+        NsName(String),
+        Literal(String),
+        Punct(&'static str),
+    }
+
+    const KEYWORDS: &[&str] = &["attribute", "default", "datatypes", "div", "element", "empty", "external", "grammar", "include",
+                                 "inherit", "list", "mixed", "namespace", "notAllowed", "parent", "start", "string", "text", "token"];
+    const PUNCT: &[&str] = &["|=", "&=", "=", "{", "}", "(", ")", ",", "|", "&", "?", "*", "+", "-", "~"];
+
+    fn lex(input: &str) -> Result<Vec<(Tok, usize)>, String> {
+        let line = |offset: usize| input[..offset].matches('\n').count() + 1;
+        let mut toks = Vec::new();
+        let mut pos = 0;
+        while pos < input.len() {
+            let rest = &input[pos..];
+            let c = rest.chars().next().unwrap_or(' ');
+            if c.is_whitespace() {
+                pos += c.len_utf8();
+            } else if c == '#' {
+                pos += rest.find('\n').unwrap_or(rest.len());
+            } else if c == '[' {
+                // annotations are skipped, including nested brackets and quoted `]`
+                let mut depth = 0;
+                let mut quote = None;
+                let mut end = None;
+                for (i, ch) in rest.char_indices() {
+                    match (quote, ch) {
+                        (Some(q), ch) if ch == q => quote = None,
+                        (Some(_), _) => {},
+                        (None, '"') | (None, '\'') => quote = Some(ch),
+                        (None, '[') => depth += 1,
+                        (None, ']') => {
+                            depth -= 1;
+                            if depth == 0 {
+                                end = Some(i + 1);
+                                break;
+                            }
+                        },
+                        _ => {},
+                    }
+                }
+                pos += end.ok_or_else(|| format!("unterminated annotation on line {}", line(pos)))?;
+            } else if c == '"' || c == '\'' {
+                let triple: String = std::iter::repeat_n(c, 3).collect();
+                let (delim, start) = if rest.starts_with(&triple) { (triple.as_str(), 3) } else { (&rest[..1], 1) };
+                let len = rest[start..].find(delim).ok_or_else(|| format!("unterminated literal on line {}", line(pos)))?;
+                toks.push((Tok::Literal(rest[start..start + len].to_string()), pos));
+                pos += start + len + delim.len();
+            } else if let Some(p) = PUNCT.iter().find(|p| rest.starts_with(**p)) {
+                toks.push((Tok::Punct(p), pos));
+                pos += p.len();
+            } else if c == '\\' || crate::parser::is_name_start_char(c) {
+                let escaped = c == '\\';
+                let start = if escaped { 1 } else { 0 };
+                let len = rest[start..].find(|c: char| !crate::parser::is_name_char(c) || c == ':').unwrap_or(rest.len() - start);
+                let name = rest[start..start + len].to_string();
+                pos += start + len;
+                let after = &input[pos..];
+                if after.starts_with(":*") {
+                    toks.push((Tok::NsName(name), pos));
+                    pos += 2;
+                } else if after.starts_with(':') && after[1..].starts_with(crate::parser::is_name_start_char) {
+                    let local_len = after[1..].find(|c: char| !crate::parser::is_name_char(c) || c == ':').unwrap_or(after.len() - 1);
+                    toks.push((Tok::CName(name, after[1..1 + local_len].to_string()), pos));
+                    pos += 1 + local_len;
+                } else {
+                    toks.push((Tok::Ident(name, escaped), pos));
+                }
+            } else {
+                return Err(format!("unexpected `{}` on line {}", c, line(pos)));
+            }
+        }
+        Ok(toks)
+    }
+
+    struct CompactParser<'a> {
+        input: &'a str,
+        toks: Vec<(Tok, usize)>,
+        pos: usize,
+        /// datatype prefixes and their library
+        datatypes: HashMap<String, String>,
+    }
+
+    /// Parses a compact syntax grammar into the root of the equivalent XML syntax
+    pub(super) fn parse(input: &str) -> Result<XMLElement, String> {
+        let mut datatypes = HashMap::new();
+        datatypes.insert("xsd".to_string(), XSD_DATATYPES.to_string());
+        let mut p = CompactParser{input, toks: lex(input)?, pos: 0, datatypes};
+        p.declarations()?;
+        let root = if p.at_grammar_content() {
+            let mut grammar = XMLElement::new("grammar");
+            p.grammar_content(&mut grammar)?;
+            grammar
+        } else {
+            p.pattern()?
+        };
+        match p.peek() {
+            None => Ok(root),
+            Some(_) => Err(p.error("unexpected content after the grammar")),
+        }
+    }
+
+    fn ele(name: &str) -> XMLElement {
+        XMLElement::new(name)
+    }
+
+    impl<'a> CompactParser<'a> {
+        fn peek(&self) -> Option<&Tok> {
+            self.toks.get(self.pos).map(|(t, _)| t)
+        }
+
+        fn peek_at(&self, n: usize) -> Option<&Tok> {
+            self.toks.get(self.pos + n).map(|(t, _)| t)
+        }
+
+        fn error(&self, message: &str) -> String {
+            let offset = self.toks.get(self.pos).map(|(_, o)| *o).unwrap_or(self.input.len());
+            format!("{} on line {}", message, self.input[..offset].matches('\n').count() + 1)
+        }
+
+        fn next(&mut self) -> Result<Tok, String> {
+            let tok = self.peek().cloned().ok_or_else(|| self.error("unexpected end of grammar"))?;
+            self.pos += 1;
+            Ok(tok)
+        }
+
+        fn is_keyword(&self, word: &str) -> bool {
+            matches!(self.peek(), Some(Tok::Ident(w, false)) if w == word)
+        }
+
+        fn eat_punct(&mut self, p: &str) -> bool {
+            if self.peek() == Some(&Tok::Punct(match PUNCT.iter().find(|x| **x == p) { Some(x) => x, None => return false })) {
+                self.pos += 1;
+                true
+            } else {
+                false
+            }
+        }
+
+        fn expect_punct(&mut self, p: &str) -> Result<(), String> {
+            if self.eat_punct(p) { Ok(()) } else { Err(self.error(&format!("expected `{}`", p))) }
+        }
+
+        fn identifier(&mut self) -> Result<String, String> {
+            match self.next()? {
+                Tok::Ident(name, _) => Ok(name),
+                _ => {
+                    self.pos -= 1;
+                    Err(self.error("expected an identifier"))
+                },
+            }
+        }
+
+        /// Reads a literal, including `"a" ~ "b"` concatenations
+        fn literal(&mut self) -> Result<String, String> {
+            let mut value = match self.next()? {
+                Tok::Literal(l) => l,
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.error("expected a literal"));
+                },
+            };
+            while self.eat_punct("~") {
+                match self.next()? {
+                    Tok::Literal(l) => value.push_str(&l),
+                    _ => return Err(self.error("expected a literal after `~`")),
+                }
+            }
+            Ok(value)
+        }
+
+        fn declarations(&mut self) -> Result<(), String> {
+            loop {
+                if self.is_keyword("namespace") || (self.is_keyword("default") && matches!(self.peek_at(1), Some(Tok::Ident(w, false)) if w == "namespace")) {
+                    // namespaces are not tracked, so their declarations are only skipped
+                    while !matches!(self.next()?, Tok::Literal(_)) {}
+                    if self.eat_punct("~") { self.literal()?; }
+                } else if self.is_keyword("datatypes") {
+                    self.pos += 1;
+                    let prefix = self.identifier()?;
+                    self.expect_punct("=")?;
+                    let uri = self.literal()?;
+                    self.datatypes.insert(prefix, uri);
+                } else {
+                    return Ok(());
+                }
+            }
+        }
+
+        fn at_grammar_content(&self) -> bool {
+            match (self.peek(), self.peek_at(1)) {
+                (Some(Tok::Ident(w, false)), Some(Tok::Punct("{"))) if w == "div" => true,
+                (Some(Tok::Ident(w, false)), Some(Tok::Literal(_))) if w == "include" => true,
+                (Some(Tok::Ident(_, _)), Some(Tok::Punct(p))) => *p == "=" || *p == "|=" || *p == "&=",
+                _ => false,
+            }
+        }
+
+        /// Reads `start = ...`, `name = ...`, `div { ... }` and `include "..."` into `grammar` until `}` or the end
+        fn grammar_content(&mut self, grammar: &mut XMLElement) -> Result<(), String> {
+            loop {
+                match self.peek() {
+                    None | Some(Tok::Punct("}")) => return Ok(()),
+                    _ => {},
+                }
+                if self.is_keyword("div") {
+                    self.pos += 1;
+                    self.expect_punct("{")?;
+                    let mut div = ele("div");
+                    self.grammar_content(&mut div)?;
+                    self.expect_punct("}")?;
+                    grammar.add_element(div);
+                } else if self.is_keyword("include") {
+                    self.pos += 1;
+                    let href = self.literal()?;
+                    if self.is_keyword("inherit") {
+                        self.pos += 3;
+                    }
+                    let mut include = ele("include").attr("href", href);
+                    if self.eat_punct("{") {
+                        self.grammar_content(&mut include)?;
+                        self.expect_punct("}")?;
+                    }
+                    grammar.add_element(include);
+                } else {
+                    let name = self.identifier()?;
+                    let combine = match self.next()? {
+                        Tok::Punct("=") => None,
+                        Tok::Punct("|=") => Some("choice"),
+                        Tok::Punct("&=") => Some("interleave"),
+                        _ => return Err(self.error("expected `=`, `|=` or `&=`")),
+                    };
+                    let mut def = if name == "start" { ele("start") } else { ele("define").attr("name", &name) };
+                    if let Some(c) = combine {
+                        def.add_attr("combine", c);
+                    }
+                    def.add_element(self.pattern()?);
+                    grammar.add_element(def);
+                }
+            }
+        }
+
+        fn pattern(&mut self) -> Result<XMLElement, String> {
+            let first = self.particle()?;
+            let op = match self.peek() {
+                Some(Tok::Punct(p)) if *p == "," || *p == "|" || *p == "&" => *p,
+                _ => return Ok(first),
+            };
+            let mut combined = ele(match op { "," => "group", "|" => "choice", _ => "interleave" }).element(first);
+            while self.eat_punct(op) {
+                combined.add_element(self.particle()?);
+            }
+            if let Some(Tok::Punct(p)) = self.peek() {
+                if *p == "," || *p == "|" || *p == "&" {
+                    return Err(self.error("`,`, `|` and `&` cannot be mixed without parentheses"));
+                }
+            }
+            Ok(combined)
+        }
+
+        fn particle(&mut self) -> Result<XMLElement, String> {
+            let primary = self.primary()?;
+            let wrapper = if self.eat_punct("?") {
+                "optional"
+            } else if self.eat_punct("*") {
+                "zeroOrMore"
+            } else if self.eat_punct("+") {
+                "oneOrMore"
+            } else {
+                return Ok(primary);
+            };
+            Ok(ele(wrapper).element(primary))
+        }
+
+        fn braced_pattern(&mut self, mut outer: XMLElement) -> Result<XMLElement, String> {
+            self.expect_punct("{")?;
+            outer.add_element(self.pattern()?);
+            self.expect_punct("}")?;
+            Ok(outer)
+        }
+
+        fn primary(&mut self) -> Result<XMLElement, String> {
+            let tok = self.next()?;
+            match tok {
+                Tok::Ident(word, false) => match word.as_str() {
+                    "element" | "attribute" => {
+                        let nc = self.name_class()?;
+                        self.braced_pattern(ele(&word).element(nc))
+                    },
+                    "mixed" | "list" => self.braced_pattern(ele(&word)),
+                    "empty" | "text" | "notAllowed" => Ok(ele(&word)),
+                    "grammar" => {
+                        self.expect_punct("{")?;
+                        let mut grammar = ele("grammar");
+                        self.grammar_content(&mut grammar)?;
+                        self.expect_punct("}")?;
+                        Ok(grammar)
+                    },
+                    "external" => {
+                        let href = self.literal()?;
+                        if self.is_keyword("inherit") {
+                            self.pos += 3;
+                        }
+                        Ok(ele("externalRef").attr("href", href))
+                    },
+                    "parent" => Ok(ele("parentRef").attr("name", self.identifier()?)),
+                    "string" | "token" => self.datatype(String::new(), word),
+                    _ if KEYWORDS.contains(&word.as_str()) => Err(self.error(&format!("unexpected keyword `{}`", word))),
+                    _ => Ok(ele("ref").attr("name", word)),
+                },
+                Tok::Ident(word, true) => Ok(ele("ref").attr("name", word)),
+                Tok::CName(prefix, local) => {
+                    let lib = self.datatypes.get(&prefix).cloned().ok_or_else(|| self.error(&format!("undeclared datatype prefix `{}`", prefix)))?;
+                    self.datatype(lib, local)
+                },
+                Tok::Literal(_) => {
+                    self.pos -= 1;
+                    Ok(ele("value").text(self.literal()?))
+                },
+                Tok::Punct("(") => {
+                    let p = self.pattern()?;
+                    self.expect_punct(")")?;
+                    Ok(p)
+                },
+                _ => {
+                    self.pos -= 1;
+                    Err(self.error("expected a pattern"))
+                },
+            }
+        }
+
+        /// Reads what follows a datatype name: a value literal, or parameters and an exception
+        fn datatype(&mut self, lib: String, name: String) -> Result<XMLElement, String> {
+            if let Some(Tok::Literal(_)) = self.peek() {
+                return Ok(ele("value").attr("type", name).attr("datatypeLibrary", lib).text(self.literal()?));
+            }
+            let mut data = ele("data").attr("type", name).attr("datatypeLibrary", lib);
+            if self.eat_punct("{") {
+                while !self.eat_punct("}") {
+                    let param = self.identifier()?;
+                    self.expect_punct("=")?;
+                    data.add_element(ele("param").attr("name", param).text(self.literal()?));
+                }
+            }
+            if self.eat_punct("-") {
+                data.add_element(ele("except").element(self.primary()?));
+            }
+            Ok(data)
+        }
+
+        fn name_class(&mut self) -> Result<XMLElement, String> {
+            let first = self.name_class_primary()?;
+            if self.peek() != Some(&Tok::Punct("|")) {
+                return Ok(first);
+            }
+            let mut choice = ele("choice").element(first);
+            while self.eat_punct("|") {
+                choice.add_element(self.name_class_primary()?);
+            }
+            Ok(choice)
+        }
+
+        fn name_class_primary(&mut self) -> Result<XMLElement, String> {
+            let mut any = match self.next()? {
+                Tok::Ident(name, _) => return Ok(ele("name").text(name)),
+                Tok::CName(_, local) => return Ok(ele("name").text(local)),
+                Tok::Punct("(") => {
+                    let nc = self.name_class()?;
+                    self.expect_punct(")")?;
+                    return Ok(nc);
+                },
+                Tok::Punct("*") => ele("anyName"),
+                Tok::NsName(_) => ele("nsName"),
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.error("expected a name class"));
+                },
+            };
+            if self.eat_punct("-") {
+                any.add_element(ele("except").element(self.name_class_primary()?));
+            }
+            Ok(any)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(schema: &RelaxNg, ele: &XMLElement) -> Vec<String> {
+        match validate_rng(ele, schema) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    const XML_SCHEMA: &str = r#"
+<grammar xmlns="http://relaxng.org/ns/structure/1.0" datatypeLibrary="http://www.w3.org/2001/XMLSchema-datatypes">
+  <start><ref name="order"/></start>
+  <define name="order">
+    <element name="order">
+      <attribute name="id"><data type="int"><param name="minInclusive">1</param></data></attribute>
+      <optional><attribute name="note"/></optional>
+      <interleave>
+        <oneOrMore><ref name="item"/></oneOrMore>
+        <element name="customer"><text/></element>
+      </interleave>
+      <element name="status"><choice><value>open</value><value>closed</value></choice></element>
+    </element>
+  </define>
+  <define name="item">
+    <element><name>item</name><list><oneOrMore><data type="token"/></oneOrMore></list></element>
+  </define>
+</grammar>"#;
+
+    const COMPACT_SCHEMA: &str = r#"
+# the same grammar as XML_SCHEMA
+datatypes xsd = "http://www.w3.org/2001/XMLSchema-datatypes"
+start = order
+order = element order {
+    attribute id { xsd:int { minInclusive = "1" } },
+    attribute note { text }?,
+    (item+ & element customer { text }),
+    element status { "open" | "closed" }
+}
+[ a:documentation [ "annotations are skipped" ] ]
+item = element item { list { xsd:token+ } }
+"#;
+
+    fn order() -> XMLElement {
+        XMLElement::new("order")
+            .attr("id", 7)
+            .element(XMLElement::new("item").text("a b"))
+            .element(XMLElement::new("customer").text("Jane"))
+            .element(XMLElement::new("item").text("c"))
+            .element(XMLElement::new("status").text("open"))
+    }
+
+    #[test]
+    fn rng_both_syntaxes() {
+        for schema in &[RelaxNg::parse_xml(XML_SCHEMA).unwrap(), RelaxNg::parse_compact(COMPACT_SCHEMA).unwrap()] {
+            assert_eq!(errors(schema, &order()), Vec::<String>::new());
+
+            let bad = XMLElement::new("order")
+                .attr("id", 0)
+                .attr("extra", "x")
+                .element(XMLElement::new("customer"))
+                .element(XMLElement::new("status").text("pending"));
+            assert_eq!(errors(schema, &bad), vec![
+                "/order: value `0` of attribute `id` does not match xsd:int",
+                "/order: attribute `extra` is not allowed",
+                "/order/status: element `status` is not allowed here, expected element `item`",
+                "/order: element `order` is incomplete, expected element `item`",
+            ]);
+
+            let bad = XMLElement::new("order")
+                .element(XMLElement::new("item"))
+                .element(XMLElement::new("customer").element(XMLElement::new("b")))
+                .element(XMLElement::new("status").text("closed"))
+                .text("stray");
+            assert_eq!(errors(schema, &bad), vec![
+                "/order: required attribute `id` is missing",
+                "/order/item: element `item` is incomplete, expected list { (xsd:token)+ }",
+                "/order/customer/b: element `b` is not allowed here, expected text",
+                "/order: text is not allowed in element `order`",
+            ]);
+        }
+    }
+
+    #[test]
+    fn rng_recursion_and_name_classes() {
+        let schema = RelaxNg::parse_compact(r#"
+            start = node
+            node = element node { attribute * - id { text }*, attribute id { xsd:ID }?, node* }
+        "#).unwrap();
+        let tree = XMLElement::new("node").attr("id", "a").attr("anything", 1)
+            .element(XMLElement::new("node").element(XMLElement::new("node")));
+        assert!(validate_rng(&tree, &schema).is_ok());
+        let tree = XMLElement::new("node").element(XMLElement::new("leaf"));
+        assert_eq!(errors(&schema, &tree), vec!["/node/leaf: element `leaf` is not allowed here, expected element `node`"]);
+    }
+
+    #[test]
+    fn rng_nested_grammar_and_mixed() {
+        let schema = RelaxNg::parse_compact(r#"
+            start = element doc { grammar { start = para  para = element p { mixed { parent inline* } } } }
+            inline = element b { text }
+        "#).unwrap();
+        let doc = XMLElement::new("doc").element(XMLElement::new("p").text("bold").element(XMLElement::new("b").text("x")));
+        assert!(validate_rng(&doc, &schema).is_ok());
+    }
+
+    #[test]
+    fn rng_load_files() {
+        let dir = std::env::temp_dir().join("sxs_rng_load_files");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("common.rnc"), "start = element a { b }\nb = element b { empty }").unwrap();
+        fs::write(dir.join("main.rnc"), "include \"common.rnc\" { b = element c { empty } }").unwrap();
+        fs::write(dir.join("main.rng"), r#"<grammar xmlns="http://relaxng.org/ns/structure/1.0">
+            <start><element name="wrap"><externalRef href="common.rnc"/></element></start></grammar>"#).unwrap();
+
+        let schema = RelaxNg::load(dir.join("main.rnc")).unwrap();
+        assert!(validate_rng(&XMLElement::new("a").element(XMLElement::new("c")), &schema).is_ok());
+        assert!(validate_rng(&XMLElement::new("a").element(XMLElement::new("b")), &schema).is_err());
+
+        let schema = RelaxNg::load(dir.join("main.rng")).unwrap();
+        let wrapped = XMLElement::new("wrap").element(XMLElement::new("a").element(XMLElement::new("b")));
+        assert!(validate_rng(&wrapped, &schema).is_ok());
+    }
+
+    #[test]
+    fn rng_schema_errors() {
+        assert!(RelaxNg::parse_compact("start = missing").unwrap_err().message.contains("undefined pattern `missing`"));
+        assert!(RelaxNg::parse_compact("start = element a { b, c | d }").is_err());
+        assert!(RelaxNg::parse_compact("start = element a { foo:int }").is_err());
+        assert!(RelaxNg::parse_compact("start = element a { xsd:nope }").is_err());
+        assert_eq!(RelaxNg::parse_compact("start = element a {\n  text\n").unwrap_err().message, "expected `}` on line 3");
+        assert!(RelaxNg::parse_xml("<element name='a'><bogus/></element>").is_err());
+    }
+}
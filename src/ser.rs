@@ -0,0 +1,566 @@
+/*!
+A serde `Serializer` that builds an `XMLElement` from any `T: Serialize`, enabled with the `serde` feature.
+
+Types map onto XML as follows:
+
+* A struct becomes an element named after the struct, or after the field holding it.
+* Fields become child elements, unless their name starts with `@`, in which case they become attributes.
+  A field named `$value` becomes the text of the element, or its children when it holds structs or enums.
+* Sequences, tuples and sets become one element per item, all with the field's name. In attributes and `$value`
+  text, sequences of simple values are written as a space separated list instead.
+* `None` fields are left out, while `()` and unit structs give an empty element.
+* A unit enum variant is written as text holding the variant's name. Other variants become a child element
+  named after the variant, inside the element for the field.
+  Tuple variants are not supported, as their fields have no names.
+* Maps work like structs, with their keys as names.
+
+Use serde's `rename` to get these names from ordinary fields, IE `#[serde(rename = "@id")]`.
+
+```rust
+# #[cfg(feature = "serde")] {
+use serde::Serialize;
+use simple_xml_serialize::ser::to_element;
+
+#[derive(Serialize)]
+struct Order {
+    #[serde(rename = "@id")]
+    id: u32,
+    item: Vec<Item>,
+    note: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Item {
+    #[serde(rename = "@sku")]
+    sku: String,
+    #[serde(rename = "$value")]
+    quantity: u32,
+}
+
+let order = Order{id: 7, item: vec![Item{sku: "a1".to_string(), quantity: 2}], note: None};
+assert_eq!(to_element(&order).unwrap().to_string(), r#"<Order id="7"><item sku="a1">2</item></Order>"#);
+# }
+```
+*/
+
+use std::error;
+use std::fmt;
+
+use serde::ser::{self, Serialize};
+
+use crate::XMLElement;
+
+/// The name of the field whose value becomes the text or children of its element
+pub const TEXT_KEY: &str = "$value";
+/// The prefix marking a field as an attribute
+pub const ATTR_PREFIX: char = '@';
+
+/// Why a value could not be written as XML
+#[derive(Clone,PartialEq,Debug)]
+pub struct Error {
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error{message: msg.to_string()}
+    }
+}
+
+fn error<T>(message: impl ToString) -> Result<T, Error> {
+    Err(Error{message: message.to_string()})
+}
+
+/// Serializes a struct, or an enum variant with data, into an element named after the type
+/// # Arguments
+///
+/// * `value` - The value to serialize
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "serde")] {
+/// # use serde::Serialize;
+/// use simple_xml_serialize::ser::to_element;
+///
+/// #[derive(Serialize)]
+/// struct Point {
+///     #[serde(rename = "@lat")]
+///     lat: f32,
+///     #[serde(rename = "@lon")]
+///     lon: f32,
+/// }
+/// assert_eq!(to_element(&Point{lat: 43.38, lon: 60.11}).unwrap().to_string(), r#"<Point lat="43.38" lon="60.11"/>"#);
+/// # }
+/// ```
+pub fn to_element<T: Serialize + ?Sized>(value: &T) -> Result<XMLElement, Error> {
+    match value.serialize(ValueSerializer)? {
+        Value::Struct(Some(name), entries) => element_from_entries(name, entries),
+        Value::Variant(name, inner) => element_from_value(name, *inner),
+        _ => error("only structs and enum variants with data have a name for the root element, use `to_element_named`"),
+    }
+}
+
+/// Serializes any value into an element with the given name
+/// # Arguments
+///
+/// * `name` - The name of the root element
+/// * `value` - The value to serialize
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "serde")] {
+/// use simple_xml_serialize::ser::to_element_named;
+/// assert_eq!(to_element_named("count", &3).unwrap().to_string(), "<count>3</count>");
+/// # }
+/// ```
+pub fn to_element_named<T: Serialize + ?Sized>(name: &str, value: &T) -> Result<XMLElement, Error> {
+    match value.serialize(ValueSerializer)? {
+        Value::None => Ok(XMLElement::new(name)),
+        value => element_from_value(name.to_string(), value),
+    }
+}
+
+/// What a value serializes to, before it is known where in the element it goes
+#[derive(Clone,PartialEq,Debug)]
+enum Value {
+    None,
+    Unit,
+    Scalar(String),
+    Seq(Vec<Value>),
+    /// A struct or map, with the struct's name
+    Struct(Option<String>, Vec<(String, Value)>),
+    /// An enum variant with data
+    Variant(String, Box<Value>),
+}
+
+fn element_from_entries(name: String, entries: Vec<(String, Value)>) -> Result<XMLElement, Error> {
+    let mut element = XMLElement::new(&name);
+    for (key, value) in entries {
+        add_entry(&mut element, &key, value)?;
+    }
+    Ok(element)
+}
+
+fn element_from_value(name: String, value: Value) -> Result<XMLElement, Error> {
+    match value {
+        Value::None | Value::Unit => Ok(XMLElement::new(&name)),
+        Value::Scalar(text) => Ok(XMLElement::new(&name).text(text)),
+        Value::Struct(_, entries) => element_from_entries(name, entries),
+        Value::Variant(variant, inner) => Ok(XMLElement::new(&name).element(element_from_value(variant, *inner)?)),
+        Value::Seq(_) => error(format!("`{}` holds a sequence directly, which has no element names for its items", name)),
+    }
+}
+
+/// Adds the field `key` holding `value` to `element` as an attribute, text or child elements
+fn add_entry(element: &mut XMLElement, key: &str, value: Value) -> Result<(), Error> {
+    if let Some(attr) = key.strip_prefix(ATTR_PREFIX) {
+        let value = match value {
+            Value::None => return Ok(()),
+            Value::Unit => String::new(),
+            Value::Scalar(text) => text,
+            // a sequence of simple values is written as a space separated list
+            Value::Seq(items) => {
+                let mut words = Vec::new();
+                for item in items {
+                    match item {
+                        Value::Scalar(text) => words.push(text),
+                        _ => return error(format!("attribute `{}` can only hold simple values", attr)),
+                    }
+                }
+                words.join(" ")
+            },
+            _ => return error(format!("attribute `{}` can only hold simple values", attr)),
+        };
+        element.add_attr(attr, value);
+        return Ok(());
+    }
+    if key == TEXT_KEY {
+        return add_content(element, value);
+    }
+    match value {
+        Value::None => Ok(()),
+        Value::Seq(items) => {
+            for item in items {
+                add_entry(element, key, item)?;
+            }
+            Ok(())
+        },
+        value => {
+            element.add_element(element_from_value(key.to_string(), value)?);
+            Ok(())
+        },
+    }
+}
+
+/// Adds the value of a `$value` field: simple values become text and others become child elements named after their type
+fn add_content(element: &mut XMLElement, value: Value) -> Result<(), Error> {
+    match value {
+        Value::None | Value::Unit => Ok(()),
+        Value::Scalar(text) => {
+            let text = element.text.take().unwrap_or_default() + &text;
+            element.set_text(text);
+            Ok(())
+        },
+        // simple values are written as a space separated list, like they are in attributes
+        Value::Seq(items) if items.iter().all(|i| matches!(i, Value::Scalar(_))) => {
+            let words: Vec<String> = items.into_iter().filter_map(|i| match i { Value::Scalar(s) => Some(s), _ => None }).collect();
+            add_content(element, Value::Scalar(words.join(" ")))
+        },
+        Value::Seq(items) => {
+            for item in items {
+                add_content(element, item)?;
+            }
+            Ok(())
+        },
+        Value::Struct(Some(name), entries) => {
+            element.add_element(element_from_entries(name, entries)?);
+            Ok(())
+        },
+        Value::Struct(None, entries) => {
+            for (key, value) in entries {
+                add_entry(element, &key, value)?;
+            }
+            Ok(())
+        },
+        Value::Variant(name, inner) => {
+            element.add_element(element_from_value(name, *inner)?);
+            Ok(())
+        },
+    }
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = ser::Impossible<Value, Error>;
+    type SerializeMap = StructSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = StructSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::Scalar(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        Ok(Value::Seq(v.iter().map(|b| Value::Scalar(b.to_string())).collect()))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Value, Error> {
+        Ok(Value::Scalar(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, variant: &'static str, value: &T) -> Result<Value, Error> {
+        Ok(Value::Variant(variant.to_string(), Box::new(value.serialize(self)?)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer{items: Vec::with_capacity(len.unwrap_or(0))})
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, _index: u32, variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, Error> {
+        // the fields would have no names to tell them apart
+        error(format!("tuple variant `{}::{}` cannot be written as XML, use a struct variant", name, variant))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<StructSerializer, Error> {
+        Ok(StructSerializer{name: None, entries: Vec::with_capacity(len.unwrap_or(0)), key: None, variant: None})
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<StructSerializer, Error> {
+        Ok(StructSerializer{name: Some(name), entries: Vec::with_capacity(len), key: None, variant: None})
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<StructSerializer, Error> {
+        Ok(StructSerializer{name: None, entries: Vec::with_capacity(len), key: None, variant: Some(variant)})
+    }
+}
+
+struct SeqSerializer {
+    items: Vec<Value>,
+}
+
+impl SeqSerializer {
+    fn finish(self) -> Value {
+        Value::Seq(self.items)
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+struct StructSerializer {
+    name: Option<&'static str>,
+    entries: Vec<(String, Value)>,
+    /// the key of the map entry whose value comes next
+    key: Option<String>,
+    /// set for struct variants
+    variant: Option<&'static str>,
+}
+
+impl StructSerializer {
+    fn finish(self) -> Value {
+        let value = Value::Struct(self.name.map(|n| n.to_string()), self.entries);
+        match self.variant {
+            Some(variant) => Value::Variant(variant.to_string(), Box::new(value)),
+            None => value,
+        }
+    }
+}
+
+impl ser::SerializeMap for StructSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(ValueSerializer)? {
+            Value::Scalar(key) => {
+                self.key = Some(key);
+                Ok(())
+            },
+            _ => error("map keys must be simple values to be used as names"),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().unwrap_or_default();
+        self.entries.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.entries.push((key.to_string(), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Square,
+        Circle(f32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Serialize)]
+    struct Drawing {
+        #[serde(rename = "@id")]
+        id: u32,
+        #[serde(rename = "@tags")]
+        tags: Vec<&'static str>,
+        title: Option<String>,
+        subtitle: Option<String>,
+        shape: Vec<Shape>,
+        meta: BTreeMap<String, i32>,
+        empty: (),
+        #[serde(rename = "$value")]
+        content: Vec<Shape>,
+    }
+
+    #[test]
+    fn ser_conventions() {
+        let drawing = Drawing{
+            id: 1,
+            tags: vec!["a", "b"],
+            title: Some("t".to_string()),
+            subtitle: None,
+            shape: vec![Shape::Square, Shape::Circle(1.5)],
+            meta: vec![("k".to_string(), 3)].into_iter().collect(),
+            empty: (),
+            content: vec![Shape::Rect{w: 2, h: 3}, Shape::Square],
+        };
+        let expected = concat!(
+            r#"<Drawing id="1" tags="a b"><title>t</title><shape>Square</shape><shape><Circle>1.5</Circle></shape>"#,
+            r#"<meta><k>3</k></meta><empty/><Rect><w>2</w><h>3</h></Rect>Square</Drawing>"#);
+        assert_eq!(to_element(&drawing).unwrap().to_string(), expected);
+    }
+
+    #[test]
+    fn ser_errors() {
+        assert!(to_element(&5).is_err());
+        assert!(to_element_named("list", &vec![1, 2]).is_err());
+
+        #[derive(Serialize)]
+        enum Tuple {
+            Pair(u8, u8),
+        }
+        assert_eq!(to_element(&Tuple::Pair(1, 2)).unwrap_err().to_string(), "tuple variant `Tuple::Pair` cannot be written as XML, use a struct variant");
+
+        #[derive(Serialize)]
+        struct BadAttr {
+            #[serde(rename = "@inner")]
+            inner: Drawing2,
+        }
+        #[derive(Serialize)]
+        struct Drawing2 {
+            x: u8,
+        }
+        assert_eq!(to_element(&BadAttr{inner: Drawing2{x: 1}}).unwrap_err().to_string(), "attribute `inner` can only hold simple values");
+    }
+}
@@ -0,0 +1,101 @@
+/*!
+The error type reported when an `XMLElement` tree is checked against a schema, such as a `dtd::Dtd` or an `xsd::Schema`.
+
+Every error names the path of the element it was found on, IE `/order/item[2]`, where the index is
+only given when an element has several siblings of the same name. When the tree came from
+`parser::parse_with_positions`, validators can also fill in the line and column.
+*/
+
+use std::fmt;
+
+use crate::parser::{Position, SourceMap};
+use crate::XMLElement;
+
+/// A single way in which an element did not match a schema
+#[derive(Clone,PartialEq,Debug)]
+pub struct ValidationError {
+    /// The path from the root to the element, IE `/order/item[2]`
+    pub path: String,
+    pub message: String,
+    /// Where the element or attribute was found, if the tree was parsed with positions
+    pub position: Option<Position>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)?;
+        if let Some(position) = self.position {
+            write!(f, " at {}", position)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Where an element is within the tree being validated, both for display and for position lookups
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) struct ElementPath {
+    pub(crate) display: String,
+    pub(crate) indices: Vec<usize>,
+}
+
+impl ElementPath {
+    pub(crate) fn root(root: &XMLElement) -> Self {
+        ElementPath{display: format!("/{}", root.name), indices: Vec::new()}
+    }
+
+    /// The path of the child at `index` in the contents of `parent`, which is the element at `self`
+    pub(crate) fn child(&self, parent: &XMLElement, index: usize) -> Self {
+        let contents = parent.contents.as_deref().unwrap_or(&[]);
+        let name = &contents[index].name;
+        let mut display = format!("{}/{}", self.display, name);
+        if contents.iter().filter(|c| &c.name == name).count() > 1 {
+            let nth = contents[..index].iter().filter(|c| &c.name == name).count() + 1;
+            display.push_str(&format!("[{}]", nth));
+        }
+        let mut indices = self.indices.clone();
+        indices.push(index);
+        ElementPath{display, indices}
+    }
+}
+
+/// Collects validation errors, looking up positions when a source map is available
+pub(crate) struct Reporter<'a> {
+    source_map: Option<&'a SourceMap>,
+    pub(crate) errors: Vec<ValidationError>,
+}
+
+impl<'a> Reporter<'a> {
+    pub(crate) fn new(source_map: Option<&'a SourceMap>) -> Self {
+        Reporter{source_map, errors: Vec::new()}
+    }
+
+    /// Records an error about the element at `path`
+    pub(crate) fn element(&mut self, path: &ElementPath, message: impl ToString) {
+        let position = self.source_map
+            .and_then(|m| m.position_at(&path.indices))
+            .map(|p| p.start_tag.start);
+        self.push(path, message, position);
+    }
+
+    /// Records an error about an attribute of the element at `path`
+    pub(crate) fn attr(&mut self, path: &ElementPath, attr: &str, message: impl ToString) {
+        let position = self.source_map
+            .and_then(|m| m.position_at(&path.indices))
+            .map(|p| p.attr(attr).map(|s| s.start).unwrap_or(p.start_tag.start));
+        self.push(path, message, position);
+    }
+
+    fn push(&mut self, path: &ElementPath, message: impl ToString, position: Option<Position>) {
+        self.errors.push(ValidationError{path: path.display.clone(), message: message.to_string(), position});
+    }
+
+    pub(crate) fn finish(self) -> Result<(), Vec<ValidationError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+}
@@ -0,0 +1,1609 @@
+/*!
+Validating `XMLElement` trees against an XML Schema (XSD) loaded from local files.
+
+```rust
+use simple_xml_serialize::XMLElement;
+use simple_xml_serialize::xsd::Schema;
+
+let schema = Schema::parse(r#"
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:element name="order">
+    <xs:complexType>
+      <xs:sequence>
+        <xs:element name="item" type="xs:string" maxOccurs="3"/>
+        <xs:element name="total" type="xs:decimal"/>
+      </xs:sequence>
+      <xs:attribute name="id" type="xs:positiveInteger" use="required"/>
+    </xs:complexType>
+  </xs:element>
+</xs:schema>"#).unwrap();
+
+let order = XMLElement::new("order")
+                .attr("id", 0)
+                .element(XMLElement::new("item").text("apple"))
+                .element(XMLElement::new("total").text("1.5O"));
+
+let errors = schema.validate(&order).unwrap_err();
+assert_eq!(errors[0].to_string(), "/order: value `0` of attribute `id` is not a valid positiveInteger");
+assert_eq!(errors[1].to_string(), "/order/total: value `1.5O` of element `total` is not a valid decimal");
+```
+
+Supported are global and local element, attribute, type, group and attribute group declarations;
+`sequence`, `choice`, `all` and `any` with `minOccurs`/`maxOccurs`; simple and complex content
+derived by extension or restriction; the built-in simple types; `list` and `union` types; and the
+`enumeration`, `pattern`, `length`, `minLength`, `maxLength`, `minInclusive`, `maxInclusive`,
+`minExclusive`, `maxExclusive`, `totalDigits`, `fractionDigits` and `whiteSpace` facets. `XMLElement`
+has no notion of namespaces, so elements and attributes are matched on their local names and
+`xmlns`/`xsi` attributes are ignored. Identity constraints and substitution groups are not checked.
+
+Going the other way, `XsdFragment`s describe element types and `assemble` writes them out as a schema document.
+`#[xml_element("name", schema)]` from `simple_xml_serialize_macro` generates a fragment for the annotated struct.
+*/
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::parser::{self, SourceMap};
+use crate::pattern::Pattern;
+use crate::validation::{ElementPath, Reporter, ValidationError};
+use crate::XMLElement;
+
+/// The namespace of the XML Schema vocabulary and its built-in types
+pub const XSD_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema";
+
+/// How deeply types may derive from or refer to each other before the schema is considered circular
+const MAX_DEPTH: usize = 32;
+
+/// Why a schema could not be loaded
+#[derive(Clone,PartialEq,Debug)]
+pub struct SchemaError {
+    /// The schema file the problem was found in, if the schema was loaded from a file
+    pub file: Option<PathBuf>,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}: {}", file.display(), self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// A loaded XML Schema, made of the declarations of the schema document and everything it includes
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct Schema {
+    pub(crate) elements: HashMap<String, ElementDecl>,
+    pub(crate) types: HashMap<String, TypeDef>,
+    pub(crate) groups: HashMap<String, Particle>,
+    pub(crate) attribute_groups: HashMap<String, Vec<AttrItem>>,
+    pub(crate) attributes: HashMap<String, AttributeUse>,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) struct ElementDecl {
+    pub(crate) name: String,
+    pub(crate) type_ref: TypeRef,
+    pub(crate) fixed: Option<String>,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) enum TypeRef {
+    /// One of the built-in types, by its local name
+    Builtin(String),
+    Named(String),
+    Inline(Box<TypeDef>),
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) enum TypeDef {
+    Simple(SimpleType),
+    Complex(ComplexType),
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) enum SimpleType {
+    Restriction{base: TypeRef, facets: Box<Facets>},
+    List(TypeRef),
+    Union(Vec<TypeRef>),
+}
+
+#[derive(Clone,PartialEq,Debug,Default)]
+pub(crate) struct Facets {
+    pub(crate) enumeration: Vec<String>,
+    /// Patterns given in the same restriction; a value must match one of them
+    pub(crate) patterns: Vec<Pattern>,
+    pub(crate) length: Option<usize>,
+    pub(crate) min_length: Option<usize>,
+    pub(crate) max_length: Option<usize>,
+    pub(crate) min_inclusive: Option<String>,
+    pub(crate) max_inclusive: Option<String>,
+    pub(crate) min_exclusive: Option<String>,
+    pub(crate) max_exclusive: Option<String>,
+    pub(crate) total_digits: Option<usize>,
+    pub(crate) fraction_digits: Option<usize>,
+    pub(crate) white_space: Option<String>,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) struct ComplexType {
+    pub(crate) mixed: bool,
+    /// The type this one extends or restricts
+    pub(crate) base: Option<(TypeRef, Derivation)>,
+    pub(crate) content: Content,
+    pub(crate) attributes: Vec<AttrItem>,
+    pub(crate) any_attribute: bool,
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub(crate) enum Derivation {
+    Extension,
+    Restriction,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) enum Content {
+    Empty,
+    Particle(Particle),
+    /// Text of the given simple type and no child elements
+    Simple(TypeRef),
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) enum AttrItem {
+    Attr(AttributeUse),
+    /// A reference to a global attribute, with the `use` given at the reference
+    Ref(String, Use),
+    Group(String),
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) struct AttributeUse {
+    pub(crate) name: String,
+    pub(crate) type_ref: TypeRef,
+    pub(crate) use_: Use,
+    pub(crate) fixed: Option<String>,
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub(crate) enum Use {
+    Optional,
+    Required,
+    Prohibited,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) struct Particle {
+    pub(crate) term: Term,
+    pub(crate) min: usize,
+    /// `None` for `maxOccurs="unbounded"`
+    pub(crate) max: Option<usize>,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub(crate) enum Term {
+    Element(ElementDecl),
+    ElementRef(String),
+    Seq(Vec<Particle>),
+    Choice(Vec<Particle>),
+    All(Vec<Particle>),
+    GroupRef(String),
+    Any,
+}
+
+impl Schema {
+    /// Loads a schema document, following its `include` and `import` elements relative to the file
+    /// # Arguments
+    ///
+    /// * `path` - The path of the `.xsd` file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use simple_xml_serialize::XMLElement;
+    /// use simple_xml_serialize::xsd::Schema;
+    /// # let dir = std::env::temp_dir().join("sxs_xsd_load_example");
+    /// # std::fs::create_dir_all(&dir).unwrap();
+    /// # std::fs::write(dir.join("types.xsd"), r#"<schema xmlns="http://www.w3.org/2001/XMLSchema">
+    /// #   <simpleType name="code"><restriction base="string"><pattern value="[A-Z]{3}"/></restriction></simpleType>
+    /// # </schema>"#).unwrap();
+    /// # std::fs::write(dir.join("main.xsd"), r#"<schema xmlns="http://www.w3.org/2001/XMLSchema">
+    /// #   <include schemaLocation="types.xsd"/>
+    /// #   <element name="currency" type="code"/>
+    /// # </schema>"#).unwrap();
+    /// let schema = Schema::load(dir.join("main.xsd")).unwrap();
+    /// assert!(schema.validate(&XMLElement::new("currency").text("EUR")).is_ok());
+    /// assert!(schema.validate(&XMLElement::new("currency").text("euro")).is_err());
+    /// ```
+    pub fn load(path: impl AsRef<Path>) -> Result<Schema, SchemaError> {
+        let mut schema = Schema::default();
+        schema.include(path.as_ref(), &mut HashSet::new())?;
+        Ok(schema)
+    }
+
+    /// Reads a schema document from a string. `include` and `import` are resolved relative to the current directory.
+    pub fn parse(text: &str) -> Result<Schema, SchemaError> {
+        let mut schema = Schema::default();
+        schema.add_document(text, Path::new("."), None, &mut HashSet::new())?;
+        Ok(schema)
+    }
+
+    fn include(&mut self, path: &Path, loaded: &mut HashSet<PathBuf>) -> Result<(), SchemaError> {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if !loaded.insert(path.clone()) {
+            return Ok(());
+        }
+        let text = fs::read_to_string(&path)
+            .map_err(|e| SchemaError{file: Some(path.clone()), message: e.to_string()})?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        self.add_document(&text, dir, Some(&path), loaded)
+    }
+
+    fn add_document(&mut self, text: &str, dir: &Path, file: Option<&Path>, loaded: &mut HashSet<PathBuf>) -> Result<(), SchemaError> {
+        let error = |message: String| SchemaError{file: file.map(|f| f.to_path_buf()), message};
+        let root = parser::parse(text).map_err(|e| error(e.to_string()))?;
+        if local(&root.name) != "schema" {
+            return Err(error(format!("expected a `schema` element but found `{}`", root.name)));
+        }
+        let reader = DocReader::new(&root);
+        for e in children(&root) {
+            let name = || attr(e, "name").map(|n| n.to_string()).ok_or_else(|| error(format!("global `{}` must have a name", local(&e.name))));
+            match local(&e.name) {
+                "include" | "import" | "redefine" | "override" => {
+                    // imports of namespaces like xml.xsd often have no location
+                    if let Some(location) = attr(e, "schemaLocation") {
+                        if location.contains("://") {
+                            return Err(error(format!("only local files can be loaded, not `{}`", location)));
+                        }
+                        self.include(&dir.join(location), loaded)?;
+                    }
+                },
+                "element" => {
+                    let decl = reader.element_decl(e).map_err(error)?;
+                    self.elements.entry(decl.name.clone()).or_insert(decl);
+                },
+                "complexType" => {
+                    let def = TypeDef::Complex(reader.complex_type(e).map_err(error)?);
+                    self.types.entry(name()?).or_insert(def);
+                },
+                "simpleType" => {
+                    let def = TypeDef::Simple(reader.simple_type(e).map_err(error)?);
+                    self.types.entry(name()?).or_insert(def);
+                },
+                "group" => {
+                    let particle = children(e).next()
+                        .ok_or_else(|| error(format!("group `{}` is empty", name().unwrap_or_default())))
+                        .and_then(|c| reader.particle(c).map_err(error))?;
+                    self.groups.entry(name()?).or_insert(particle);
+                },
+                "attributeGroup" => {
+                    let (items, _) = reader.attributes(e).map_err(error)?;
+                    self.attribute_groups.entry(name()?).or_insert(items);
+                },
+                "attribute" => {
+                    if let AttrItem::Attr(a) = reader.attribute(e).map_err(error)? {
+                        self.attributes.entry(a.name.clone()).or_insert(a);
+                    }
+                },
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks an element tree against the schema, returning every violation found
+    /// # Arguments
+    ///
+    /// * `root` - The root element of the document, which must match a global element declaration
+    pub fn validate(&self, root: &XMLElement) -> Result<(), Vec<ValidationError>> {
+        self.validate_inner(root, None)
+    }
+
+    /// Same as `validate`, but adds the line and column to each error using the source map from `parser::parse_with_positions`
+    pub fn validate_with_positions(&self, root: &XMLElement, source_map: &SourceMap) -> Result<(), Vec<ValidationError>> {
+        self.validate_inner(root, Some(source_map))
+    }
+
+    fn validate_inner(&self, root: &XMLElement, source_map: Option<&SourceMap>) -> Result<(), Vec<ValidationError>> {
+        let mut v = Validator{schema: self, reporter: Reporter::new(source_map)};
+        let path = ElementPath::root(root);
+        match self.elements.get(local(&root.name)) {
+            Some(decl) => v.element(root, decl, &path),
+            None => v.reporter.element(&path, format!("element `{}` is not declared in the schema", root.name)),
+        }
+        v.reporter.finish()
+    }
+
+    /// Follows a type reference to a simple type, including the simple content of a complex type
+    fn simple_def<'a>(&'a self, t: &'a TypeRef, depth: usize) -> Result<SimpleDef<'a>, String> {
+        if depth > MAX_DEPTH {
+            return Err("the schema's types are circular".to_string());
+        }
+        let def = match t {
+            TypeRef::Builtin(name) => return Ok(SimpleDef::Builtin(name)),
+            TypeRef::Named(name) => self.types.get(name).ok_or_else(|| format!("type `{}` is not declared", name))?,
+            TypeRef::Inline(def) => def,
+        };
+        match def {
+            TypeDef::Simple(st) => Ok(SimpleDef::Type(st)),
+            TypeDef::Complex(ComplexType{content: Content::Simple(inner), ..}) => self.simple_def(inner, depth + 1),
+            TypeDef::Complex(_) => Err("is not text".to_string()),
+        }
+    }
+
+    /// The built-in type a simple type is ultimately derived from, and whether it is a list
+    fn primitive<'a>(&'a self, t: &'a TypeRef, depth: usize) -> (&'a str, bool) {
+        match self.simple_def(t, depth) {
+            Ok(SimpleDef::Builtin(name)) => (name, false),
+            Ok(SimpleDef::Type(SimpleType::Restriction{base, ..})) => self.primitive(base, depth + 1),
+            Ok(SimpleDef::Type(SimpleType::List(_))) => ("", true),
+            _ => ("", false),
+        }
+    }
+
+    /// Checks `value` against a simple type. Errors complete the sentence "value `x` ..."
+    pub(crate) fn check_simple(&self, t: &TypeRef, value: &str, depth: usize) -> Result<(), String> {
+        match self.simple_def(t, depth)? {
+            SimpleDef::Builtin(name) => check_builtin(name, value),
+            SimpleDef::Type(SimpleType::Restriction{base, facets}) => {
+                self.check_simple(base, value, depth + 1)?;
+                self.check_facets(base, facets, value, depth)
+            },
+            SimpleDef::Type(SimpleType::List(item)) => {
+                value.split(parser::is_xml_whitespace).filter(|v| !v.is_empty())
+                    .try_for_each(|v| self.check_simple(item, v, depth + 1).map_err(|e| format!("has item `{}` which {}", v, e)))
+            },
+            SimpleDef::Type(SimpleType::Union(members)) => {
+                if members.iter().any(|m| self.check_simple(m, value, depth + 1).is_ok()) {
+                    Ok(())
+                } else {
+                    Err("does not match any member of the union".to_string())
+                }
+            },
+        }
+    }
+
+    fn check_facets(&self, base: &TypeRef, facets: &Facets, value: &str, depth: usize) -> Result<(), String> {
+        let (primitive, list) = self.primitive(base, depth + 1);
+        let white_space = facets.white_space.as_deref().unwrap_or(match primitive {
+            _ if list => "collapse",
+            "string" => "preserve",
+            "normalizedString" => "replace",
+            _ => "collapse",
+        });
+        let value = normalize_space(value, white_space);
+
+        if !facets.enumeration.is_empty() && !facets.enumeration.contains(&value) {
+            return Err(format!("is not one of ({})", facets.enumeration.join("|")));
+        }
+        if !facets.patterns.is_empty() && !facets.patterns.iter().any(|p| p.is_match(&value)) {
+            let patterns: Vec<&str> = facets.patterns.iter().map(|p| p.source()).collect();
+            return Err(format!("does not match pattern `{}`", patterns.join("` or `")));
+        }
+
+        let length = if list {
+            value.split(' ').filter(|v| !v.is_empty()).count()
+        } else if primitive == "hexBinary" {
+            value.len() / 2
+        } else if primitive == "base64Binary" {
+            let data = value.replace(' ', "");
+            data.len() / 4 * 3 - data.chars().rev().take_while(|c| *c == '=').count()
+        } else {
+            value.chars().count()
+        };
+        if let Some(l) = facets.length.filter(|l| *l != length) {
+            return Err(format!("must have length {}", l));
+        }
+        if let Some(l) = facets.min_length.filter(|l| length < *l) {
+            return Err(format!("must have a length of at least {}", l));
+        }
+        if let Some(l) = facets.max_length.filter(|l| length > *l) {
+            return Err(format!("must have a length of at most {}", l));
+        }
+
+        let numeric = is_numeric_type(primitive);
+        let compare = |bound: &str| if numeric {
+            let (v, b) = (value.parse::<f64>().unwrap_or(f64::NAN), bound.parse::<f64>().unwrap_or(f64::NAN));
+            v.partial_cmp(&b)
+        } else {
+            Some(value.as_str().cmp(bound))
+        };
+        use std::cmp::Ordering::*;
+        if let Some(b) = facets.min_inclusive.as_deref().filter(|b| compare(b) == Some(Less)) {
+            return Err(format!("must be at least {}", b));
+        }
+        if let Some(b) = facets.max_inclusive.as_deref().filter(|b| compare(b) == Some(Greater)) {
+            return Err(format!("must be at most {}", b));
+        }
+        if let Some(b) = facets.min_exclusive.as_deref().filter(|b| compare(b) != Some(Greater)) {
+            return Err(format!("must be greater than {}", b));
+        }
+        if let Some(b) = facets.max_exclusive.as_deref().filter(|b| compare(b) != Some(Less)) {
+            return Err(format!("must be less than {}", b));
+        }
+
+        if facets.total_digits.is_some() || facets.fraction_digits.is_some() {
+            let digits = value.trim_start_matches(['+', '-']);
+            let (int, frac) = match digits.find('.') {
+                Some(dot) => (&digits[..dot], digits[dot + 1..].trim_end_matches('0')),
+                None => (digits, ""),
+            };
+            let int = int.trim_start_matches('0');
+            if let Some(d) = facets.total_digits.filter(|d| int.len() + frac.len() > *d) {
+                return Err(format!("must have at most {} digits", d));
+            }
+            if let Some(d) = facets.fraction_digits.filter(|d| frac.len() > *d) {
+                return Err(format!("must have at most {} fraction digits", d));
+            }
+        }
+        Ok(())
+    }
+
+    /// The attributes, content and mixedness of a complex type once its base types are taken into account
+    pub(crate) fn effective(&self, ct: &ComplexType, depth: usize) -> Result<Effective, String> {
+        if depth > MAX_DEPTH {
+            return Err("the schema's types are circular".to_string());
+        }
+        let mut eff = match &ct.base {
+            Some((TypeRef::Named(name), derivation)) => match self.types.get(name) {
+                Some(TypeDef::Complex(base)) => {
+                    let mut eff = self.effective(base, depth + 1)?;
+                    match (*derivation, &ct.content) {
+                        (Derivation::Extension, Content::Empty) => {},
+                        (Derivation::Extension, Content::Particle(own)) => {
+                            eff.content = match eff.content {
+                                Content::Particle(base) => Content::Particle(Particle{term: Term::Seq(vec![base, own.clone()]), min: 1, max: Some(1)}),
+                                _ => Content::Particle(own.clone()),
+                            };
+                        },
+                        _ => eff.content = ct.content.clone(),
+                    }
+                    eff
+                },
+                Some(TypeDef::Simple(_)) => Effective{mixed: false, content: ct.content.clone(), attributes: Vec::new(), any_attribute: false},
+                None => return Err(format!("type `{}` is not declared", name)),
+            },
+            _ => Effective{mixed: false, content: ct.content.clone(), attributes: Vec::new(), any_attribute: false},
+        };
+        eff.mixed |= ct.mixed;
+        eff.any_attribute |= ct.any_attribute;
+        for a in self.attribute_uses(&ct.attributes, depth)? {
+            // attributes of a derived type replace those of the same name from the base
+            eff.attributes.retain(|b| b.name != a.name);
+            eff.attributes.push(a);
+        }
+        Ok(eff)
+    }
+
+    fn attribute_uses(&self, items: &[AttrItem], depth: usize) -> Result<Vec<AttributeUse>, String> {
+        if depth > MAX_DEPTH {
+            return Err("the schema's attribute groups are circular".to_string());
+        }
+        let mut uses = Vec::new();
+        for item in items {
+            match item {
+                AttrItem::Attr(a) => uses.push(a.clone()),
+                AttrItem::Ref(name, use_) => {
+                    let global = self.attributes.get(name).ok_or_else(|| format!("attribute `{}` is not declared", name))?;
+                    uses.push(AttributeUse{use_: *use_, ..global.clone()});
+                },
+                AttrItem::Group(name) => {
+                    let group = self.attribute_groups.get(name).ok_or_else(|| format!("attribute group `{}` is not declared", name))?;
+                    uses.extend(self.attribute_uses(group, depth + 1)?);
+                },
+            }
+        }
+        Ok(uses)
+    }
+}
+
+enum SimpleDef<'a> {
+    Builtin(&'a str),
+    Type(&'a SimpleType),
+}
+
+pub(crate) struct Effective {
+    pub(crate) mixed: bool,
+    pub(crate) content: Content,
+    pub(crate) attributes: Vec<AttributeUse>,
+    pub(crate) any_attribute: bool,
+}
+
+/// The part of a name after its namespace prefix
+pub(crate) fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn attr<'e>(e: &'e XMLElement, name: &str) -> Option<&'e str> {
+    e.attrs.iter().flatten().find(|a| a.name == name).map(|a| a.value.as_str())
+}
+
+/// The child elements of a schema element, without annotations
+fn children(e: &XMLElement) -> impl Iterator<Item = &XMLElement> {
+    e.contents.iter().flatten().filter(|c| local(&c.name) != "annotation")
+}
+
+/// Whether an attribute only carries namespace or schema instance information
+fn is_meta_attr(name: &str) -> bool {
+    name == "xmlns" || name.starts_with("xmlns:") || name.starts_with("xsi:")
+}
+
+/// Applies the `whiteSpace` facet's `preserve`, `replace` or `collapse`
+pub(crate) fn normalize_space(value: &str, white_space: &str) -> String {
+    match white_space {
+        "preserve" => value.to_string(),
+        "replace" => value.replace(['\t', '\n', '\r'], " "),
+        _ => value.split(parser::is_xml_whitespace).filter(|v| !v.is_empty()).collect::<Vec<&str>>().join(" "),
+    }
+}
+
+fn is_numeric_type(name: &str) -> bool {
+    integer_range(name).is_some() || name == "integer" || name == "decimal" || name == "float" || name == "double"
+}
+
+/// The bounds of the built-in integer types that have them
+fn integer_range(name: &str) -> Option<(i128, i128)> {
+    Some(match name {
+        "nonNegativeInteger" => (0, i128::MAX),
+        "positiveInteger" => (1, i128::MAX),
+        "nonPositiveInteger" => (i128::MIN, 0),
+        "negativeInteger" => (i128::MIN, -1),
+        "long" => (i64::MIN as i128, i64::MAX as i128),
+        "int" => (i32::MIN as i128, i32::MAX as i128),
+        "short" => (i16::MIN as i128, i16::MAX as i128),
+        "byte" => (i8::MIN as i128, i8::MAX as i128),
+        "unsignedLong" => (0, u64::MAX as i128),
+        "unsignedInt" => (0, u32::MAX as i128),
+        "unsignedShort" => (0, u16::MAX as i128),
+        "unsignedByte" => (0, u8::MAX as i128),
+        _ => return None,
+    })
+}
+
+/// The lexical pattern of the built-in types that are checked with one
+fn builtin_pattern(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "boolean" => "true|false|1|0",
+        "decimal" => "[+\\-]?(\\d+(\\.\\d*)?|\\.\\d+)",
+        "integer" => "[+\\-]?\\d+",
+        "float" | "double" => "[+\\-]?(\\d+(\\.\\d*)?|\\.\\d+)([eE][+\\-]?\\d+)?|[+\\-]?INF|NaN",
+        "duration" => "-?P(\\d+Y)?(\\d+M)?(\\d+D)?(T(\\d+H)?(\\d+M)?(\\d+(\\.\\d+)?S)?)?",
+        "gYear" => "-?\\d{4,}(Z|[+\\-]\\d{2}:\\d{2})?",
+        "gYearMonth" => "-?\\d{4,}-(0[1-9]|1[0-2])(Z|[+\\-]\\d{2}:\\d{2})?",
+        "gMonth" => "--(0[1-9]|1[0-2])(Z|[+\\-]\\d{2}:\\d{2})?",
+        "gDay" => "---(0[1-9]|[12]\\d|3[01])(Z|[+\\-]\\d{2}:\\d{2})?",
+        "gMonthDay" => "--(0[1-9]|1[0-2])-(0[1-9]|[12]\\d|3[01])(Z|[+\\-]\\d{2}:\\d{2})?",
+        "Name" => "\\i\\c*",
+        "NCName" | "ID" | "IDREF" | "ENTITY" => "[\\i-[:]][\\c-[:]]*",
+        "QName" => "([\\i-[:]][\\c-[:]]*:)?[\\i-[:]][\\c-[:]]*",
+        "NMTOKEN" => "\\c+",
+        "NMTOKENS" => "\\c+( \\c+)*",
+        "IDREFS" | "ENTITIES" => "[\\i-[:]][\\c-[:]]*( [\\i-[:]][\\c-[:]]*)*",
+        "language" => "[a-zA-Z]{1,8}(-[a-zA-Z0-9]{1,8})*",
+        "hexBinary" => "([0-9a-fA-F]{2})*",
+        "base64Binary" => "(([A-Za-z0-9+/] ?){4})*(([A-Za-z0-9+/] ?){2}[AEIMQUYcgkosw048] ?=|[A-Za-z0-9+/] ?[AQgw] ?= ?=)?",
+        "date" => "-?\\d{4,}-(0[1-9]|1[0-2])-(0[1-9]|[12]\\d|3[01])(Z|[+\\-]\\d{2}:\\d{2})?",
+        "time" => "(([01]\\d|2[0-3]):[0-5]\\d:[0-5]\\d(\\.\\d+)?|24:00:00(\\.0+)?)(Z|[+\\-]\\d{2}:\\d{2})?",
+        "dateTime" => "-?\\d{4,}-(0[1-9]|1[0-2])-(0[1-9]|[12]\\d|3[01])T(([01]\\d|2[0-3]):[0-5]\\d:[0-5]\\d(\\.\\d+)?|24:00:00(\\.0+)?)(Z|[+\\-]\\d{2}:\\d{2})?",
+        _ => return None,
+    })
+}
+
+const STRING_TYPES: &[&str] = &["string", "normalizedString", "token", "anyURI", "anySimpleType", "anyType"];
+
+/// Returns whether `name` is the local name of one of the built-in types
+pub(crate) fn is_builtin(name: &str) -> bool {
+    STRING_TYPES.contains(&name) || builtin_pattern(name).is_some() || integer_range(name).is_some()
+}
+
+/// Checks a value against one of the built-in types. Errors complete the sentence "value `x` ..."
+pub(crate) fn check_builtin(name: &str, value: &str) -> Result<(), String> {
+    let invalid = || Err(format!("is not a valid {}", name));
+    match name {
+        "string" | "anySimpleType" | "anyType" => return Ok(()),
+        "normalizedString" => {
+            return if value.contains(['\t', '\n', '\r']) { invalid() } else { Ok(()) };
+        },
+        // collapsing whitespace always produces a valid token, and URIs are not checked further
+        "token" | "anyURI" => return Ok(()),
+        _ => {},
+    }
+    let value = normalize_space(value, "collapse");
+    if let Some((min, max)) = integer_range(name) {
+        return match value.trim_start_matches('+').parse::<i128>() {
+            Ok(v) if v >= min && v <= max => Ok(()),
+            _ => invalid(),
+        };
+    }
+    match builtin_pattern(name) {
+        Some(p) => {
+            let pattern = Pattern::new(p).expect("the built-in patterns are valid");
+            let valid = pattern.is_match(&value) && (name != "duration" || (value.len() > 1 && !value.ends_with('P') && !value.ends_with('T')));
+            if valid { Ok(()) } else { invalid() }
+        },
+        None => Err(format!("has unknown type `{}`", name)),
+    }
+}
+
+/// Reads the declarations of one schema document
+struct DocReader {
+    /// Namespace prefixes bound on the `schema` element, `""` for the default namespace
+    namespaces: HashMap<String, String>,
+}
+
+impl DocReader {
+    fn new(root: &XMLElement) -> Self {
+        let namespaces = root.attrs.iter().flatten()
+            .filter_map(|a| match a.name.as_str() {
+                "xmlns" => Some((String::new(), a.value.clone())),
+                n if n.starts_with("xmlns:") => Some((n["xmlns:".len()..].to_string(), a.value.clone())),
+                _ => None,
+            })
+            .collect();
+        DocReader{namespaces}
+    }
+
+    /// Resolves a type name like `xs:string` or `myType` to a built-in or declared type
+    fn type_ref(&self, qname: &str) -> Result<TypeRef, String> {
+        let prefix = match qname.find(':') {
+            Some(i) => &qname[..i],
+            None => "",
+        };
+        let name = local(qname);
+        let in_xsd = self.namespaces.get(prefix).map(|ns| ns.as_str()) == Some(XSD_NAMESPACE);
+        match (in_xsd, is_builtin(name)) {
+            (true, true) => Ok(TypeRef::Builtin(name.to_string())),
+            // schemas that use the XSD namespace as their default refer to their own types without a prefix
+            (true, false) if !prefix.is_empty() => Err(format!("`{}` is not a built-in type", qname)),
+            _ => Ok(TypeRef::Named(name.to_string())),
+        }
+    }
+
+    /// The type of an element or attribute from its `type` attribute or an inline definition
+    fn declared_type(&self, e: &XMLElement) -> Result<TypeRef, String> {
+        if let Some(t) = attr(e, "type") {
+            return self.type_ref(t);
+        }
+        for c in children(e) {
+            match local(&c.name) {
+                "complexType" => return Ok(TypeRef::Inline(Box::new(TypeDef::Complex(self.complex_type(c)?)))),
+                "simpleType" => return Ok(TypeRef::Inline(Box::new(TypeDef::Simple(self.simple_type(c)?)))),
+                _ => {},
+            }
+        }
+        Ok(TypeRef::Builtin(if local(&e.name) == "attribute" { "anySimpleType" } else { "anyType" }.to_string()))
+    }
+
+    fn element_decl(&self, e: &XMLElement) -> Result<ElementDecl, String> {
+        let name = attr(e, "name").ok_or("an element declaration must have a name")?;
+        Ok(ElementDecl{
+            name: name.to_string(),
+            type_ref: self.declared_type(e)?,
+            fixed: attr(e, "fixed").map(|f| f.to_string()),
+        })
+    }
+
+    fn occurs(&self, e: &XMLElement) -> Result<(usize, Option<usize>), String> {
+        let min = match attr(e, "minOccurs") {
+            Some(m) => m.trim().parse().map_err(|_| format!("invalid minOccurs `{}`", m))?,
+            None => 1,
+        };
+        let max = match attr(e, "maxOccurs").map(|m| m.trim()) {
+            Some("unbounded") => None,
+            Some(m) => Some(m.parse().map_err(|_| format!("invalid maxOccurs `{}`", m))?),
+            None => Some(1),
+        };
+        Ok((min, max))
+    }
+
+    /// Reads an `element`, `sequence`, `choice`, `all`, `group` or `any` within a content model
+    fn particle(&self, e: &XMLElement) -> Result<Particle, String> {
+        let (min, max) = self.occurs(e)?;
+        let nested = || children(e)
+            .filter(|c| ["element", "sequence", "choice", "all", "group", "any"].contains(&local(&c.name)))
+            .map(|c| self.particle(c))
+            .collect::<Result<Vec<Particle>, String>>();
+        let term = match local(&e.name) {
+            "element" => match attr(e, "ref") {
+                Some(r) => Term::ElementRef(local(r).to_string()),
+                None => Term::Element(self.element_decl(e)?),
+            },
+            "sequence" => Term::Seq(nested()?),
+            "choice" => Term::Choice(nested()?),
+            "all" => Term::All(nested()?),
+            "group" => match attr(e, "ref") {
+                Some(r) => Term::GroupRef(local(r).to_string()),
+                None => return children(e).next().ok_or_else(|| "group is empty".to_string()).and_then(|c| self.particle(c)),
+            },
+            "any" => Term::Any,
+            other => return Err(format!("unexpected `{}` in content model", other)),
+        };
+        Ok(Particle{term, min, max})
+    }
+
+    fn attribute(&self, e: &XMLElement) -> Result<AttrItem, String> {
+        let use_ = match attr(e, "use") {
+            Some("required") => Use::Required,
+            Some("prohibited") => Use::Prohibited,
+            Some("optional") | None => Use::Optional,
+            Some(other) => return Err(format!("invalid attribute use `{}`", other)),
+        };
+        if let Some(r) = attr(e, "ref") {
+            return Ok(AttrItem::Ref(local(r).to_string(), use_));
+        }
+        let name = attr(e, "name").ok_or("an attribute declaration must have a name")?;
+        Ok(AttrItem::Attr(AttributeUse{
+            name: name.to_string(),
+            type_ref: self.declared_type(e)?,
+            use_,
+            fixed: attr(e, "fixed").map(|f| f.to_string()),
+        }))
+    }
+
+    /// Reads the attribute declarations among the children of `e`, and whether it has an `anyAttribute`
+    fn attributes(&self, e: &XMLElement) -> Result<(Vec<AttrItem>, bool), String> {
+        let mut items = Vec::new();
+        let mut any = false;
+        for c in children(e) {
+            match local(&c.name) {
+                "attribute" => items.push(self.attribute(c)?),
+                "attributeGroup" => {
+                    let r = attr(c, "ref").ok_or("an attribute group reference must have a ref")?;
+                    items.push(AttrItem::Group(local(r).to_string()));
+                },
+                "anyAttribute" => any = true,
+                _ => {},
+            }
+        }
+        Ok((items, any))
+    }
+
+    /// The content model among the children of `e`, if any
+    fn content_particle(&self, e: &XMLElement) -> Result<Content, String> {
+        for c in children(e) {
+            if ["sequence", "choice", "all", "group"].contains(&local(&c.name)) {
+                return Ok(Content::Particle(self.particle(c)?));
+            }
+        }
+        Ok(Content::Empty)
+    }
+
+    fn complex_type(&self, e: &XMLElement) -> Result<ComplexType, String> {
+        let mixed = attr(e, "mixed") == Some("true");
+        for c in children(e) {
+            let kind = local(&c.name);
+            if kind != "simpleContent" && kind != "complexContent" {
+                continue;
+            }
+            let derivation = children(c).next().ok_or_else(|| format!("`{}` is empty", kind))?;
+            let base = self.type_ref(attr(derivation, "base").ok_or("a derivation must have a base")?)?;
+            let how = match local(&derivation.name) {
+                "extension" => Derivation::Extension,
+                "restriction" => Derivation::Restriction,
+                other => return Err(format!("unexpected `{}` in `{}`", other, kind)),
+            };
+            let content = match (kind, how) {
+                ("simpleContent", Derivation::Extension) => Content::Simple(base.clone()),
+                ("simpleContent", Derivation::Restriction) => {
+                    let facets = self.facets(derivation)?;
+                    Content::Simple(TypeRef::Inline(Box::new(TypeDef::Simple(SimpleType::Restriction{base: base.clone(), facets: Box::new(facets)}))))
+                },
+                _ => self.content_particle(derivation)?,
+            };
+            let (attributes, any_attribute) = self.attributes(derivation)?;
+            let mixed = mixed || attr(c, "mixed") == Some("true");
+            return Ok(ComplexType{mixed, base: Some((base, how)), content, attributes, any_attribute});
+        }
+        let (attributes, any_attribute) = self.attributes(e)?;
+        Ok(ComplexType{mixed, base: None, content: self.content_particle(e)?, attributes, any_attribute})
+    }
+
+    fn simple_type(&self, e: &XMLElement) -> Result<SimpleType, String> {
+        let c = children(e).next().ok_or("a simple type must have a restriction, list or union")?;
+        let inline = |c: &XMLElement| -> Result<Option<TypeRef>, String> {
+            match children(c).find(|s| local(&s.name) == "simpleType") {
+                Some(s) => Ok(Some(TypeRef::Inline(Box::new(TypeDef::Simple(self.simple_type(s)?))))),
+                None => Ok(None),
+            }
+        };
+        match local(&c.name) {
+            "restriction" => {
+                let base = match attr(c, "base") {
+                    Some(b) => self.type_ref(b)?,
+                    None => inline(c)?.ok_or("a restriction must have a base")?,
+                };
+                Ok(SimpleType::Restriction{base, facets: Box::new(self.facets(c)?)})
+            },
+            "list" => {
+                let item = match attr(c, "itemType") {
+                    Some(t) => self.type_ref(t)?,
+                    None => inline(c)?.ok_or("a list must have an item type")?,
+                };
+                Ok(SimpleType::List(item))
+            },
+            "union" => {
+                let mut members = Vec::new();
+                for m in attr(c, "memberTypes").unwrap_or("").split(parser::is_xml_whitespace).filter(|m| !m.is_empty()) {
+                    members.push(self.type_ref(m)?);
+                }
+                for s in children(c).filter(|s| local(&s.name) == "simpleType") {
+                    members.push(TypeRef::Inline(Box::new(TypeDef::Simple(self.simple_type(s)?))));
+                }
+                Ok(SimpleType::Union(members))
+            },
+            other => Err(format!("unexpected `{}` in simple type", other)),
+        }
+    }
+
+    fn facets(&self, e: &XMLElement) -> Result<Facets, String> {
+        let mut facets = Facets::default();
+        for c in children(e) {
+            if let Some(value) = attr(c, "value") {
+                facets.set(local(&c.name), value)?;
+            }
+        }
+        Ok(facets)
+    }
+}
+
+impl Facets {
+    /// Sets the facet called `name`, ignoring names that are not facets
+    pub(crate) fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let number = || value.trim().parse::<usize>().map(Some).map_err(|_| format!("invalid `{}` value `{}`", name, value));
+        match name {
+            "enumeration" => self.enumeration.push(value.to_string()),
+            "pattern" => self.patterns.push(Pattern::new(value)?),
+            "length" => self.length = number()?,
+            "minLength" => self.min_length = number()?,
+            "maxLength" => self.max_length = number()?,
+            "totalDigits" => self.total_digits = number()?,
+            "fractionDigits" => self.fraction_digits = number()?,
+            "minInclusive" => self.min_inclusive = Some(value.to_string()),
+            "maxInclusive" => self.max_inclusive = Some(value.to_string()),
+            "minExclusive" => self.min_exclusive = Some(value.to_string()),
+            "maxExclusive" => self.max_exclusive = Some(value.to_string()),
+            "whiteSpace" => self.white_space = Some(value.to_string()),
+            _ => {},
+        }
+        Ok(())
+    }
+}
+
+/// Checks a value against a built-in type restricted by `facets`, for schema languages that borrow the XSD types
+pub(crate) fn check_datatype(name: &str, facets: &Facets, value: &str) -> Result<(), String> {
+    check_builtin(name, value)?;
+    Schema::default().check_facets(&TypeRef::Builtin(name.to_string()), facets, value, 0)
+}
+
+struct Validator<'s> {
+    schema: &'s Schema,
+    reporter: Reporter<'s>,
+}
+
+impl<'s> Validator<'s> {
+    fn element(&mut self, ele: &XMLElement, decl: &ElementDecl, path: &ElementPath) {
+        if let (Some(fixed), Some(text)) = (&decl.fixed, &ele.text) {
+            if text != fixed {
+                self.reporter.element(path, format!("element `{}` must have the value `{}`", ele.name, fixed));
+            }
+        }
+        let def = match &decl.type_ref {
+            TypeRef::Builtin(name) if name == "anyType" => return,
+            TypeRef::Builtin(_) => None,
+            TypeRef::Named(name) => match self.schema.types.get(name) {
+                Some(def) => Some(def),
+                None => return self.reporter.element(path, format!("type `{}` is not declared", name)),
+            },
+            TypeRef::Inline(def) => Some(&**def),
+        };
+        match def {
+            Some(TypeDef::Complex(ct)) => self.complex(ele, ct, path),
+            _ => {
+                self.no_attributes(ele, path);
+                self.text_only(ele, &decl.type_ref, path);
+            },
+        }
+    }
+
+    fn no_attributes(&mut self, ele: &XMLElement, path: &ElementPath) {
+        for a in ele.attrs.iter().flatten().filter(|a| !is_meta_attr(&a.name)) {
+            self.reporter.attr(path, &a.name, format!("attribute `{}` is not allowed", a.name));
+        }
+    }
+
+    /// Checks an element that may only hold text of a simple type
+    fn text_only(&mut self, ele: &XMLElement, t: &TypeRef, path: &ElementPath) {
+        if ele.contents.as_ref().is_some_and(|c| !c.is_empty()) {
+            self.reporter.element(path, format!("element `{}` cannot contain elements", ele.name));
+        }
+        let text = ele.text.as_deref().unwrap_or("");
+        if let Err(e) = self.schema.check_simple(t, text, 0) {
+            self.reporter.element(path, format!("value `{}` of element `{}` {}", text, ele.name, e));
+        }
+    }
+
+    fn complex(&mut self, ele: &XMLElement, ct: &ComplexType, path: &ElementPath) {
+        let eff = match self.schema.effective(ct, 0) {
+            Ok(eff) => eff,
+            Err(e) => return self.reporter.element(path, e),
+        };
+        for a in ele.attrs.iter().flatten().filter(|a| !is_meta_attr(&a.name)) {
+            match eff.attributes.iter().find(|u| u.name == local(&a.name) && u.use_ != Use::Prohibited) {
+                Some(u) => {
+                    if let Some(fixed) = u.fixed.as_ref().filter(|f| **f != a.value) {
+                        self.reporter.attr(path, &a.name, format!("attribute `{}` must have the value `{}`", a.name, fixed));
+                    } else if let Err(e) = self.schema.check_simple(&u.type_ref, &a.value, 0) {
+                        self.reporter.attr(path, &a.name, format!("value `{}` of attribute `{}` {}", a.value, a.name, e));
+                    }
+                },
+                None if eff.any_attribute => {},
+                None => self.reporter.attr(path, &a.name, format!("attribute `{}` is not allowed", a.name)),
+            }
+        }
+        for u in eff.attributes.iter().filter(|u| u.use_ == Use::Required) {
+            if !ele.attrs.iter().flatten().any(|a| local(&a.name) == u.name) {
+                self.reporter.element(path, format!("required attribute `{}` is missing", u.name));
+            }
+        }
+
+        let contents = ele.contents.as_deref().unwrap_or(&[]);
+        match &eff.content {
+            Content::Simple(t) => return self.text_only(ele, t, path),
+            _ if ele.text.is_some() && !eff.mixed => {
+                self.reporter.element(path, format!("text is not allowed in element `{}`", ele.name));
+            },
+            _ => {},
+        }
+        let particle = match &eff.content {
+            Content::Particle(p) => p,
+            _ => {
+                if !contents.is_empty() {
+                    self.reporter.element(path, format!("element `{}` cannot contain elements", ele.name));
+                }
+                return;
+            },
+        };
+
+        let names: Vec<&str> = contents.iter().map(|c| local(&c.name)).collect();
+        let matcher = Matcher{schema: self.schema};
+        if !matcher.ends(particle, &names, 0, 0).contains(&names.len()) {
+            let reached = matcher.prefixes(particle, &names, 0, 0).into_iter().max().unwrap_or(0);
+            let mut candidates = Vec::new();
+            matcher.collect_names(&particle.term, &mut candidates, 0);
+            let mut expected: Vec<String> = Vec::new();
+            for c in candidates {
+                let mut extended = names[..reached].to_vec();
+                extended.push(&c);
+                if matcher.prefixes(particle, &extended, 0, 0).contains(&(reached + 1)) && !expected.contains(&c) {
+                    expected.push(c);
+                }
+            }
+            let expected = match expected.len() {
+                0 => String::new(),
+                _ => format!(", expected `{}`", expected.join("` or `")),
+            };
+            if reached < contents.len() {
+                let msg = format!("element `{}` is not expected here{}", contents[reached].name, expected);
+                self.reporter.element(&path.child(ele, reached), msg);
+            } else {
+                self.reporter.element(path, format!("element `{}` is incomplete{}", ele.name, expected));
+            }
+        }
+
+        for (i, c) in contents.iter().enumerate() {
+            if let Some(decl) = matcher.decl_for(particle, local(&c.name), 0) {
+                self.element(c, &decl, &path.child(ele, i));
+            }
+        }
+    }
+}
+
+/// Matches the names of an element's children against a content model
+struct Matcher<'s> {
+    schema: &'s Schema,
+}
+
+impl<'s> Matcher<'s> {
+    fn group(&self, name: &str, depth: usize) -> Option<&'s Particle> {
+        if depth > MAX_DEPTH { None } else { self.schema.groups.get(name) }
+    }
+
+    /// All positions where a complete match of `p` starting at `start` can end
+    fn ends(&self, p: &Particle, names: &[&str], start: usize, depth: usize) -> BTreeSet<usize> {
+        let mut out = BTreeSet::new();
+        if p.min == 0 {
+            out.insert(start);
+        }
+        let mut frontier: BTreeSet<usize> = Some(start).into_iter().collect();
+        let mut seen = BTreeSet::new();
+        let mut count = 0;
+        while !frontier.is_empty() && p.max.is_none_or(|m| count < m) {
+            frontier = frontier.iter().flat_map(|s| self.term_ends(&p.term, names, *s, depth)).collect();
+            count += 1;
+            if count >= p.min {
+                frontier.retain(|e| seen.insert(*e));
+                out.extend(frontier.iter().cloned());
+            }
+        }
+        out
+    }
+
+    /// All positions up to which the names are the start of some match of `p`
+    fn prefixes(&self, p: &Particle, names: &[&str], start: usize, depth: usize) -> BTreeSet<usize> {
+        let mut out: BTreeSet<usize> = Some(start).into_iter().collect();
+        let mut frontier = out.clone();
+        let mut seen = BTreeSet::new();
+        let mut count = 0;
+        while !frontier.is_empty() && p.max.is_none_or(|m| count < m) {
+            for s in &frontier {
+                out.extend(self.term_prefixes(&p.term, names, *s, depth));
+            }
+            frontier = frontier.iter().flat_map(|s| self.term_ends(&p.term, names, *s, depth)).collect();
+            frontier.retain(|e| seen.insert(*e));
+            count += 1;
+        }
+        out
+    }
+
+    fn term_ends(&self, term: &Term, names: &[&str], start: usize, depth: usize) -> BTreeSet<usize> {
+        match term {
+            Term::Element(decl) => self.name_end(&decl.name, names, start),
+            Term::ElementRef(name) => self.name_end(name, names, start),
+            Term::Any => if start < names.len() { Some(start + 1).into_iter().collect() } else { BTreeSet::new() },
+            Term::Seq(items) => {
+                let mut positions: BTreeSet<usize> = Some(start).into_iter().collect();
+                for item in items {
+                    positions = positions.iter().flat_map(|s| self.ends(item, names, *s, depth)).collect();
+                }
+                positions
+            },
+            Term::Choice(items) => items.iter().flat_map(|i| self.ends(i, names, start, depth)).collect(),
+            Term::All(items) => {
+                let mut out = BTreeSet::new();
+                self.all(items, names, start, &mut vec![false; items.len()], depth, &mut out, false);
+                out
+            },
+            Term::GroupRef(name) => match self.group(name, depth) {
+                Some(g) => self.ends(g, names, start, depth + 1),
+                None => BTreeSet::new(),
+            },
+        }
+    }
+
+    fn term_prefixes(&self, term: &Term, names: &[&str], start: usize, depth: usize) -> BTreeSet<usize> {
+        match term {
+            Term::Seq(items) => {
+                let mut out = BTreeSet::new();
+                let mut positions: BTreeSet<usize> = Some(start).into_iter().collect();
+                for item in items {
+                    for s in &positions {
+                        out.extend(self.prefixes(item, names, *s, depth));
+                    }
+                    positions = positions.iter().flat_map(|s| self.ends(item, names, *s, depth)).collect();
+                }
+                out
+            },
+            Term::Choice(items) => items.iter().flat_map(|i| self.prefixes(i, names, start, depth)).collect(),
+            Term::All(items) => {
+                let mut out = BTreeSet::new();
+                self.all(items, names, start, &mut vec![false; items.len()], depth, &mut out, true);
+                out
+            },
+            Term::GroupRef(name) => match self.group(name, depth) {
+                Some(g) => self.prefixes(g, names, start, depth + 1),
+                None => Some(start).into_iter().collect(),
+            },
+            _ => {
+                let mut out = self.term_ends(term, names, start, depth);
+                out.insert(start);
+                out
+            },
+        }
+    }
+
+    /// Tries the items of an `all` in every order. With `partial` every reachable position is recorded,
+    /// otherwise only those where no required item is left.
+    #[allow(clippy::too_many_arguments)]
+    fn all(&self, items: &[Particle], names: &[&str], pos: usize, used: &mut Vec<bool>, depth: usize, out: &mut BTreeSet<usize>, partial: bool) {
+        let done = items.iter().zip(used.iter()).all(|(item, used)| *used || item.min == 0);
+        if partial || done {
+            out.insert(pos);
+        }
+        for i in 0..items.len() {
+            if used[i] {
+                continue;
+            }
+            let one = Particle{min: 1, ..items[i].clone()};
+            used[i] = true;
+            for end in self.ends(&one, names, pos, depth) {
+                if end > pos {
+                    self.all(items, names, end, used, depth, out, partial);
+                }
+            }
+            used[i] = false;
+        }
+    }
+
+    fn name_end(&self, name: &str, names: &[&str], start: usize) -> BTreeSet<usize> {
+        let mut out = BTreeSet::new();
+        if names.get(start) == Some(&name) {
+            out.insert(start + 1);
+        }
+        out
+    }
+
+    /// The element names that appear in a content model, used to suggest what was expected
+    fn collect_names(&self, term: &Term, out: &mut Vec<String>, depth: usize) {
+        match term {
+            Term::Element(decl) => out.push(decl.name.clone()),
+            Term::ElementRef(name) => out.push(name.clone()),
+            Term::Seq(items) | Term::Choice(items) | Term::All(items) => {
+                for i in items {
+                    self.collect_names(&i.term, out, depth);
+                }
+            },
+            Term::GroupRef(name) => if let Some(g) = self.group(name, depth) {
+                self.collect_names(&g.term, out, depth + 1);
+            },
+            Term::Any => {},
+        }
+    }
+
+    /// The declaration a child of the given name is validated against. Within one content model
+    /// all elements of the same name must have the same type, so the first one found is used.
+    fn decl_for(&self, p: &Particle, name: &str, depth: usize) -> Option<ElementDecl> {
+        match &p.term {
+            Term::Element(decl) if decl.name == name => Some(decl.clone()),
+            Term::ElementRef(r) if r == name => self.schema.elements.get(r).cloned(),
+            Term::Seq(items) | Term::Choice(items) | Term::All(items) => items.iter().find_map(|i| self.decl_for(i, name, depth)),
+            Term::GroupRef(g) => self.group(g, depth).and_then(|g| self.decl_for(g, name, depth + 1)),
+            // wildcards are validated laxly, against a global declaration if there is one
+            Term::Any => self.schema.elements.get(name).cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// The part of a schema describing one element type, as generated for `#[xml_element("name", schema)]` structs by
+/// `simple_xml_serialize_macro`. `assemble` gathers a fragment and those of its children into a schema document.
+#[derive(Clone,Debug)]
+pub struct XsdFragment {
+    /// The name the element is written with
+    pub name: String,
+    /// The name of the complex type declared for the element, IE the name of the struct
+    pub type_name: String,
+    pub attributes: Vec<XsdAttribute>,
+    /// Child elements, in the order they are written
+    pub children: Vec<XsdChild>,
+    /// The built-in type of the element's text, IE `xs:string`, if it has any
+    pub text: Option<String>,
+}
+
+/// An attribute of an `XsdFragment`
+#[derive(Clone,PartialEq,Debug)]
+pub struct XsdAttribute {
+    pub name: String,
+    /// A built-in type, IE `xs:int`
+    pub type_name: String,
+    pub required: bool,
+}
+
+/// A child element of an `XsdFragment`
+#[derive(Clone,Debug)]
+pub struct XsdChild {
+    /// The name the child is written with, or `None` for the name in its own fragment
+    pub name: Option<String>,
+    /// Gives the fragment of the child's type
+    pub schema: fn() -> XsdFragment,
+    pub min_occurs: usize,
+    /// `None` for `maxOccurs="unbounded"`
+    pub max_occurs: Option<usize>,
+}
+
+impl XsdFragment {
+    /// Starts a fragment with no attributes, children or text
+    /// # Arguments
+    ///
+    /// * `name` - The name the element is written with
+    /// * `type_name` - The name of the complex type declared for it
+    pub fn new(name: &str, type_name: &str) -> Self {
+        XsdFragment{
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+            text: None,
+        }
+    }
+
+    /// Adds an attribute of a built-in type
+    pub fn attribute(mut self, name: &str, type_name: &str, required: bool) -> Self {
+        self.attributes.push(XsdAttribute{name: name.to_string(), type_name: type_name.to_string(), required});
+        self
+    }
+
+    /// Adds a child element
+    /// # Arguments
+    ///
+    /// * `name` - The name the child is written with, or `None` for the name in its own fragment
+    /// * `schema` - Gives the fragment of the child's type
+    /// * `min_occurs` - How many times the child must appear
+    /// * `max_occurs` - How many times the child may appear, or `None` for any number
+    pub fn child(mut self, name: Option<&str>, schema: fn() -> XsdFragment, min_occurs: usize, max_occurs: Option<usize>) -> Self {
+        self.children.push(XsdChild{name: name.map(String::from), schema, min_occurs, max_occurs});
+        self
+    }
+
+    /// Gives the element text of a built-in type
+    pub fn text(mut self, type_name: &str) -> Self {
+        self.text = Some(type_name.to_string());
+        self
+    }
+
+    /// Adds the attributes, children and text of another fragment, for a struct flattened into this one
+    /// # Arguments
+    ///
+    /// * `other` - The fragment of the flattened struct. Its name and type name are not used.
+    pub fn flatten(mut self, other: XsdFragment) -> Self {
+        self.attributes.extend(other.attributes);
+        self.children.extend(other.children);
+        self.text = self.text.or(other.text);
+        self
+    }
+}
+
+/// Assembles a schema document declaring each of `roots` as a global element, along with a complex type for every
+/// fragment reachable from them. Types are told apart by their names, so the first fragment seen with a name is used.
+/// # Arguments
+///
+/// * `roots` - The fragments of the elements documents may start with
+///
+/// # Example
+///
+/// ```
+/// use simple_xml_serialize::XMLElement;
+/// use simple_xml_serialize::xsd::{assemble, Schema, XsdFragment};
+///
+/// fn item() -> XsdFragment {
+///     XsdFragment::new("item", "Item").attribute("sku", "xs:string", true).text("xs:int")
+/// }
+/// let order = XsdFragment::new("order", "Order").child(None, item, 0, None);
+///
+/// let xsd = assemble(&[order]).to_string_pretty_prolog("\n", "  ");
+/// let schema = Schema::parse(&xsd).unwrap();
+/// let ele = XMLElement::new("order").element(XMLElement::new("item").attr("sku", "a").text("two"));
+/// assert_eq!(schema.validate(&ele).unwrap_err()[0].to_string(),
+///            "/order/item: value `two` of element `item` is not a valid int");
+/// ```
+pub fn assemble(roots: &[XsdFragment]) -> XMLElement {
+    let mut schema = XMLElement::new("xs:schema")
+        .attr("xmlns:xs", XSD_NAMESPACE)
+        .attr("elementFormDefault", "qualified");
+    for root in roots {
+        schema.add_element(XMLElement::new("xs:element").attr("name", &root.name).attr("type", &root.type_name));
+    }
+
+    let mut declared = HashSet::new();
+    let mut pending: Vec<XsdFragment> = roots.iter().rev().cloned().collect();
+    while let Some(fragment) = pending.pop() {
+        if !declared.insert(fragment.type_name.clone()) {
+            continue;
+        }
+        let children: Vec<(XsdChild, XsdFragment)> = fragment.children.iter().map(|c| (c.clone(), (c.schema)())).collect();
+        schema.add_element(complex_type(&fragment, &children));
+        pending.extend(children.into_iter().rev().map(|(_, f)| f));
+    }
+    schema
+}
+
+fn complex_type(fragment: &XsdFragment, children: &[(XsdChild, XsdFragment)]) -> XMLElement {
+    let mut complex = XMLElement::new("xs:complexType").attr("name", &fragment.type_name);
+    let attributes: Vec<XMLElement> = fragment.attributes.iter().map(|a| {
+        XMLElement::new("xs:attribute")
+            .attr("name", &a.name)
+            .attr("type", &a.type_name)
+            .attr("use", if a.required { "required" } else { "optional" })
+    }).collect();
+
+    match (&fragment.text, children.is_empty()) {
+        (Some(text), true) => {
+            let extension = XMLElement::new("xs:extension").attr("base", text).elements(attributes);
+            return complex.element(XMLElement::new("xs:simpleContent").element(extension));
+        },
+        // text can only be typed when there are no children
+        (Some(_), false) => complex.add_attr("mixed", "true"),
+        (None, _) => {},
+    }
+    if !children.is_empty() {
+        let mut sequence = XMLElement::new("xs:sequence");
+        for (child, child_fragment) in children {
+            let mut element = XMLElement::new("xs:element")
+                .attr("name", child.name.as_ref().unwrap_or(&child_fragment.name))
+                .attr("type", &child_fragment.type_name);
+            if child.min_occurs != 1 {
+                element.add_attr("minOccurs", child.min_occurs);
+            }
+            match child.max_occurs {
+                Some(1) => {},
+                Some(max) => element.add_attr("maxOccurs", max),
+                None => element.add_attr("maxOccurs", "unbounded"),
+            }
+            sequence.add_element(element);
+        }
+        complex.add_element(sequence);
+    }
+    complex.elements(attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(body: &str) -> Schema {
+        let text = format!("<xs:schema xmlns:xs=\"{}\">{}</xs:schema>", XSD_NAMESPACE, body);
+        Schema::parse(&text).unwrap()
+    }
+
+    fn errors(schema: &Schema, ele: &XMLElement) -> Vec<String> {
+        match schema.validate(ele) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    fn ele(name: &str, children: &[&str]) -> XMLElement {
+        let mut ele = XMLElement::new(name);
+        for c in children {
+            ele.add_element(XMLElement::new(c));
+        }
+        ele
+    }
+
+    #[test]
+    fn xsd_sequence_and_occurs() {
+        let s = schema(r#"
+            <xs:element name="list">
+              <xs:complexType>
+                <xs:sequence>
+                  <xs:element name="head" minOccurs="0"/>
+                  <xs:element name="item" minOccurs="2" maxOccurs="3"/>
+                  <xs:element name="tail"/>
+                </xs:sequence>
+              </xs:complexType>
+            </xs:element>"#);
+        assert!(errors(&s, &ele("list", &["head", "item", "item", "tail"])).is_empty());
+        assert!(errors(&s, &ele("list", &["item", "item", "item", "tail"])).is_empty());
+        assert_eq!(errors(&s, &ele("list", &["item", "tail"])), vec!["/list/tail: element `tail` is not expected here, expected `item`"]);
+        assert_eq!(errors(&s, &ele("list", &["item", "item", "item", "item"])), vec!["/list/item[4]: element `item` is not expected here, expected `tail`"]);
+        assert_eq!(errors(&s, &ele("list", &["item", "item"])), vec!["/list: element `list` is incomplete, expected `item` or `tail`"]);
+        assert_eq!(errors(&s, &ele("list", &["item", "item", "tail"]).text("loose")), vec!["/list: text is not allowed in element `list`"]);
+        assert_eq!(errors(&s, &ele("other", &[])), vec!["/other: element `other` is not declared in the schema"]);
+    }
+
+    #[test]
+    fn xsd_choice_all_and_groups() {
+        let s = schema(r#"
+            <xs:group name="contact">
+              <xs:choice>
+                <xs:element name="email" type="xs:string"/>
+                <xs:element name="phone" type="xs:string" maxOccurs="unbounded"/>
+              </xs:choice>
+            </xs:group>
+            <xs:element name="person">
+              <xs:complexType>
+                <xs:sequence>
+                  <xs:element ref="name"/>
+                  <xs:group ref="contact"/>
+                  <xs:element name="extra">
+                    <xs:complexType>
+                      <xs:all>
+                        <xs:element name="a"/>
+                        <xs:element name="b" minOccurs="0"/>
+                      </xs:all>
+                    </xs:complexType>
+                  </xs:element>
+                </xs:sequence>
+              </xs:complexType>
+            </xs:element>
+            <xs:element name="name" type="xs:token"/>"#);
+        let mut person = ele("person", &["name", "phone", "phone"]);
+        person.add_element(ele("extra", &["b", "a"]));
+        assert!(errors(&s, &person).is_empty());
+
+        let mut person = ele("person", &["name", "email", "phone"]);
+        person.add_element(ele("extra", &["b", "b"]));
+        assert_eq!(errors(&s, &person), vec![
+            "/person/phone: element `phone` is not expected here, expected `extra`",
+            "/person/extra/b[2]: element `b` is not expected here, expected `a`",
+        ]);
+    }
+
+    #[test]
+    fn xsd_attributes_and_derivation() {
+        let s = schema(r#"
+            <xs:attributeGroup name="common">
+              <xs:attribute name="id" type="xs:ID" use="required"/>
+            </xs:attributeGroup>
+            <xs:complexType name="base">
+              <xs:sequence><xs:element name="a"/></xs:sequence>
+              <xs:attributeGroup ref="common"/>
+            </xs:complexType>
+            <xs:complexType name="derived">
+              <xs:complexContent>
+                <xs:extension base="base">
+                  <xs:sequence><xs:element name="b" type="xs:int"/></xs:sequence>
+                  <xs:attribute name="version" type="xs:string" fixed="2"/>
+                </xs:extension>
+              </xs:complexContent>
+            </xs:complexType>
+            <xs:complexType name="price">
+              <xs:simpleContent>
+                <xs:extension base="xs:decimal">
+                  <xs:attribute name="currency" use="required">
+                    <xs:simpleType>
+                      <xs:restriction base="xs:string">
+                        <xs:enumeration value="EUR"/>
+                        <xs:enumeration value="USD"/>
+                      </xs:restriction>
+                    </xs:simpleType>
+                  </xs:attribute>
+                </xs:extension>
+              </xs:simpleContent>
+            </xs:complexType>
+            <xs:element name="doc" type="derived"/>
+            <xs:element name="price" type="price"/>"#);
+        let doc = ele("doc", &["a"]).attr("id", "x1").attr("xmlns:xsi", "ignored")
+                    .element(XMLElement::new("b").text("12"));
+        assert!(errors(&s, &doc).is_empty());
+
+        let doc = ele("doc", &["a"]).attr("version", "3").attr("other", "")
+                    .element(XMLElement::new("b").text("twelve").attr("c", 1));
+        assert_eq!(errors(&s, &doc), vec![
+            "/doc: attribute `version` must have the value `2`",
+            "/doc: attribute `other` is not allowed",
+            "/doc: required attribute `id` is missing",
+            "/doc/b: attribute `c` is not allowed",
+            "/doc/b: value `twelve` of element `b` is not a valid int",
+        ]);
+
+        assert!(errors(&s, &XMLElement::new("price").attr("currency", "EUR").text("9.99")).is_empty());
+        assert_eq!(errors(&s, &XMLElement::new("price").attr("currency", "GBP").text("9.99").element(XMLElement::new("x"))), vec![
+            "/price: value `GBP` of attribute `currency` is not one of (EUR|USD)",
+            "/price: element `price` cannot contain elements",
+        ]);
+    }
+
+    #[test]
+    fn xsd_simple_types_and_facets() {
+        let s = schema(r#"
+            <xs:simpleType name="sku">
+              <xs:restriction base="xs:string">
+                <xs:pattern value="[A-Z]{3}-\d+"/>
+                <xs:maxLength value="6"/>
+              </xs:restriction>
+            </xs:simpleType>
+            <xs:simpleType name="percent">
+              <xs:restriction base="xs:decimal">
+                <xs:minInclusive value="0"/>
+                <xs:maxExclusive value="100"/>
+                <xs:fractionDigits value="1"/>
+              </xs:restriction>
+            </xs:simpleType>
+            <xs:simpleType name="sizes">
+              <xs:restriction>
+                <xs:simpleType><xs:list itemType="xs:positiveInteger"/></xs:simpleType>
+                <xs:length value="2"/>
+              </xs:restriction>
+            </xs:simpleType>
+            <xs:simpleType name="flexible">
+              <xs:union memberTypes="xs:date xs:boolean"/>
+            </xs:simpleType>
+            <xs:element name="sku" type="sku"/>
+            <xs:element name="percent" type="percent"/>
+            <xs:element name="sizes" type="sizes"/>
+            <xs:element name="flexible" type="flexible"/>"#);
+        let check = |name: &str, value: &str| errors(&s, &XMLElement::new(name).text(value));
+        assert!(check("sku", "ABC-12").is_empty());
+        assert_eq!(check("sku", "AB-12"), vec!["/sku: value `AB-12` of element `sku` does not match pattern `[A-Z]{3}-\\d+`"]);
+        assert_eq!(check("sku", "ABC-123"), vec!["/sku: value `ABC-123` of element `sku` must have a length of at most 6"]);
+        assert!(check("percent", " 99.5 ").is_empty());
+        assert_eq!(check("percent", "100"), vec!["/percent: value `100` of element `percent` must be less than 100"]);
+        assert_eq!(check("percent", "-1"), vec!["/percent: value `-1` of element `percent` must be at least 0"]);
+        assert_eq!(check("percent", "1.25"), vec!["/percent: value `1.25` of element `percent` must have at most 1 fraction digits"]);
+        assert!(check("sizes", "3 4").is_empty());
+        assert_eq!(check("sizes", "3 4 5"), vec!["/sizes: value `3 4 5` of element `sizes` must have length 2"]);
+        assert_eq!(check("sizes", "3 0"), vec!["/sizes: value `3 0` of element `sizes` has item `0` which is not a valid positiveInteger"]);
+        assert!(check("flexible", "2024-02-29").is_empty());
+        assert!(check("flexible", "true").is_empty());
+        assert_eq!(check("flexible", "yes"), vec!["/flexible: value `yes` of element `flexible` does not match any member of the union"]);
+    }
+
+    #[test]
+    fn xsd_builtins() {
+        let valid = [
+            ("boolean", "1"), ("int", "-2147483648"), ("unsignedByte", "255"), ("double", "-1.5E3"), ("float", "INF"),
+            ("dateTime", "2024-01-31T23:59:59.5Z"), ("time", "12:00:00+01:00"), ("duration", "P1Y2MT3.5S"),
+            ("gMonthDay", "--12-25"), ("NCName", "a-b.c"), ("QName", "xs:int"), ("language", "en-GB"),
+            ("hexBinary", "0aFF"), ("base64Binary", "aGk="), ("NMTOKENS", "a b"),
+        ];
+        for (t, v) in valid.iter() {
+            assert_eq!(check_builtin(t, v), Ok(()), "{} {}", t, v);
+        }
+        let invalid = [
+            ("boolean", "yes"), ("int", "2147483648"), ("unsignedByte", "-1"), ("double", "inf"), ("decimal", "1e3"),
+            ("date", "2024-13-01"), ("time", "25:00:00"), ("duration", "P"), ("duration", "P1DT"), ("NCName", "a:b"),
+            ("hexBinary", "abc"), ("base64Binary", "aGk"), ("normalizedString", "a\tb"),
+        ];
+        for (t, v) in invalid.iter() {
+            assert!(check_builtin(t, v).is_err(), "{} {}", t, v);
+        }
+    }
+
+    #[test]
+    fn xsd_positions() {
+        let s = schema(r#"<xs:element name="a"><xs:complexType><xs:attribute name="n" type="xs:int"/></xs:complexType></xs:element>"#);
+        let (root, map) = parser::parse_with_positions("<a\n   n=\"x\"/>").unwrap();
+        let errors = s.validate_with_positions(&root, &map).unwrap_err();
+        assert_eq!(errors[0].to_string(), "/a: value `x` of attribute `n` is not a valid int at line 2, column 4");
+    }
+
+    #[test]
+    fn xsd_load_errors() {
+        let dir = std::env::temp_dir().join("sxs_xsd_load_errors");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("loop.xsd"), r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"><xs:include schemaLocation="loop.xsd"/><xs:element name="a"/></xs:schema>"#).unwrap();
+        fs::write(dir.join("broken.xsd"), "<xs:schema>").unwrap();
+        assert!(Schema::load(dir.join("loop.xsd")).unwrap().elements.contains_key("a"));
+
+        let err = Schema::load(dir.join("broken.xsd")).unwrap_err();
+        assert!(err.to_string().ends_with("broken.xsd: unclosed element `<xs:schema>` at line 1, column 1"));
+        assert!(Schema::load(dir.join("missing.xsd")).is_err());
+        assert!(Schema::parse("<a/>").is_err());
+        assert!(Schema::parse(r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"><xs:element name="a" type="xs:nope"/></xs:schema>"#).is_err());
+        assert!(Schema::parse(r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"><xs:simpleType name="s"><xs:restriction base="xs:string"><xs:pattern value="(a"/></xs:restriction></xs:simpleType></xs:schema>"#).is_err());
+    }
+
+    fn folder() -> XsdFragment {
+        XsdFragment::new("folder", "Folder")
+            .attribute("name", "xs:string", true)
+            .attribute("hidden", "xs:boolean", false)
+            .child(Some("sub"), folder, 0, None)
+            .child(None, note, 0, Some(1))
+    }
+
+    fn note() -> XsdFragment {
+        XsdFragment::new("note", "Note").text("xs:string")
+    }
+
+    #[test]
+    fn xsd_assemble() {
+        let xsd = assemble(&[folder()]);
+        assert_eq!(xsd.to_string(), concat!(
+            r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema" elementFormDefault="qualified">"#,
+            r#"<xs:element name="folder" type="Folder"/>"#,
+            r#"<xs:complexType name="Folder"><xs:sequence>"#,
+            r#"<xs:element name="sub" type="Folder" minOccurs="0" maxOccurs="unbounded"/>"#,
+            r#"<xs:element name="note" type="Note" minOccurs="0"/></xs:sequence>"#,
+            r#"<xs:attribute name="name" type="xs:string" use="required"/>"#,
+            r#"<xs:attribute name="hidden" type="xs:boolean" use="optional"/></xs:complexType>"#,
+            r#"<xs:complexType name="Note"><xs:simpleContent><xs:extension base="xs:string"/></xs:simpleContent></xs:complexType>"#,
+            r#"</xs:schema>"#));
+
+        let s = Schema::parse(&xsd.to_string()).unwrap();
+        let valid = XMLElement::new("folder").attr("name", "a")
+            .element(XMLElement::new("sub").attr("name", "b").attr("hidden", true))
+            .element(XMLElement::new("note").text("hi"));
+        assert_eq!(errors(&s, &valid), Vec::<String>::new());
+        let invalid = XMLElement::new("folder").attr("hidden", "yes").element(XMLElement::new("note")).element(XMLElement::new("note"));
+        assert_eq!(errors(&s, &invalid), vec![
+            "/folder: value `yes` of attribute `hidden` is not a valid boolean",
+            "/folder: required attribute `name` is missing",
+            "/folder/note[2]: element `note` is not expected here",
+        ]);
+    }
+}
\ No newline at end of file